@@ -0,0 +1,135 @@
+//! Launching Bonnie when the user logs in.
+//!
+//! - Linux: an XDG autostart `.desktop` entry.
+//! - macOS: a LaunchAgent plist.
+//! - Windows: a value under the current user's `Run` registry key.
+//!
+//! Installing overwrites any existing entry and uninstalling a missing one is
+//! fine, so both can be run any number of times.
+
+use std::{env, io, path::PathBuf};
+
+#[cfg(target_os = "linux")]
+fn entry_path() -> io::Result<PathBuf> {
+    dirs::config_dir()
+        .map(|dir| dir.join("autostart/bonnie-buddy.desktop"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))
+}
+
+#[cfg(target_os = "macos")]
+fn entry_path() -> io::Result<PathBuf> {
+    dirs::home_dir()
+        .map(|dir| dir.join("Library/LaunchAgents/bonnie.buddy.plist"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory"))
+}
+
+#[cfg(target_os = "linux")]
+fn entry_contents(exe: &str) -> String {
+    format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=Bonnie Buddy\n\
+         Exec=\"{exe}\"\n\
+         X-GNOME-Autostart-enabled=true\n"
+    )
+}
+
+#[cfg(target_os = "macos")]
+fn entry_contents(exe: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>bonnie.buddy</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{exe}</string>
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+</dict>
+</plist>
+"#
+    )
+}
+
+/// Registers Bonnie to start at login, returning where the entry went.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub fn install() -> io::Result<String> {
+    let exe = env::current_exe()?;
+    let path = entry_path()?;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, entry_contents(&exe.to_string_lossy()))?;
+
+    Ok(path.display().to_string())
+}
+
+/// Stops Bonnie starting at login, returning where the entry was.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub fn uninstall() -> io::Result<String> {
+    let path = entry_path()?;
+
+    match std::fs::remove_file(&path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(path.display().to_string()),
+    }
+}
+
+#[cfg(target_os = "windows")]
+const RUN_KEY: &str = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run";
+#[cfg(target_os = "windows")]
+const RUN_VALUE: &str = "Bonnie Buddy";
+
+#[cfg(target_os = "windows")]
+fn reg(args: &[&str]) -> io::Result<bool> {
+    std::process::Command::new("reg")
+        .args(args)
+        .output()
+        .map(|output| output.status.success())
+}
+
+/// Registers Bonnie to start at login, returning where the entry went.
+#[cfg(target_os = "windows")]
+pub fn install() -> io::Result<String> {
+    let exe: PathBuf = env::current_exe()?;
+    let command = format!("\"{}\"", exe.display());
+
+    if reg(&[
+        "add", RUN_KEY, "/v", RUN_VALUE, "/t", "REG_SZ", "/d", &command, "/f",
+    ])? {
+        Ok(format!(r"{RUN_KEY}\{RUN_VALUE}"))
+    } else {
+        Err(io::Error::other("reg add failed"))
+    }
+}
+
+/// Stops Bonnie starting at login, returning where the entry was.
+#[cfg(target_os = "windows")]
+pub fn uninstall() -> io::Result<String> {
+    // deleting a value that isn't there fails, so check first
+    if reg(&["query", RUN_KEY, "/v", RUN_VALUE])?
+        && !reg(&["delete", RUN_KEY, "/v", RUN_VALUE, "/f"])?
+    {
+        return Err(io::Error::other("reg delete failed"));
+    }
+
+    Ok(format!(r"{RUN_KEY}\{RUN_VALUE}"))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub fn install() -> io::Result<String> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "autostart isn't supported on this platform",
+    ))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub fn uninstall() -> io::Result<String> {
+    install()
+}
@@ -1,33 +1,225 @@
-use crate::plugins::bonnie_state::BonnieState;
-use bevy::prelude::*;
+use std::time::Duration;
+
+use crate::plugins::bonnie_state::{BonnieState, BonnieTransition, bonnie_layer};
+use bevy::{prelude::*, render::camera::RenderTarget, utils::HashMap, window::WindowRef};
+
+/// Where her feet are on her window, from its top left, so where she stands
+/// when walking somewhere.
+pub const FEET_OFFSET: IVec2 = IVec2::new(90, 147);
 
 #[derive(Component, Default)]
+#[require(FractionalPosition, ChaseVelocity)]
 pub struct Bonnie {
     pub state: BonnieState,
+    /// Which Bonnie this is, used to keep each one's render layers apart.
+    pub index: usize,
+}
+
+/// A Bonnie to spawn, by default idling wherever her window opens with two
+/// seconds before she picks something else to do.
+#[derive(Debug, Clone)]
+pub struct BonnieBuilder {
+    index: usize,
+    state: BonnieState,
+    position: Option<IVec2>,
+    timer: Duration,
+}
+
+impl BonnieBuilder {
+    pub fn new(index: usize) -> Self {
+        Self {
+            index,
+            state: BonnieState::Idle,
+            position: None,
+            timer: Duration::from_secs_f32(2.0),
+        }
+    }
+
+    /// Starts her off in `state` rather than idling.
+    pub fn state(mut self, state: BonnieState) -> Self {
+        self.state = state;
+        self
+    }
+
+    /// Puts her window's top left at `position` on the desktop.
+    pub fn at(mut self, position: IVec2) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    /// How long her first state lasts.
+    pub fn timer(mut self, duration: Duration) -> Self {
+        self.timer = duration;
+        self
+    }
+
+    /// Spawns her showing `sprite` in `window`, with a camera drawing her
+    /// layer into it.
+    pub fn spawn(self, commands: &mut Commands, window: Entity, sprite: Sprite) -> Entity {
+        let layer = bonnie_layer(self.index);
+
+        commands.spawn((
+            Camera2d,
+            Camera {
+                target: RenderTarget::Window(WindowRef::Entity(window)),
+                ..default()
+            },
+            layer.clone(),
+        ));
+
+        if let Some(position) = self.position {
+            commands
+                .entity(window)
+                .entry::<Window>()
+                .and_modify(move |mut window| window.position = WindowPosition::At(position));
+        }
+
+        let bonnie = commands
+            .spawn((
+                Bonnie {
+                    index: self.index,
+                    state: self.state.clone(),
+                },
+                BonnieWindow(window),
+                Name::new(format!("Bonnie {}", self.index)),
+                StateMachine::new(Timer::new(self.timer, TimerMode::Once)),
+                sprite,
+                layer,
+            ))
+            .id();
+
+        // so the state sets itself up as if she'd just picked it
+        if self.state != BonnieState::Idle {
+            commands.send_event(BonnieTransition {
+                bonnie,
+                from: BonnieState::Idle,
+                to: self.state,
+            });
+        }
+
+        bonnie
+    }
+}
+
+/// Where a Bonnie's window would be without rounding to whole pixels, so slow
+/// or high framerate movement isn't lost to rounding every frame.
+#[derive(Component, Default, Debug, Clone, Copy)]
+pub struct FractionalPosition(pub Vec2);
+
+/// How fast a Bonnie is running after the cursor, so she speeds up and slows
+/// down rather than snapping to full speed.
+#[derive(Component, Default, Debug, Clone, Copy)]
+pub struct ChaseVelocity(pub Vec2);
+
+/// The window a Bonnie lives in.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct BonnieWindow(pub Entity);
+
+/// Why a state machine's being held in its current state. Each reason is
+/// lifted on its own, so a state finishing doesn't undo a pause.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockReason {
+    /// Paused from the keyboard.
+    UserPause,
+    /// The current state is in charge of when it ends, like teaching or chasing.
+    StateOwned,
+    ScreenLock,
+    Fullscreen,
+}
+
+impl BlockReason {
+    const ALL: [BlockReason; 4] = [
+        BlockReason::UserPause,
+        BlockReason::StateOwned,
+        BlockReason::ScreenLock,
+        BlockReason::Fullscreen,
+    ];
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
 }
 
 #[derive(Component, Debug)]
 pub struct StateMachine {
     pub timer: Timer,
-    pub can_change: bool,
+    /// Set of `BlockReason` bits currently holding it.
+    blocked: u8,
 }
 
 impl StateMachine {
+    pub fn new(timer: Timer) -> Self {
+        Self { timer, blocked: 0 }
+    }
+
+    /// Whether nothing's holding it, so it's free to pick a new state.
+    pub fn can_change(&self) -> bool {
+        self.blocked == 0
+    }
+
+    pub fn block_for(&mut self, reason: BlockReason) {
+        self.blocked |= reason.bit();
+    }
+
+    pub fn unblock_for(&mut self, reason: BlockReason) {
+        self.blocked &= !reason.bit();
+    }
+
+    pub fn is_blocked_for(&self, reason: BlockReason) -> bool {
+        self.blocked & reason.bit() != 0
+    }
+
+    /// Everything currently holding it.
+    pub fn block_reasons(&self) -> impl Iterator<Item = BlockReason> + '_ {
+        BlockReason::ALL
+            .into_iter()
+            .filter(|reason| self.is_blocked_for(*reason))
+    }
+
+    /// Blocks for the current state, see `BlockReason::StateOwned`.
     pub fn block(&mut self) {
-        self.can_change = false;
+        self.block_for(BlockReason::StateOwned);
     }
 
     pub fn unblock(&mut self) {
-        self.can_change = true;
+        self.unblock_for(BlockReason::StateOwned);
     }
 
+    /// Ends the current state, leaving any pauses in place.
     pub fn finish(&mut self) {
-        self.can_change = true;
+        self.unblock();
         let remaining = self.timer.remaining();
         self.timer.tick(remaining);
     }
 
     pub fn toggle_block(&mut self) {
-        self.can_change = !self.can_change;
+        if self.is_blocked_for(BlockReason::StateOwned) {
+            self.unblock();
+        } else {
+            self.block();
+        }
+    }
+}
+
+/// A copy of every Bonnie's [`StateMachine`], refreshed each frame, for
+/// anything that only shows it. The component's still what counts.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct StateTimers(pub HashMap<Entity, StateTimer>);
+
+/// Where one Bonnie's state machine was at the end of the last frame.
+#[derive(Debug, Clone, Default)]
+pub struct StateTimer {
+    /// How long until she picks something else, once nothing's blocking her.
+    pub remaining: Duration,
+    /// Everything holding her in her current state.
+    pub blocked_by: Vec<BlockReason>,
+}
+
+impl From<&StateMachine> for StateTimer {
+    fn from(machine: &StateMachine) -> Self {
+        Self {
+            remaining: machine.timer.remaining(),
+            blocked_by: machine.block_reasons().collect(),
+        }
     }
 }
@@ -0,0 +1,145 @@
+//! Command line options.
+
+use std::path::PathBuf;
+
+use bevy::{log::Level, prelude::*};
+use clap::{Parser, Subcommand};
+
+use crate::{
+    edges::EdgeBehaviour, plugins::bonnie_state::BonnieStateDiscriminants, profile::Profile,
+    stacking::Stacking,
+};
+
+#[derive(Parser, Resource, Debug, Clone)]
+#[command(version, about = "A desktop pet Bonnie.")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Config file to read instead of the default one.
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// How many Bonnies to spawn.
+    #[arg(long, default_value_t = 1)]
+    pub count: usize,
+
+    /// Show tips as text in a speech bubble rather than as images.
+    #[arg(long)]
+    pub text_tips: bool,
+
+    /// Locale to show tips in, defaults to the system locale.
+    #[arg(long)]
+    pub locale: Option<String>,
+
+    /// Behaviour preset to start with.
+    #[arg(long, value_enum, default_value_t)]
+    pub profile: Profile,
+
+    /// How lively Bonnie is from 0.0 (sleepy) to 1.0 (hyper).
+    #[arg(long, default_value_t = 0.5)]
+    pub energy: f32,
+
+    /// State Bonnie starts in, like meowing or bird, rather than idling.
+    #[arg(long, value_name = "STATE")]
+    pub start_state: Option<BonnieStateDiscriminants>,
+
+    /// Where the first Bonnie's window starts, as x,y from the top left.
+    #[arg(long, value_name = "X,Y", value_parser = parse_position)]
+    pub start_at: Option<IVec2>,
+
+    /// Seconds before Bonnie first moves on to something else.
+    #[arg(long, value_name = "SECONDS", default_value_t = 2.0)]
+    pub start_after: f32,
+
+    /// Let Bonnie fall to the bottom of the screen.
+    #[arg(long)]
+    pub gravity: bool,
+
+    /// What Bonnie does at the edges of the screen.
+    #[arg(long, value_enum, default_value_t)]
+    pub edges: EdgeBehaviour,
+
+    /// Whether Bonnie stays above, among or below other windows, overriding
+    /// the config file.
+    #[arg(long, value_enum)]
+    pub stacking: Option<Stacking>,
+
+    /// How many seconds ahead of a moving cursor Bonnie aims when chasing it.
+    #[arg(long, default_value_t = 0.15)]
+    pub chase_lead: f32,
+
+    /// Have Bonnie remind you to take a break after this many minutes of work.
+    #[arg(long, value_name = "MINUTES")]
+    pub pomodoro: Option<f32>,
+
+    /// How many minutes each pomodoro break lasts.
+    #[arg(long, value_name = "MINUTES", default_value_t = 5.0)]
+    pub pomodoro_break: f32,
+
+    /// Show a red dot where Bonnie thinks the cursor is.
+    #[arg(long)]
+    pub debug_cursor: bool,
+
+    /// Keep chasing and waking up on the cursor even if it can't be found at startup.
+    #[arg(long)]
+    pub assume_cursor: bool,
+
+    /// Write Bonnie's states and positions to this file as she goes.
+    #[arg(long, value_name = "PATH", conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+
+    /// Play back a file written by --record instead of picking states at random.
+    #[arg(long, value_name = "PATH")]
+    pub replay: Option<PathBuf>,
+
+    /// Folder of sprites to use instead of the bundled ones, any it's missing
+    /// come from the bundled ones.
+    #[arg(long, value_name = "DIR")]
+    pub skin: Option<PathBuf>,
+
+    /// Don't play any sound, or even open an audio device.
+    #[arg(long)]
+    pub no_audio: bool,
+
+    /// Reload sprites as they're edited on disk, for working on Bonnie's art.
+    /// Only works when built with `--features watch-assets`.
+    #[arg(long)]
+    pub watch_assets: bool,
+
+    /// Most verbose level to log: trace, debug, info, warn or error.
+    #[arg(long, default_value_t = Level::INFO)]
+    pub log_level: Level,
+
+    /// Start Bonnie when you log in, then exit.
+    #[arg(long, conflicts_with = "uninstall_autostart")]
+    pub install_autostart: bool,
+
+    /// Stop Bonnie starting when you log in, then exit.
+    #[arg(long)]
+    pub uninstall_autostart: bool,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Send a command to the running Bonnie, like `meow` or `state walking`,
+    /// then exit.
+    Ctl {
+        #[arg(required = true, trailing_var_arg = true)]
+        command: Vec<String>,
+    },
+}
+
+fn parse_position(position: &str) -> Result<IVec2, String> {
+    let (x, y) = position
+        .split_once(',')
+        .ok_or_else(|| format!("expected x,y, got {:?}", position))?;
+
+    let parse = |n: &str| {
+        n.trim()
+            .parse::<i32>()
+            .map_err(|err| format!("invalid coordinate {:?}: {}", n, err))
+    };
+
+    Ok(IVec2::new(parse(x)?, parse(y)?))
+}
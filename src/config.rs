@@ -0,0 +1,393 @@
+//! Settings read from the config file.
+//!
+//! It lives at `bonnie-buddy/config.toml` in the OS's config directory unless
+//! `--config` says otherwise, and everything in it is optional:
+//!
+//! ```toml
+//! # times Bonnie won't meow or munch, e.g. for meetings
+//! quiet_hours = ["09:00-09:30", "22:00-07:00"]
+//! # how fast she moves, 1.0 being normal
+//! speed_multiplier = 0.8
+//! # fade out and back in on another monitor rather than walking across
+//! fade_between_monitors = true
+//! # things she won't do, or list only the ones she will with enabled_states
+//! disabled_states = ["pooping", "bird"]
+//! # how many of her last states she avoids going back to straight away
+//! state_history = 3
+//! # where her window goes while patrolling, in pixels from the top left
+//! patrol_route = [[200, 200], [1400, 200], [1400, 700]]
+//! # "loop" back to the first waypoint or "ping-pong" back along the route
+//! patrol_mode = "ping-pong"
+//! # only change state while you're away from the mouse, or only while you're
+//! # there, rather than "always"
+//! transition_timing = "only-when-idle"
+//! # keep her wandering around part of the screen, like the bottom strip
+//! wander_region = { x = 0, y = 800, width = 1920, height = 280 }
+//! # the state the t key drops everything for
+//! force_state = "bird"
+//! # how far she strays from where she's leashed with the a key
+//! leash_radius = 300.0
+//! # how many birds she lets loose at once, up to 8, and how fast they fly
+//! bird_count = 3
+//! bird_speed = 0.7
+//! # pause her while a fullscreen app's in front, and hide her too
+//! pause_for_fullscreen = true
+//! hide_for_fullscreen = true
+//! # a drop shadow behind her windows, only drawn on macOS
+//! shadow = true
+//! # where her poops go from her window's top left, behind her feet if unset
+//! poop_offset = [30, 80]
+//! # save her poops, speech bubbles and such too when snapshotting her
+//! snapshot_windows = true
+//! # force a transparency mode if she shows up with a faint box around her:
+//! # "post_multiplied", "pre_multiplied", "opaque", "inherit" or "auto"
+//! composite_mode = "pre_multiplied"
+//! # "bottom" keeps her on the desktop behind every window, "top" by default
+//! stacking = "bottom"
+//! # how see-through she is, from 0.0 to 1.0
+//! opacity = 0.7
+//! # head for the nearest screen edge, or corner, to idle there
+//! dock = true
+//! dock_corners = true
+//! # sprites she picks from at random when idling, a skin can add more
+//! idle_sprites = ["BonSleep.png", "BonNormal.png", "BonLoaf.png"]
+//! # when to use the sprites in assets/night, if there are any
+//! night_hours = "21:00-07:00"
+//! # get stressed when the CPU's busy, checked every few seconds
+//! react_to_cpu = true
+//! # notice typing, coming over to look at most every few minutes
+//! react_to_typing = true
+//! typing_cooldown = 5.0
+//! # minutes ignored before each step up in attention seeking, [] for never
+//! bored_after = [20.0, 45.0, 90.0]
+//! # battery percentage she warns you at, and whether to post a notification too
+//! low_battery = 15
+//! battery_notify = false
+//! # turn off every desktop notification, from breaks and the battery alike
+//! notifications = false
+//! # let her get hungry, fed with the f key
+//! hunger = true
+//! # listen to what's playing so she dances along, where the OS allows it
+//! dance_to_audio = true
+//! # seconds between saving her stats, mood and hunger, 0.0 for only on exit
+//! autosave_interval = 300.0
+//! # a folder of reskinned sprites like BonNormal.png, overridden by --skin
+//! skin = "/home/me/bonnie-skins/tabby"
+//!
+//! # rebinding keys, see the controls for every action
+//! [keys]
+//! quit = "F10"
+//! summon = "j"
+//! pause = "Pause"
+//! force_state = "F9"
+//!
+//! # how fast each thing moves, as a share of the screen's diagonal a second
+//! # times screen_fraction, before speed_multiplier
+//! [speeds]
+//! screen_fraction = 0.15
+//! chasing = 2.5
+//! bird = 1.0
+//!
+//! # a sound played as she goes into a state, from the assets folder
+//! [state_sounds]
+//! walking = "chirp.ogg"
+//! pooping = "plop.ogg"
+//!
+//! # kinds of bird, one picked at random for each, just the usual one if unset
+//! [[bird_types]]
+//! sprite = "Bird.png"
+//! size = [55, 55]
+//!
+//! [[bird_types]]
+//! sprite = "Crow.png"
+//! size = [80, 60]
+//! speed = 0.6
+//! ```
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::{Res, Resource, warn};
+use chrono::NaiveTime;
+use serde::Deserialize;
+
+use crate::{
+    plugins::{
+        birds::BirdType,
+        bonnie_state::{BonnieStateDiscriminants, PatrolMode, SpeedConfig, TransitionTiming},
+        compositor::CompositeMode,
+        control::{Action, Key},
+    },
+    stacking::Stacking,
+};
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Config {
+    /// Times of day Bonnie keeps quiet.
+    pub quiet_hours: Vec<TimeRange>,
+    /// Scales how fast everything moves.
+    pub speed_multiplier: f32,
+    /// Whether she hops between monitors rather than walking across.
+    pub fade_between_monitors: bool,
+    /// The only states she picks from, all of them if unset.
+    pub enabled_states: Option<Vec<BonnieStateDiscriminants>>,
+    /// States she never picks. Idle can't be turned off.
+    pub disabled_states: Vec<BonnieStateDiscriminants>,
+    /// How many recent states she avoids picking again.
+    pub state_history: usize,
+    /// Waypoints she patrols between, she won't patrol without any.
+    pub patrol_route: Vec<[i32; 2]>,
+    pub patrol_mode: PatrolMode,
+    /// When she changes state on her own.
+    pub transition_timing: TransitionTiming,
+    /// State the force-state key sends her into.
+    pub force_state: BonnieStateDiscriminants,
+    /// Where she walks to, the whole monitor if unset.
+    pub wander_region: Option<Region>,
+    /// Furthest she gets from her leash anchor, in pixels.
+    pub leash_radius: f32,
+    /// How many birds fly at once.
+    pub bird_count: usize,
+    /// Scales how fast birds fly.
+    pub bird_speed: f32,
+    /// Kinds of bird to pick from.
+    pub bird_types: Vec<BirdType>,
+    /// Whether she pauses while a fullscreen app's focused.
+    pub pause_for_fullscreen: bool,
+    /// Whether she hides while a fullscreen app's focused.
+    pub hide_for_fullscreen: bool,
+    /// Whether her windows have a drop shadow.
+    pub shadow: bool,
+    /// Where poops go relative to her window, in pixels.
+    pub poop_offset: Option<[i32; 2]>,
+    /// Whether snapshots include her other windows.
+    pub snapshot_windows: bool,
+    /// How her windows' transparency is blended with the desktop, picked
+    /// automatically if unset.
+    pub composite_mode: Option<CompositeMode>,
+    /// Whether she stays above, among or below other windows, overridden by
+    /// --stacking.
+    pub stacking: Option<Stacking>,
+    /// How opaque she is, from 0.0 to 1.0.
+    pub opacity: f32,
+    /// Whether she docks against the nearest edge when idle.
+    pub dock: bool,
+    /// Whether she can dock in corners too.
+    pub dock_corners: bool,
+    /// Sprites she picks between when idling.
+    pub idle_sprites: Vec<String>,
+    /// When she swaps to her night sprites, never unless set.
+    pub night_hours: Option<TimeRange>,
+    /// Whether she reacts to high CPU usage.
+    pub react_to_cpu: bool,
+    /// Whether she reacts to typing.
+    pub react_to_typing: bool,
+    /// Fewest minutes between her coming over to see what you're typing.
+    pub typing_cooldown: f32,
+    /// Minutes without attention before she gets more bored, for each level.
+    pub bored_after: Vec<f32>,
+    /// Battery percentage she worries at.
+    pub low_battery: u8,
+    /// Whether a low battery posts a desktop notification.
+    pub battery_notify: bool,
+    /// Whether anything can post desktop notifications.
+    pub notifications: bool,
+    /// Whether she gets hungry and needs feeding.
+    pub hunger: bool,
+    /// Whether she captures the system's audio to dance to.
+    pub dance_to_audio: bool,
+    /// Seconds between saves of her state, only saving on exit if zero.
+    pub autosave_interval: f32,
+    /// Folder of sprites to use instead of the bundled ones.
+    pub skin: Option<PathBuf>,
+    /// Keys rebound from their defaults.
+    pub keys: HashMap<Action, Key>,
+    /// Sounds played on going into a state.
+    pub state_sounds: HashMap<BonnieStateDiscriminants, String>,
+    /// How fast each thing moves.
+    pub speeds: SpeedConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            quiet_hours: Vec::new(),
+            speed_multiplier: 1.0,
+            fade_between_monitors: false,
+            enabled_states: None,
+            disabled_states: Vec::new(),
+            state_history: 2,
+            patrol_route: Vec::new(),
+            patrol_mode: PatrolMode::default(),
+            transition_timing: TransitionTiming::default(),
+            force_state: BonnieStateDiscriminants::Meowing,
+            wander_region: None,
+            leash_radius: 300.0,
+            bird_count: 1,
+            bird_speed: 1.0,
+            bird_types: Vec::new(),
+            pause_for_fullscreen: true,
+            hide_for_fullscreen: false,
+            shadow: false,
+            poop_offset: None,
+            snapshot_windows: false,
+            composite_mode: None,
+            stacking: None,
+            opacity: 1.0,
+            dock: false,
+            dock_corners: false,
+            idle_sprites: vec!["BonSleep.png".to_string(), "BonNormal.png".to_string()],
+            night_hours: None,
+            react_to_cpu: false,
+            react_to_typing: true,
+            typing_cooldown: 5.0,
+            bored_after: vec![15.0, 30.0, 60.0],
+            low_battery: 15,
+            battery_notify: true,
+            notifications: true,
+            hunger: false,
+            dance_to_audio: false,
+            autosave_interval: 60.0,
+            skin: None,
+            keys: HashMap::new(),
+            state_sounds: HashMap::new(),
+            speeds: SpeedConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Where the config file is read from by default.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("bonnie-buddy/config.toml"))
+    }
+
+    /// Sets the patrol route in the config file at `path`, or the default one,
+    /// keeping the rest of its settings but not its comments.
+    pub fn save_patrol_route(path: Option<&Path>, route: &[[i32; 2]]) -> io::Result<PathBuf> {
+        let path = path
+            .map(Path::to_path_buf)
+            .or_else(Self::default_path)
+            .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "no config directory"))?;
+
+        let mut table = match fs::read_to_string(&path) {
+            Ok(contents) => contents.parse::<toml::Table>().map_err(io::Error::other)?,
+            Err(err) if err.kind() == ErrorKind::NotFound => toml::Table::new(),
+            Err(err) => return Err(err),
+        };
+        table.insert(
+            "patrol_route".into(),
+            toml::Value::try_from(route).map_err(io::Error::other)?,
+        );
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, table.to_string())?;
+
+        Ok(path)
+    }
+
+    /// Reads the config file at `path`, or the default one, falling back to
+    /// the defaults if it's missing or broken, along with anything wrong with
+    /// it to warn about once logging is up.
+    pub fn load(path: Option<&Path>) -> (Self, ConfigProblems) {
+        let mut problems = ConfigProblems::default();
+        let Some(path) = path.map(Path::to_path_buf).or_else(Self::default_path) else {
+            return (Self::default(), problems);
+        };
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => return (Self::default(), problems),
+            Err(err) => {
+                problems
+                    .0
+                    .push(format!("Couldn't read config {}: {}", path.display(), err));
+                return (Self::default(), problems);
+            }
+        };
+
+        let config: Self = toml::from_str(&contents).unwrap_or_else(|err| {
+            problems.0.push(format!(
+                "Ignoring invalid config {}: {}",
+                path.display(),
+                err
+            ));
+            Self::default()
+        });
+
+        if config
+            .disabled_states
+            .contains(&BonnieStateDiscriminants::Idle)
+        {
+            problems
+                .0
+                .push("Idle can't be disabled, she needs somewhere to fall back to.".into());
+        }
+
+        (config, problems)
+    }
+}
+
+/// What went wrong loading the config, kept until logging is set up.
+#[derive(Resource, Debug, Default)]
+pub struct ConfigProblems(Vec<String>);
+
+pub fn warn_config_problems(problems: Res<ConfigProblems>) {
+    for problem in &problems.0 {
+        warn!("{}", problem);
+    }
+}
+
+/// A rectangle on the monitor in pixels, from its top left.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// A time of day range like `22:00-07:00`, which can wrap past midnight.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub struct TimeRange {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl TimeRange {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+impl TryFrom<String> for TimeRange {
+    type Error = String;
+
+    fn try_from(range: String) -> Result<Self, Self::Error> {
+        let (start, end) = range
+            .split_once('-')
+            .ok_or_else(|| format!("expected a range like 09:00-17:00, got {:?}", range))?;
+
+        let parse = |time: &str| {
+            NaiveTime::parse_from_str(time.trim(), "%H:%M")
+                .map_err(|err| format!("invalid time {:?}: {}", time, err))
+        };
+
+        Ok(Self {
+            start: parse(start)?,
+            end: parse(end)?,
+        })
+    }
+}
@@ -0,0 +1,67 @@
+//! What happens at the edges of the monitor.
+
+use bevy::prelude::*;
+use clap::ValueEnum;
+
+/// Whether Bonnie stops at the edges of the monitor or bounces off them.
+#[derive(Resource, ValueEnum, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeBehaviour {
+    #[default]
+    Clamp,
+    Bounce,
+}
+
+/// Which way to head to get back inside `min..=max` from `position`, or zero if
+/// it's already inside.
+///
+/// Each axis is checked on its own, so past a corner both components point
+/// back inside.
+pub fn push_inward(position: IVec2, min: IVec2, max: IVec2) -> IVec2 {
+    let mut inward = IVec2::ZERO;
+
+    if position.x < min.x {
+        inward.x = 1;
+    } else if position.x > max.x {
+        inward.x = -1;
+    }
+
+    if position.y < min.y {
+        inward.y = 1;
+    } else if position.y > max.y {
+        inward.y = -1;
+    }
+
+    inward
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIN: IVec2 = IVec2::new(0, 0);
+    const MAX: IVec2 = IVec2::new(100, 50);
+
+    #[test]
+    fn past_a_corner_pushes_back_on_both_axes() {
+        let corners = [
+            (IVec2::new(-10, -10), IVec2::new(1, 1)),
+            (IVec2::new(110, -10), IVec2::new(-1, 1)),
+            (IVec2::new(-10, 60), IVec2::new(1, -1)),
+            (IVec2::new(110, 60), IVec2::new(-1, -1)),
+        ];
+
+        for (position, inward) in corners {
+            assert_eq!(push_inward(position, MIN, MAX), inward, "from {position}");
+        }
+    }
+
+    #[test]
+    fn only_pushes_on_the_axis_thats_out() {
+        assert_eq!(
+            push_inward(IVec2::new(110, 25), MIN, MAX),
+            IVec2::new(-1, 0)
+        );
+        assert_eq!(push_inward(IVec2::new(50, -5), MIN, MAX), IVec2::new(0, 1));
+        assert_eq!(push_inward(IVec2::new(50, 25), MIN, MAX), IVec2::ZERO);
+    }
+}
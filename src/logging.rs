@@ -0,0 +1,55 @@
+//! Writing logs to a file as well as the console.
+//!
+//! Logs go to `bonnie-buddy/logs/bonnie.log` under the OS's state or local
+//! data directory, rolling over to `bonnie.log.1` and so on once it gets big.
+
+use std::{path::PathBuf, sync::Mutex};
+
+use bevy::{
+    log::{BoxedLayer, tracing_subscriber::fmt},
+    prelude::*,
+};
+use rolling_file::{BasicRollingFileAppender, RollingConditionBasic};
+
+/// Size a log file can grow to before rolling over.
+const MAX_LOG_SIZE: u64 = 5 * 1024 * 1024;
+/// How many old log files are kept.
+const MAX_LOG_FILES: usize = 3;
+
+/// Where the log file lives, if the OS has somewhere for it.
+pub fn log_path() -> Option<PathBuf> {
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|dir| dir.join("bonnie-buddy/logs/bonnie.log"))
+}
+
+/// Extra layer for `LogPlugin` that also writes logs to the log file.
+pub fn file_layer(_app: &mut App) -> Option<BoxedLayer> {
+    let path = log_path()?;
+
+    let appender = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| {
+            // unbuffered, so nothing is lost if she crashes
+            BasicRollingFileAppender::new_with_buffer_capacity(
+                &path,
+                RollingConditionBasic::new().max_size(MAX_LOG_SIZE),
+                MAX_LOG_FILES,
+                0,
+            )
+        });
+
+    match appender {
+        Ok(appender) => Some(Box::new(
+            fmt::layer()
+                .with_ansi(false)
+                .with_writer(Mutex::new(appender)),
+        )),
+        Err(err) => {
+            // logging isn't set up yet
+            eprintln!("Couldn't open log file {}: {}", path.display(), err);
+            None
+        }
+    }
+}
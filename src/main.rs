@@ -1,25 +1,66 @@
 use std::time::Duration;
 
-use bevy::window::WindowLevel;
-use bevy::{prelude::*, window::CompositeAlphaMode};
+use bevy::audio::AudioPlugin;
+use bevy::log::LogPlugin;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use clap::Parser;
 
 mod plugins;
+use plugins::audio;
+use plugins::battery;
+use plugins::birds;
 use plugins::bonnie_state;
+use plugins::boredom;
+use plugins::chase;
+use plugins::compositor;
 use plugins::control;
+use plugins::cpu_load;
+use plugins::dance;
+use plugins::drag;
+use plugins::footsteps;
+use plugins::fullscreen;
 use plugins::global_cursor;
+use plugins::hunger;
+use plugins::idle;
+use plugins::ipc;
+use plugins::laser;
+use plugins::loopback;
+use plugins::meow;
+use plugins::monitor;
+use plugins::mood;
+use plugins::particles;
+use plugins::persistence;
+use plugins::physics;
+use plugins::pomodoro;
+use plugins::poop;
+use plugins::recording;
+use plugins::scratch;
+use plugins::screen_lock;
+use plugins::snapshot;
+use plugins::state_sounds;
+use plugins::teaching;
+use plugins::typing;
+use plugins::waypoints;
+use plugins::window_flags;
 
+pub mod autostart;
 pub mod bonnie;
-use bonnie::{Bonnie, StateMachine};
+use bonnie::BonnieBuilder;
 
-#[cfg(target_os = "macos")]
-pub fn get_composite_mode() -> CompositeAlphaMode {
-    CompositeAlphaMode::PostMultiplied
-}
+pub mod cli;
+use cli::Cli;
 
-#[cfg(not(target_os = "macos"))]
-pub fn get_composite_mode() -> CompositeAlphaMode {
-    CompositeAlphaMode::default()
-}
+pub mod config;
+use config::Config;
+
+pub mod edges;
+pub mod logging;
+pub mod notify;
+pub mod profile;
+pub mod stacking;
+pub mod window;
+use stacking::Stacking;
 
 #[cfg(target_os = "linux")]
 fn configure_linux_audio() {
@@ -27,17 +68,23 @@ fn configure_linux_audio() {
         use std::path::PathBuf;
 
         // try to detect PulseAudio/PipeWire socket location
-        let runtime_dir =
-            std::env::var("XDG_RUNTIME_DIR").unwrap_or_else(|_| "/run/user/1000".to_string());
+        let runtime_dir = std::env::var("XDG_RUNTIME_DIR")
+            .unwrap_or_else(|_| format!("/run/user/{}", libc::getuid()));
+        let runtime_dir = PathBuf::from(runtime_dir);
 
-        let pulse_path = PathBuf::from(&runtime_dir).join("pulse/native");
-        let pipewire_path = PathBuf::from(&runtime_dir).join("pipewire-0");
+        // try PulseAudio first, then the usual PipeWire sockets
+        let socket = ["pulse/native", "pipewire-0", "pipewire-1"]
+            .into_iter()
+            .map(|name| runtime_dir.join(name))
+            .find(|path| path.exists());
 
-        // try both PulseAudio and PipeWire sockets
-        if pulse_path.exists() {
-            std::env::set_var("PULSE_SERVER", pulse_path.to_str().unwrap());
-        } else if pipewire_path.exists() {
-            std::env::set_var("PULSE_SERVER", pipewire_path.to_str().unwrap());
+        // logging isn't set up yet
+        match socket {
+            Some(path) => {
+                println!("Using audio socket {}", path.display());
+                std::env::set_var("PULSE_SERVER", &path);
+            }
+            None => eprintln!("No audio socket found in {}", runtime_dir.display()),
         }
 
         // ALSA fallback configuration
@@ -46,65 +93,350 @@ fn configure_linux_audio() {
     }
 }
 
+/// The window each Bonnie lives in.
+fn bonnie_window(stacking: Stacking) -> Window {
+    Window {
+        transparent: true,
+        composite_alpha_mode: compositor::get_composite_mode(),
+        decorations: false,
+        resizable: false,
+        has_shadow: false,
+        titlebar_shown: false,
+        titlebar_transparent: false,
+        titlebar_show_buttons: false,
+        titlebar_show_title: false,
+        title: "Bonnie Buddy".to_string(),
+        name: Some("bonnie.buddy".into()),
+        resolution: (100.0, 100.0).into(),
+        resize_constraints: WindowResizeConstraints {
+            min_width: 100.0,
+            min_height: 100.0,
+            max_width: 100.0,
+            max_height: 100.0,
+        },
+        window_level: stacking.level(),
+        ..default()
+    }
+}
+
 fn main() {
+    let cli = Cli::parse();
+
+    if let Some(cli::Command::Ctl { command }) = &cli.command {
+        match ipc::send(&command.join(" ")) {
+            Ok(reply) => {
+                println!("{reply}");
+                if reply.starts_with("error") {
+                    std::process::exit(1);
+                }
+            }
+            Err(err) => {
+                eprintln!("Couldn't reach Bonnie: {err}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let (config, config_problems) = Config::load(cli.config.as_deref());
+
+    if cli.install_autostart || cli.uninstall_autostart {
+        let (action, result) = if cli.install_autostart {
+            ("Installed", autostart::install())
+        } else {
+            ("Removed", autostart::uninstall())
+        };
+
+        match result {
+            Ok(entry) => println!("{action} autostart entry: {entry}"),
+            Err(err) => {
+                eprintln!("Couldn't update autostart: {err}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     #[cfg(target_os = "linux")]
-    {
+    if !cli.no_audio {
         configure_linux_audio();
         unsafe { std::env::set_var("BEVY_AUDIO_THREAD", "1") };
     }
 
-    App::new()
-        .add_plugins(
-            DefaultPlugins
-                .set(WindowPlugin {
-                    primary_window: Some(Window {
-                        transparent: true,
-                        composite_alpha_mode: get_composite_mode(),
-                        decorations: false,
-                        resizable: false,
-                        has_shadow: false,
-                        titlebar_shown: false,
-                        titlebar_transparent: false,
-                        titlebar_show_buttons: false,
-                        titlebar_show_title: false,
-                        title: "Bonnie Buddy".to_string(),
-                        name: Some("bonnie.buddy".into()),
-                        resolution: (100.0, 100.0).into(),
-                        resize_constraints: WindowResizeConstraints {
-                            min_width: 100.0,
-                            min_height: 100.0,
-                            max_width: 100.0,
-                            max_height: 100.0,
-                        },
-                        window_level: WindowLevel::AlwaysOnTop,
-                        ..default()
-                    }),
-                    ..default()
-                })
-                .set(ImagePlugin::default_nearest()),
-        )
-        .add_plugins(control::BonnieControlPlugin)
-        .add_plugins(bonnie_state::BonnieStatePlugin)
-        .add_plugins(global_cursor::GlobalCursorPlugin)
-        .insert_resource(ClearColor(Color::NONE))
-        .add_systems(Startup, setup)
-        .run();
-}
+    if cli.watch_assets && !cfg!(feature = "watch-assets") {
+        eprintln!("--watch-assets needs building with `--features watch-assets`, ignoring it.");
+    }
 
-fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
-    commands.spawn(Camera2d);
+    let skin = cli.skin.clone().or(config.skin.clone());
+    let stacking = cli.stacking.or(config.stacking).unwrap_or_default();
 
-    let mut bonnie_sprite = Sprite::from_image(asset_server.load("BonNormal.png"));
+    let mut app = App::new();
+    if let Some(skin) = &skin {
+        app.register_asset_source("skin", bonnie_state::skin_source(skin.clone()));
+    }
 
-    bonnie_sprite.custom_size = Some(Vec2::new(100.0, 100.0));
+    // without the audio plugin no audio device gets opened
+    let default_plugins = if cli.no_audio {
+        DefaultPlugins.build().disable::<AudioPlugin>()
+    } else {
+        DefaultPlugins.build()
+    };
 
-    commands.spawn((
-        Bonnie::default(),
-        Name::new("Bonnie"),
-        StateMachine {
-            timer: Timer::new(Duration::from_secs_f32(2.0), TimerMode::Once),
-            can_change: true,
+    app.add_plugins(
+        default_plugins
+            .set(WindowPlugin {
+                primary_window: Some(bonnie_window(stacking)),
+                ..default()
+            })
+            .set(ImagePlugin::default_nearest())
+            .set(AssetPlugin {
+                // sprites hold their handles, so reloads show up on their own
+                watch_for_changes_override: Some(cli.watch_assets),
+                ..default()
+            })
+            .set(LogPlugin {
+                level: cli.log_level,
+                custom_layer: logging::file_layer,
+                ..default()
+            }),
+    )
+    .add_plugins(audio::BonnieAudioPlugin)
+    .add_plugins(control::BonnieControlPlugin)
+    .add_plugins(bonnie_state::BonnieStatePlugin)
+    .add_plugins(idle::IdlePlugin)
+    .add_plugins(footsteps::FootstepsPlugin)
+    .add_plugins(poop::PoopPlugin)
+    .add_plugins(chase::ChasePlugin)
+    .add_plugins(teaching::TeachingPlugin)
+    .add_plugins(meow::MeowPlugin)
+    .add_plugins(state_sounds::StateSoundsPlugin)
+    .add_plugins(birds::BirdPlugin)
+    .add_plugins(scratch::ScratchPlugin)
+    .add_plugins(dance::DancePlugin)
+    .add_plugins(global_cursor::GlobalCursorPlugin)
+    .add_plugins(physics::PhysicsPlugin)
+    .add_plugins(drag::DragPlugin)
+    .add_plugins(screen_lock::ScreenLockPlugin)
+    .add_plugins(monitor::MonitorPlugin)
+    .add_plugins(compositor::CompositorPlugin)
+    .add_plugins(laser::LaserPlugin)
+    .add_plugins(pomodoro::PomodoroPlugin)
+    .add_plugins(waypoints::WaypointPlugin)
+    .add_plugins(recording::RecordingPlugin)
+    .add_plugins(cpu_load::CpuLoadPlugin)
+    .add_plugins(battery::BatteryPlugin)
+    .add_plugins(hunger::HungerPlugin)
+    .add_plugins(mood::MoodPlugin)
+    .add_plugins(particles::ParticlePlugin)
+    .add_plugins(typing::TypingPlugin)
+    .add_plugins(boredom::BoredomPlugin)
+    .add_plugins(fullscreen::FullscreenPlugin)
+    .add_plugins(persistence::PersistencePlugin)
+    .add_plugins(ipc::IpcPlugin)
+    .add_plugins(loopback::LoopbackPlugin)
+    .add_plugins(window_flags::WindowFlagsPlugin)
+    .add_plugins(snapshot::SnapshotPlugin)
+    .insert_resource(ClearColor(Color::NONE))
+    .insert_resource(audio::AudioConfig {
+        enabled: !cli.no_audio,
+        volume: cli.profile.volume(),
+        quiet_hours: config.quiet_hours,
+        ..default()
+    })
+    .insert_resource(cli.profile.behaviour())
+    .insert_resource(bonnie_state::MovementConfig {
+        speed_multiplier: config.speed_multiplier.clamp(
+            bonnie_state::MIN_SPEED_MULTIPLIER,
+            bonnie_state::MAX_SPEED_MULTIPLIER,
+        ),
+        fade_between_monitors: config.fade_between_monitors,
+    })
+    .insert_resource(config.speeds.clone())
+    .insert_resource(bonnie_state::EnabledStates::new(
+        config.enabled_states.as_deref(),
+        &config.disabled_states,
+    ))
+    .insert_resource(bonnie_state::StateHistory::new(config.state_history))
+    .insert_resource(config.transition_timing)
+    .insert_resource(bonnie_state::PatrolRoute {
+        waypoints: config.patrol_route.into_iter().map(IVec2::from).collect(),
+        mode: config.patrol_mode,
+    })
+    .insert_resource(bonnie_state::WanderRegion(config.wander_region.map(
+        |region| {
+            IRect::new(
+                region.x,
+                region.y,
+                region.x + region.width,
+                region.y + region.height,
+            )
+        },
+    )))
+    .insert_resource(bonnie_state::LeashConfig {
+        radius: config.leash_radius.max(0.0),
+    })
+    .insert_resource(fullscreen::FullscreenConfig {
+        enabled: config.pause_for_fullscreen,
+        hide: config.hide_for_fullscreen,
+    })
+    .insert_resource(control::ShadowConfig {
+        enabled: config.shadow,
+    })
+    .insert_resource(bonnie_state::OpacityConfig {
+        opacity: config.opacity.clamp(0.0, 1.0),
+        ..default()
+    })
+    .insert_resource(idle::DockConfig {
+        enabled: config.dock,
+        corners: config.dock_corners,
+    })
+    .insert_resource(idle::IdleSprites(config.idle_sprites))
+    .insert_resource(bonnie_state::BonnieSprites::new(
+        config.night_hours,
+        skin.clone(),
+    ))
+    .insert_resource(cpu_load::CpuLoadConfig {
+        enabled: config.react_to_cpu,
+    })
+    .insert_resource(boredom::BoredomConfig {
+        thresholds: config
+            .bored_after
+            .iter()
+            .map(|mins| Duration::from_secs_f32(mins.max(0.0) * 60.0))
+            .collect(),
+    })
+    .insert_resource(typing::TypingConfig {
+        enabled: config.react_to_typing,
+        cooldown: Duration::from_secs_f32(config.typing_cooldown.max(0.0) * 60.0),
+    })
+    .insert_resource(notify::Notifications {
+        enabled: config.notifications,
+    })
+    .insert_resource(battery::BatteryConfig {
+        threshold: config.low_battery,
+        notify: config.battery_notify,
+    })
+    .insert_resource(hunger::HungerConfig {
+        enabled: config.hunger,
+    })
+    .insert_resource(loopback::LoopbackConfig {
+        enabled: config.dance_to_audio && !cli.no_audio,
+    })
+    .insert_resource(persistence::PersistenceConfig {
+        autosave: Duration::from_secs_f32(config.autosave_interval.max(0.0)),
+    })
+    .insert_resource(control::KeyBindings::new(&config.keys))
+    .insert_resource(control::ForceStateConfig {
+        state: config.force_state,
+    })
+    .insert_resource(recording::RecordingConfig {
+        record: cli.record.clone(),
+        replay: cli.replay.clone(),
+    })
+    .insert_resource(cli.profile)
+    .insert_resource(bonnie_state::Energy(cli.energy.clamp(0.0, 1.0)))
+    .insert_resource(teaching::TeachingConfig {
+        text_tips: cli.text_tips,
+        locale: cli.locale.clone(),
+    })
+    .insert_resource(compositor::CompositorConfig {
+        composite_mode: config.composite_mode,
+    })
+    .insert_resource(snapshot::SnapshotConfig {
+        windows: config.snapshot_windows,
+    })
+    .insert_resource(state_sounds::StateSounds(
+        config.state_sounds.clone().into_iter().collect(),
+    ))
+    .insert_resource(poop::PoopConfig {
+        offset: config.poop_offset.map(IVec2::from),
+    })
+    .insert_resource(birds::BirdConfig {
+        count: config.bird_count.clamp(1, birds::MAX_BIRDS),
+        speed: config.bird_speed.max(0.0),
+        types: if config.bird_types.is_empty() {
+            vec![birds::BirdType::default()]
+        } else {
+            config.bird_types.clone()
         },
-        bonnie_sprite,
-    ));
+    })
+    .insert_resource(chase::ChaseConfig {
+        lead: cli.chase_lead.max(0.0),
+    })
+    .insert_resource(pomodoro::PomodoroConfig {
+        work: cli
+            .pomodoro
+            .map(|minutes| Duration::from_secs_f32(minutes.max(1.0) * 60.0)),
+        rest: Duration::from_secs_f32(cli.pomodoro_break.max(1.0) * 60.0),
+    })
+    .insert_resource(physics::PhysicsConfig {
+        gravity: cli.gravity,
+    })
+    .insert_resource(global_cursor::GlobalCursorConfig {
+        debug_overlay: cli.debug_cursor,
+        assume_available: cli.assume_cursor,
+    })
+    .insert_resource(cli.edges)
+    .insert_resource(stacking)
+    .insert_resource(cli)
+    .insert_resource(config_problems)
+    .add_systems(
+        Startup,
+        (
+            // she can only start in a state cursor tracking allows
+            setup.after(global_cursor::check_cursor_tracking),
+            config::warn_config_problems,
+        ),
+    )
+    .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    sprites: bonnie_state::Sprites,
+    opacity: Res<bonnie_state::OpacityConfig>,
+    cli: Res<Cli>,
+    stacking: Res<Stacking>,
+    rules: bonnie_state::StateRules,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+) {
+    let primary_window = primary_window.single();
+
+    let start_state = cli.start_state.filter(|&state| {
+        let allowed = rules.allowed()(state);
+        if !allowed {
+            warn!(
+                "Bonnie can't start in {:?} at the moment, so she'll idle.",
+                state
+            );
+        }
+        allowed
+    });
+
+    for index in 0..cli.count.max(1) {
+        // the first bonnie gets the primary window, the rest get their own
+        let window = if index == 0 {
+            primary_window
+        } else {
+            commands.spawn(bonnie_window(*stacking)).id()
+        };
+
+        let mut bonnie_sprite = Sprite::from_image(sprites.load("BonNormal.png"));
+
+        bonnie_sprite.custom_size = Some(Vec2::new(100.0, 100.0));
+        bonnie_sprite.color.set_alpha(opacity.target());
+
+        let mut bonnie =
+            BonnieBuilder::new(index).timer(Duration::from_secs_f32(cli.start_after.max(0.0)));
+        if let Some(state) = start_state {
+            bonnie = bonnie.state(state.into());
+        }
+        if index == 0
+            && let Some(position) = cli.start_at
+        {
+            bonnie = bonnie.at(position);
+        }
+
+        bonnie.spawn(&mut commands, window, bonnie_sprite);
+    }
 }
@@ -0,0 +1,87 @@
+//! Posting desktop notifications.
+//!
+//! - Linux: `notify-send`.
+//! - macOS: `osascript`.
+//!
+//! Posting happens off the main thread and failing just gets logged, so a
+//! missing notification daemon never gets in Bonnie's way.
+
+use std::thread;
+
+use bevy::prelude::*;
+
+/// Whether features are allowed to post desktop notifications at all.
+#[derive(Resource, Debug, Clone)]
+pub struct Notifications {
+    pub enabled: bool,
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+impl Notifications {
+    /// Posts a notification if they're turned on.
+    pub fn notify(&self, title: &str, body: &str) {
+        if self.enabled {
+            notify(title, body);
+        }
+    }
+}
+
+/// Posts a desktop notification regardless of whether they're turned on.
+pub fn notify(title: &str, body: &str) {
+    let (title, body) = (title.to_string(), body.to_string());
+
+    let spawned = thread::Builder::new().name("notify".into()).spawn(move || {
+        if let Err(err) = post(&title, &body) {
+            warn!("Couldn't post notification {:?}: {}", title, err);
+        }
+    });
+
+    if let Err(err) = spawned {
+        warn!("Couldn't start posting a notification: {}", err);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn post(title: &str, body: &str) -> std::io::Result<()> {
+    let status = std::process::Command::new("notify-send")
+        .args(["--app-name", "Bonnie Buddy", title, body])
+        .status()?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!(
+            "notify-send failed: {status}"
+        )))
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn post(title: &str, body: &str) -> std::io::Result<()> {
+    let status = std::process::Command::new("osascript")
+        .arg("-e")
+        .arg(format!(
+            "display notification {:?} with title {:?}",
+            body, title
+        ))
+        .status()?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!("osascript failed: {status}")))
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn post(_title: &str, _body: &str) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "notifications aren't supported on this platform",
+    ))
+}
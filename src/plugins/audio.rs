@@ -0,0 +1,298 @@
+//! Audio settings shared by every sound Bonnie makes.
+//!
+//! Munches are picked at random from whatever's in `assets/munches`, or just
+//! `munch.ogg` if that's empty or missing, so more can be added without
+//! rebuilding.
+
+use bevy::{
+    asset::io::file::FileAssetReader,
+    audio::{PlaybackMode, Volume},
+    ecs::system::SystemParam,
+    prelude::*,
+    utils::HashMap,
+};
+use chrono::Local;
+use rand::{Rng, seq::IndexedRandom};
+use std::{fs, time::Duration};
+
+use crate::config::TimeRange;
+
+use super::bonnie_state::BonnieStateDiscriminants;
+
+/// How far off-centre a sound can be panned, 1.0 being entirely in one ear.
+const MAX_PAN: f32 = 0.8;
+/// Minimum seconds between two plays of the same state's sound.
+const STATE_SOUND_COOLDOWN: f32 = 2.0;
+
+pub struct BonnieAudioPlugin;
+
+impl Plugin for BonnieAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AudioConfig>()
+            .init_resource::<SoundCooldowns>()
+            .init_resource::<MunchSounds>()
+            .add_systems(Startup, (setup_listener, load_munches));
+    }
+}
+
+/// Ears one unit either side of the origin, so panned sounds can sit between them.
+fn setup_listener(mut commands: Commands) {
+    commands.spawn((SpatialListener::new(2.0), Transform::default()));
+}
+
+/// Stereo pan for something at `x` across a monitor `width` wide, negative being left.
+pub fn stereo_pan(x: i32, width: u32) -> f32 {
+    if width == 0 {
+        return 0.0;
+    }
+
+    (x as f32 / width as f32 * 2.0 - 1.0).clamp(-MAX_PAN, MAX_PAN)
+}
+
+/// Where to put a spatial sound so it's heard at `pan`.
+pub fn pan_transform(pan: f32) -> Transform {
+    Transform::from_xyz(pan, 0.0, 0.0)
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct AudioConfig {
+    /// Whether there's audio at all, unlike muting nothing even gets loaded.
+    pub enabled: bool,
+    pub volume: f32,
+    pub muted: bool,
+    /// How far meows can stray from their recorded pitch, e.g. 0.1 for 0.9x to 1.1x.
+    pub meow_pitch_variation: f32,
+    /// Minimum seconds between two meows.
+    pub meow_cooldown: f32,
+    /// Minimum seconds between two munches.
+    pub munch_cooldown: f32,
+    /// Times of day she doesn't meow or munch.
+    pub quiet_hours: Vec<TimeRange>,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            volume: 1.0,
+            muted: false,
+            meow_pitch_variation: 0.1,
+            meow_cooldown: 8.0,
+            munch_cooldown: 1.0,
+            quiet_hours: Vec::new(),
+        }
+    }
+}
+
+impl AudioConfig {
+    /// Volume to play a sound at, `scale` being how loud it is relative to the others.
+    pub fn volume(&self, scale: f32) -> Volume {
+        if self.muted {
+            Volume::ZERO
+        } else {
+            Volume::new(self.volume * scale)
+        }
+    }
+
+    /// How long `sound` has to wait after last playing.
+    pub fn cooldown(&self, sound: Sound) -> Duration {
+        let secs = match sound {
+            Sound::Meow => self.meow_cooldown,
+            Sound::Munch => self.munch_cooldown,
+            Sound::State(_) => STATE_SOUND_COOLDOWN,
+        };
+
+        Duration::from_secs_f32(secs.max(0.0))
+    }
+
+    /// Whether it's currently quiet hours by the system clock.
+    pub fn is_quiet(&self) -> bool {
+        let now = Local::now().time();
+        self.quiet_hours.iter().any(|range| range.contains(now))
+    }
+
+    /// A random playback speed for a meow, which also shifts its pitch.
+    pub fn meow_speed(&self, rng: &mut impl Rng) -> f32 {
+        let variation = self.meow_pitch_variation.clamp(0.0, 0.5);
+
+        if variation == 0.0 {
+            1.0
+        } else {
+            rng.random_range((1.0 - variation)..=(1.0 + variation))
+        }
+    }
+}
+
+/// A sound to play once, at the configured volume.
+#[derive(Debug, Clone)]
+pub struct PlaySound {
+    path: String,
+    volume: f32,
+    pan: Option<f32>,
+    speed: f32,
+}
+
+impl PlaySound {
+    /// The sound at `path` in the assets, centred at full volume.
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            volume: 1.0,
+            pan: None,
+            speed: 1.0,
+        }
+    }
+
+    /// How loud it is relative to the other sounds.
+    pub fn volume(mut self, scale: f32) -> Self {
+        self.volume = scale;
+        self
+    }
+
+    /// Where it's heard between the ears, see [`stereo_pan`].
+    pub fn panned(mut self, pan: f32) -> Self {
+        self.pan = Some(pan);
+        self
+    }
+
+    /// Playback speed, which also shifts its pitch.
+    pub fn speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Starts it playing, unless there's no audio at all.
+    pub fn spawn(self, commands: &mut Commands, asset_server: &AssetServer, config: &AudioConfig) {
+        if !config.enabled {
+            return;
+        }
+
+        let player = (
+            AudioPlayer::new(asset_server.load(self.path)),
+            PlaybackSettings {
+                mode: PlaybackMode::Once,
+                volume: config.volume(self.volume),
+                speed: self.speed,
+                spatial: self.pan.is_some(),
+                ..default()
+            },
+        );
+
+        match self.pan {
+            Some(pan) => commands.spawn((player, pan_transform(pan))),
+            None => commands.spawn(player),
+        };
+    }
+}
+
+/// Plays sounds with the configured settings, minding their cooldowns.
+#[derive(SystemParam)]
+pub struct Sounds<'w, 's> {
+    commands: Commands<'w, 's>,
+    asset_server: Res<'w, AssetServer>,
+    pub config: Res<'w, AudioConfig>,
+    cooldowns: ResMut<'w, SoundCooldowns>,
+    time: Res<'w, Time>,
+}
+
+impl Sounds<'_, '_> {
+    /// Whether the rate limited `sound` is allowed to play now, marking it as
+    /// played if so.
+    pub fn try_play(&mut self, sound: Sound) -> bool {
+        self.cooldowns
+            .try_play(sound, self.time.elapsed(), &self.config)
+    }
+
+    /// Starts `sound` playing, unless there's no audio at all.
+    pub fn play(&mut self, sound: PlaySound) {
+        sound.spawn(&mut self.commands, &self.asset_server, &self.config);
+    }
+}
+
+/// Every munch sound there is to pick from.
+#[derive(Resource, Debug, Clone)]
+pub struct MunchSounds(Vec<String>);
+
+impl Default for MunchSounds {
+    fn default() -> Self {
+        Self(vec!["munch.ogg".into()])
+    }
+}
+
+impl MunchSounds {
+    /// One of them at random.
+    pub fn random(&self, rng: &mut impl Rng) -> &str {
+        self.0.choose(rng).map_or("munch.ogg", String::as_str)
+    }
+}
+
+fn load_munches(mut munches: ResMut<MunchSounds>, config: Res<AudioConfig>) {
+    if !config.enabled {
+        return;
+    }
+
+    let dir = FileAssetReader::get_base_path().join("assets/munches");
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return;
+    };
+
+    let mut found: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name.ends_with(".ogg") || name.ends_with(".wav"))
+        .map(|name| format!("munches/{name}"))
+        .collect();
+    found.sort();
+
+    if !found.is_empty() {
+        info!("Found {} munch sounds in {}.", found.len(), dir.display());
+        munches.0 = found;
+    }
+}
+
+/// Run condition for systems that only play sounds.
+pub fn audio_enabled(config: Res<AudioConfig>) -> bool {
+    config.enabled
+}
+
+/// Sounds that are rate limited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sound {
+    Meow,
+    Munch,
+    /// Going into a state, each rate limited on its own.
+    State(BonnieStateDiscriminants),
+}
+
+/// When each rate limited sound last played.
+#[derive(Resource, Default, Debug)]
+pub struct SoundCooldowns {
+    last_played: HashMap<Sound, Duration>,
+}
+
+impl SoundCooldowns {
+    /// Whether `sound` is allowed to play at `now`, marking it as played if so.
+    pub fn try_play(&mut self, sound: Sound, now: Duration, config: &AudioConfig) -> bool {
+        if !config.enabled {
+            return false;
+        }
+
+        if config.is_quiet() {
+            debug!("Skipping {:?}, it's quiet hours.", sound);
+            return false;
+        }
+
+        let cooling_down = self
+            .last_played
+            .get(&sound)
+            .is_some_and(|last| now.saturating_sub(*last) < config.cooldown(sound));
+
+        if cooling_down {
+            debug!("Skipping {:?}, still cooling down.", sound);
+            return false;
+        }
+
+        self.last_played.insert(sound, now);
+        true
+    }
+}
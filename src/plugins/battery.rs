@@ -0,0 +1,195 @@
+//! Bonnie noticing the battery's running low.
+//!
+//! The battery's read every `POLL_INTERVAL` on a background thread. When it
+//! drops below the threshold while discharging she comes to tell you to plug
+//! in, along with a desktop notification if that's on, once each time it runs
+//! low.
+
+#[cfg(target_os = "macos")]
+use std::process::Command;
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use bevy::{prelude::*, time::common_conditions::on_timer};
+
+use crate::{
+    bonnie::{Bonnie, StateMachine},
+    notify::Notifications,
+};
+
+use super::teaching::Messengers;
+
+/// How often the battery's checked, it doesn't drain fast enough to need more.
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+pub struct BatteryPlugin;
+
+impl Plugin for BatteryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BatteryConfig>()
+            .init_resource::<Battery>()
+            .init_resource::<Notifications>()
+            .add_systems(Startup, spawn_battery_poller)
+            .add_systems(Update, check_battery.run_if(on_timer(POLL_INTERVAL)));
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct BatteryConfig {
+    /// Percentage at or below which the battery counts as low.
+    pub threshold: u8,
+    /// Whether to post a desktop notification too.
+    pub notify: bool,
+}
+
+impl Default for BatteryConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 15,
+            notify: true,
+        }
+    }
+}
+
+/// How full the battery is and whether it's running on it.
+#[derive(Debug, Clone, Copy)]
+struct BatteryLevel {
+    percent: u8,
+    discharging: bool,
+}
+
+#[derive(Resource, Default, Debug)]
+struct Battery {
+    /// Whether she's already warned about this time it ran low.
+    warned: bool,
+    /// Latest reading from the poller.
+    level: Arc<Mutex<Option<BatteryLevel>>>,
+}
+
+fn spawn_battery_poller(battery: Res<Battery>) {
+    let level = battery.level.clone();
+
+    let spawned = thread::Builder::new()
+        .name("battery-poller".into())
+        .spawn(move || {
+            loop {
+                let reading = battery_level();
+                if let Ok(mut level) = level.lock() {
+                    *level = reading;
+                }
+
+                // no battery, or no way of reading it
+                if reading.is_none() {
+                    info!("No battery to watch.");
+                    return;
+                }
+
+                thread::sleep(POLL_INTERVAL);
+            }
+        });
+
+    if let Err(err) = spawned {
+        warn!("Couldn't start battery poller: {}", err);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn battery_level() -> Option<BatteryLevel> {
+    let read = |path: std::path::PathBuf| std::fs::read_to_string(path).ok();
+
+    std::fs::read_dir("/sys/class/power_supply")
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .find(|path| read(path.join("type")).is_some_and(|kind| kind.trim() == "Battery"))
+        .and_then(|battery| {
+            Some(BatteryLevel {
+                percent: read(battery.join("capacity"))?.trim().parse().ok()?,
+                discharging: read(battery.join("status"))?.trim() == "Discharging",
+            })
+        })
+}
+
+#[cfg(target_os = "macos")]
+fn battery_level() -> Option<BatteryLevel> {
+    // e.g. "-InternalBattery-0 (id=1234)	85%; discharging; 4:12 remaining"
+    let output = Command::new("pmset").args(["-g", "batt"]).output().ok()?;
+    let output = String::from_utf8_lossy(&output.stdout);
+    let line = output.lines().find(|line| line.contains('%'))?;
+
+    let percent = line.split('%').next()?;
+    let percent = percent
+        .rsplit(|c: char| !c.is_ascii_digit())
+        .next()?
+        .parse()
+        .ok()?;
+
+    Some(BatteryLevel {
+        percent,
+        discharging: line.contains("discharging"),
+    })
+}
+
+#[cfg(target_os = "windows")]
+fn battery_level() -> Option<BatteryLevel> {
+    use windows_sys::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    let mut status: SYSTEM_POWER_STATUS = unsafe { std::mem::zeroed() };
+    if unsafe { GetSystemPowerStatus(&mut status) } == 0 || status.BatteryLifePercent > 100 {
+        return None;
+    }
+
+    Some(BatteryLevel {
+        percent: status.BatteryLifePercent,
+        discharging: status.ACLineStatus == 0,
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn battery_level() -> Option<BatteryLevel> {
+    None
+}
+
+fn check_battery(
+    mut battery: ResMut<Battery>,
+    config: Res<BatteryConfig>,
+    notifications: Res<Notifications>,
+    mut bonnie_query: Query<(Entity, &mut Bonnie, &mut StateMachine)>,
+    mut messengers: Messengers,
+) {
+    let Some(level) = battery.level.lock().ok().and_then(|level| *level) else {
+        return;
+    };
+
+    let low = level.discharging && level.percent <= config.threshold;
+    if !low {
+        // plugged in or charged back up, so warn again next time
+        battery.warned = false;
+        return;
+    }
+
+    if battery.warned {
+        return;
+    }
+    battery.warned = true;
+
+    let message = format!("Battery's at {}%, time to plug me in!", level.percent);
+    info!("Battery low at {}%.", level.percent);
+
+    if config.notify {
+        notifications.notify("Bonnie Buddy", &message);
+    }
+
+    // only the first bonnie comes to worry about it
+    let Some((entity, mut bonnie, mut machine)) = bonnie_query
+        .iter_mut()
+        .find(|(_, bonnie, _)| bonnie.index == 0)
+    else {
+        return;
+    };
+
+    messengers.bring(entity, &mut bonnie, &mut machine, message);
+}
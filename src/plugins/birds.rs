@@ -0,0 +1,495 @@
+//! The Bird state.
+//!
+//! She lets a few birds loose, each flying about in its own window and
+//! bouncing off the edges of the screen, landing every so often. Clicking a
+//! bird catches it, and she creeps up on landed ones to pounce on them
+//! herself. The state's over once every bird's been caught.
+
+use bevy::{
+    ecs::system::SystemParam, input::mouse::MouseButtonInput, prelude::*,
+    render::view::RenderLayers,
+};
+use rand::{Rng, prelude::IndexedRandom};
+use serde::Deserialize;
+
+use crate::{
+    bonnie::{Bonnie, BonnieWindow, ChaseVelocity, FEET_OFFSET, StateMachine},
+    edges::push_inward,
+    stacking::Stacking,
+    window::PetWindow,
+};
+
+use super::{
+    audio::{PlaySound, Sounds},
+    bonnie_state::{
+        BonnieState, BonnieStateDiscriminants, BonnieTransition, CATCH_DISTANCE, GlobalRng,
+        MovementConfig, OwnedBy, SpeedConfig, Sprites, StateSystems, calculate_movement_speed,
+        despawn_window, entered, exited, left_clicked, window_centre,
+    },
+    monitor::{MonitorInfo, MonitorLayout},
+    particles::{ParticleBurst, SpawnParticles},
+    poop::{FADE_LAYER_BASE, FADE_LAYERS},
+};
+
+const BIRD_SIZE_BUFFER: i32 = 80;
+/// Each bird gets its own layer, after the fade layers, so birds facing
+/// different ways don't show up in each other's windows.
+const BIRD_LAYER_BASE: usize = FADE_LAYER_BASE + FADE_LAYERS;
+/// How many birds can be out at once before layers get reused.
+const BIRD_LAYERS: usize = 32;
+/// Most birds one Bird state can let loose.
+pub const MAX_BIRDS: usize = 8;
+/// Shortest and longest seconds a bird flies before landing.
+const BIRD_FLIGHT_SECS: (f32, f32) = (5.0, 12.0);
+/// Seconds a bird sits on the ground, giving her a chance to pounce.
+const BIRD_LANDED_SECS: f32 = 4.0;
+
+pub struct BirdPlugin;
+
+impl Plugin for BirdPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BirdConfig>()
+            .add_systems(PreUpdate, exit_bird.in_set(StateSystems::Exit))
+            .add_systems(PreUpdate, setup_bird.in_set(StateSystems::Enter))
+            .add_systems(Update, catch_clicked_birds.in_set(StateSystems::Input))
+            .add_systems(
+                Update,
+                (update_birds, stalk_birds)
+                    .chain()
+                    .in_set(StateSystems::Behave),
+            );
+    }
+}
+
+/// How many birds she lets loose and how fast they fly.
+#[derive(Resource, Debug, Clone)]
+pub struct BirdConfig {
+    /// Kept within `1..=MAX_BIRDS`.
+    pub count: usize,
+    /// Scales how fast birds fly, on top of the speed multiplier.
+    pub speed: f32,
+    /// The kinds of bird there are, one picked at random for each bird. Never
+    /// empty, there's always at least the usual bird.
+    pub types: Vec<BirdType>,
+}
+
+impl Default for BirdConfig {
+    fn default() -> Self {
+        Self {
+            count: 1,
+            speed: 1.0,
+            types: vec![BirdType::default()],
+        }
+    }
+}
+
+/// A kind of bird, how it looks and how fast it flies.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct BirdType {
+    /// Sprite name, looked up in the skin and assets like Bonnie's own.
+    pub sprite: String,
+    /// Width and height of its window, in pixels.
+    pub size: [f32; 2],
+    /// Scales how fast it flies, on top of [`BirdConfig::speed`].
+    pub speed: f32,
+}
+
+impl Default for BirdType {
+    fn default() -> Self {
+        Self {
+            sprite: "Bird.png".into(),
+            size: [55.0, 55.0],
+            speed: 1.0,
+        }
+    }
+}
+
+/// Where a Bonnie's creeping up on a landed bird.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Stalking(pub IVec2);
+
+#[derive(Component, Hash)]
+pub struct BirdWindow;
+
+#[derive(Component, Debug, Default)]
+struct BirdDirection {
+    v: IVec2,
+}
+
+/// Whether a bird's flying about or has landed, and for how much longer.
+#[derive(Component, Debug)]
+struct BirdFlight {
+    landed: bool,
+    timer: Timer,
+    /// Its type's speed scale.
+    speed: f32,
+}
+
+/// Catches a clicked bird, finishing the state if it was her last.
+fn catch_clicked_birds(
+    mut commands: Commands,
+    mut mouse_events: EventReader<MouseButtonInput>,
+    bird_query: Query<(Entity, &Window, &OwnedBy), With<BirdWindow>>,
+    mut bonnie_query: Query<&mut StateMachine>,
+    camera_query: Query<(Entity, &Camera)>,
+    mut celebrations: Celebrations,
+) {
+    for window in left_clicked(&mut mouse_events) {
+        let Ok((_, bird_window, &OwnedBy(owner))) = bird_query.get(window) else {
+            continue;
+        };
+        let centre = window_centre(bird_window);
+
+        despawn_window(&mut commands, window, &camera_query);
+
+        // caught the last one, so she's done
+        let others_left = bird_query
+            .iter()
+            .any(|(other, _, other_owner)| other != window && other_owner.0 == owner);
+        if !others_left && let Ok(mut machine) = bonnie_query.get_mut(owner) {
+            machine.finish();
+        }
+
+        // caught it!
+        celebrations.catch(centre);
+    }
+}
+
+/// Confetti where a bird was caught, and its last squawk.
+#[derive(SystemParam)]
+struct Celebrations<'w, 's> {
+    particles: EventWriter<'w, SpawnParticles>,
+    sounds: Sounds<'w, 's>,
+}
+
+impl Celebrations<'_, '_> {
+    fn catch(&mut self, at: Option<IVec2>) {
+        if let Some(at) = at {
+            self.particles.send(SpawnParticles {
+                at,
+                burst: ParticleBurst::confetti(),
+            });
+        }
+
+        self.sounds.play(PlaySound::new("kakapo-death.ogg"));
+    }
+}
+
+/// The birds to let loose, each drawn on the next of their layers in turn.
+#[derive(SystemParam)]
+struct Flock<'w, 's> {
+    config: Res<'w, BirdConfig>,
+    next_layer: Local<'s, usize>,
+}
+
+impl Flock<'_, '_> {
+    /// A layer for the next bird, reusing them once they run out.
+    fn next_layer(&mut self) -> RenderLayers {
+        let layer = RenderLayers::layer(BIRD_LAYER_BASE + *self.next_layer);
+        *self.next_layer = (*self.next_layer + 1) % BIRD_LAYERS;
+        layer
+    }
+}
+
+/// The monitor each Bonnie's on, for her birds to fly about.
+#[derive(SystemParam)]
+struct Skies<'w, 's> {
+    monitor_info: Res<'w, MonitorInfo>,
+    bonnie_query: Query<'w, 's, &'static BonnieWindow>,
+    window_query: Query<'w, 's, &'static Window, Without<BirdWindow>>,
+}
+
+impl Skies<'_, '_> {
+    /// The monitor `bonnie`'s window is on.
+    fn over(&self, bonnie: Entity) -> Option<&MonitorLayout> {
+        let window = self
+            .bonnie_query
+            .get(bonnie)
+            .ok()
+            .and_then(|bonnie_window| self.window_query.get(bonnie_window.0).ok());
+        self.monitor_info.for_window(window)
+    }
+}
+
+/// Where a bird's window can go on `monitor`, keeping clear of its edges.
+fn flight_bounds(monitor: &MonitorLayout) -> (IVec2, IVec2) {
+    let rect = monitor.rect();
+    let min = rect.min + BIRD_SIZE_BUFFER;
+    (min, (rect.max - BIRD_SIZE_BUFFER).max(min))
+}
+
+/// Lets the birds loose, flying until they're all caught or the state ends.
+fn setup_bird(
+    mut commands: Commands,
+    mut transitions: EventReader<BonnieTransition>,
+    sprites: Sprites,
+    skies: Skies,
+    mut rng: ResMut<GlobalRng>,
+    stacking: Res<Stacking>,
+    mut flock: Flock,
+) {
+    for bonnie in entered(&mut transitions, BonnieStateDiscriminants::Bird) {
+        let Some((min, max)) = skies.over(bonnie).map(flight_bounds) else {
+            continue;
+        };
+
+        for _ in 0..flock.config.count.clamp(1, MAX_BIRDS) {
+            let pos = IVec2::new(
+                rng.0.random_range(min.x..=max.x),
+                rng.0.random_range(min.y..=max.y),
+            );
+            let direction = IVec2::new(
+                if rng.0.random_bool(0.5) { 1 } else { -1 },
+                if rng.0.random_bool(0.5) { 1 } else { -1 },
+            );
+
+            let layer = flock.next_layer();
+
+            let bird_type = flock
+                .config
+                .types
+                .choose(&mut rng.0)
+                .cloned()
+                .unwrap_or_default();
+            let size = Vec2::from(bird_type.size).max(Vec2::ONE);
+
+            let mut bird_sprite = Sprite::from_image(sprites.load(&bird_type.sprite));
+            bird_sprite.custom_size = Some(size);
+
+            PetWindow::new("Bird!", size, layer.clone())
+                .at(WindowPosition::At(pos))
+                .spawn(
+                    &mut commands,
+                    *stacking,
+                    (
+                        BirdWindow,
+                        OwnedBy(bonnie),
+                        bird_sprite,
+                        BirdDirection { v: direction },
+                        BirdFlight {
+                            landed: false,
+                            timer: Timer::from_seconds(
+                                rng.0.random_range(BIRD_FLIGHT_SECS.0..=BIRD_FLIGHT_SECS.1),
+                                TimerMode::Once,
+                            ),
+                            speed: bird_type.speed.max(0.0),
+                        },
+                        layer,
+                    ),
+                );
+        }
+    }
+}
+
+/// Any birds still flying when the state ends fly off.
+fn exit_bird(
+    mut commands: Commands,
+    mut transitions: EventReader<BonnieTransition>,
+    mut bonnie_query: Query<&mut Sprite, (With<Bonnie>, With<Stalking>)>,
+    bird_query: Query<(Entity, &OwnedBy), With<BirdWindow>>,
+    camera_query: Query<(Entity, &Camera)>,
+    sprites: Sprites,
+) {
+    for bonnie in exited(&mut transitions, BonnieStateDiscriminants::Bird) {
+        // gave up mid stalk
+        if let Ok(mut sprite) = bonnie_query.get_mut(bonnie) {
+            sprite.image = sprites.load("BonNormal.png");
+            commands.entity(bonnie).remove::<Stalking>();
+        }
+
+        for (bird_window, owner) in &bird_query {
+            if owner.0 == bonnie {
+                despawn_window(&mut commands, bird_window, &camera_query);
+            }
+        }
+    }
+}
+
+fn update_birds(
+    mut bird_windows: Query<
+        (
+            &mut Window,
+            &mut BirdDirection,
+            &mut BirdFlight,
+            &mut Sprite,
+            &OwnedBy,
+        ),
+        With<BirdWindow>,
+    >,
+    skies: Skies,
+    time: Res<Time>,
+    movement_config: Res<MovementConfig>,
+    speed_config: Res<SpeedConfig>,
+    bird_config: Res<BirdConfig>,
+    mut rng: ResMut<GlobalRng>,
+) {
+    for (mut bird_window, mut bird_direction, mut flight, mut bird_sprite, owner) in
+        &mut bird_windows
+    {
+        // land for a bit every so often, then take off again
+        if flight.timer.tick(time.delta()).finished() {
+            flight.landed = !flight.landed;
+            let secs = if flight.landed {
+                BIRD_LANDED_SECS
+            } else {
+                rng.0.random_range(BIRD_FLIGHT_SECS.0..=BIRD_FLIGHT_SECS.1)
+            };
+            flight.timer = Timer::from_seconds(secs, TimerMode::Once);
+        }
+
+        if flight.landed {
+            continue;
+        }
+
+        // flying about the monitor of the Bonnie that let it loose
+        let Some(monitor) = skies.over(owner.0) else {
+            continue;
+        };
+
+        let current_pos = match bird_window.position {
+            WindowPosition::At(pos) => pos,
+            _ => monitor.rect().min,
+        };
+
+        let (min, max) = flight_bounds(monitor);
+        let speed = (calculate_movement_speed(
+            monitor.size,
+            &BonnieState::Bird,
+            movement_config.speed_multiplier * bird_config.speed * flight.speed,
+            &speed_config,
+        ) as f64
+            * time.delta_secs_f64()) as f32;
+        let (new_pos, direction) = fly(current_pos, bird_direction.v, speed, min, max);
+
+        bird_direction.v = direction;
+        bird_sprite.flip_x = direction.x > 0;
+        bird_window.position = WindowPosition::At(new_pos);
+    }
+}
+
+/// Moves a bird `distance` along `direction`, turning it back on any axis that
+/// went past `min..=max`.
+///
+/// A long frame could carry it well past the edge, leaving it turning back and
+/// forth out there, so it's kept inside too.
+fn fly(position: IVec2, direction: IVec2, distance: f32, min: IVec2, max: IVec2) -> (IVec2, IVec2) {
+    let moved = position + (direction.as_vec2() * distance).as_ivec2();
+
+    let mut direction = direction;
+    let inward = push_inward(moved, min, max);
+    if inward.x != 0 {
+        direction.x = inward.x;
+    }
+    if inward.y != 0 {
+        direction.y = inward.y;
+    }
+
+    (moved.clamp(min, max), direction)
+}
+
+/// A Bonnie that might creep up on her birds, and whether she already is.
+type Stalker = (
+    Entity,
+    &'static Bonnie,
+    &'static BonnieWindow,
+    &'static mut StateMachine,
+    &'static mut Sprite,
+    &'static mut ChaseVelocity,
+    Has<Stalking>,
+);
+
+/// Creeps each bird-watching Bonnie up on the nearest of her landed birds,
+/// catching it if she gets there before it takes off.
+fn stalk_birds(
+    mut commands: Commands,
+    mut bonnie_query: Query<Stalker, Without<BirdWindow>>,
+    bird_query: Query<(Entity, &Window, &BirdFlight, &OwnedBy), With<BirdWindow>>,
+    window_query: Query<&Window>,
+    camera_query: Query<(Entity, &Camera)>,
+    sprites: Sprites,
+    mut celebrations: Celebrations,
+) {
+    for (entity, bonnie, bonnie_window, mut machine, mut sprite, mut chase_velocity, stalking) in
+        &mut bonnie_query
+    {
+        if bonnie.state != BonnieState::Bird {
+            continue;
+        }
+
+        let Ok(WindowPosition::At(position)) = window_query
+            .get(bonnie_window.0)
+            .map(|window| window.position)
+        else {
+            continue;
+        };
+        let feet = position + FEET_OFFSET;
+
+        let landed = bird_query
+            .iter()
+            .filter(|(_, _, flight, owner)| owner.0 == entity && flight.landed)
+            .filter_map(|(bird, window, _, _)| match window.position {
+                WindowPosition::At(bird_position) => {
+                    Some((bird, bird_position + window.physical_size().as_ivec2() / 2))
+                }
+                _ => None,
+            })
+            .min_by_key(|(_, centre)| centre.distance_squared(feet));
+
+        let Some((bird, centre)) = landed else {
+            // it took off before she got there
+            if stalking {
+                commands.entity(entity).remove::<Stalking>();
+                sprite.image = sprites.load("BonNormal.png");
+            }
+            continue;
+        };
+
+        if feet.as_vec2().distance(centre.as_vec2()) < CATCH_DISTANCE {
+            info!("Bonnie pounced on a bird!");
+            despawn_window(&mut commands, bird, &camera_query);
+            celebrations.catch(Some(centre));
+
+            commands.entity(entity).remove::<Stalking>();
+            sprite.image = sprites.load("BonNormal.png");
+
+            // that was the last one
+            let others_left = bird_query
+                .iter()
+                .any(|(other, _, _, owner)| other != bird && owner.0 == entity);
+            if !others_left {
+                machine.finish();
+            }
+            continue;
+        }
+
+        if !stalking {
+            // crouch down and start from a standstill, ready to pounce
+            chase_velocity.0 = Vec2::ZERO;
+            sprite.image = sprites.load("BonAngryMouth.png");
+        }
+        commands
+            .entity(entity)
+            .insert(Stalking(centre - FEET_OFFSET));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_huge_step_off_the_edge_turns_back_inside() {
+        let min = IVec2::splat(BIRD_SIZE_BUFFER);
+        let max = IVec2::new(1920, 1080) - BIRD_SIZE_BUFFER;
+        let position = IVec2::new(max.x - 5, min.y + 5);
+
+        let (position, direction) = fly(position, IVec2::new(1, -1), 10_000.0, min, max);
+
+        assert!(
+            position.cmpge(min).all() && position.cmple(max).all(),
+            "ended up at {position}"
+        );
+        assert_eq!(direction, IVec2::new(-1, 1));
+    }
+}
@@ -1,46 +1,106 @@
 //! All the state stuff for Bonnie
-
-use std::any::TypeId;
+//!
+//! Picking her next state, moving her about and keeping track of the windows
+//! she opens. What she does in each state lives in that state's own plugin,
+//! slotted in through [`StateSystems`].
+
+use std::{
+    f32::consts::TAU,
+    fs,
+    path::{Path, PathBuf},
+};
 
 use crate::{
-    bonnie::{Bonnie, StateMachine},
-    get_composite_mode,
+    bonnie::{
+        Bonnie, BonnieWindow, ChaseVelocity, FEET_OFFSET, FractionalPosition, StateMachine,
+        StateTimer, StateTimers,
+    },
+    config::TimeRange,
+    edges::{EdgeBehaviour, push_inward},
+    stacking::Stacking,
+    window::Transient,
 };
 use bevy::{
-    audio::PlaybackMode,
+    asset::io::{AssetSource, AssetSourceBuilder, file::FileAssetReader},
+    ecs::system::SystemParam,
     input::{ButtonState, mouse::MouseButtonInput},
     prelude::*,
     render::{camera::RenderTarget, view::RenderLayers},
-    utils::Duration,
-    window::{CursorOptions, Monitor, PresentMode, PrimaryWindow, WindowLevel, WindowRef},
+    utils::{Duration, HashMap, HashSet},
+    window::WindowRef,
 };
-use rand::{
-    Rng, SeedableRng, TryRngCore,
-    prelude::{IndexedRandom, IteratorRandom},
-    rngs::StdRng,
+use rand::{Rng, SeedableRng, TryRngCore, prelude::IndexedRandom, rngs::StdRng};
+use serde::{Deserialize, Serialize};
+use strum::{EnumDiscriminants, EnumIter, EnumString, IntoEnumIterator};
+
+use super::{
+    audio::{AudioConfig, stereo_pan},
+    birds::{BirdWindow, Stalking},
+    boredom::Boredom,
+    chase::{ChaseConfig, Pounce},
+    cpu_load::CpuLoad,
+    drag::Dragging,
+    global_cursor::{CursorHistory, CursorTracking, GlobalCursorPosition},
+    hunger::Hunger,
+    idle::DockTarget,
+    laser::LaserDot,
+    monitor::MonitorInfo,
+    mood::{Mood, WindowsClosed},
+    physics::{Airborne, PhysicsConfig},
+    poop::{Fading, PoopWindow},
+    recording::Replay,
+    scratch::ScratchWindow,
+    screen_lock::ScreenLock,
+    teaching::{NerdWindow, TeachWindow},
+    typing::TypingActivity,
 };
-use strum::{EnumDiscriminants, EnumIter, IntoEnumIterator};
-
-use super::global_cursor::GlobalCursorPosition;
 
 ////////
 // Constants
 ////////
 
-const WINDOW_SIZE_BUFFER: u32 = 200;
-const BIRD_SIZE_BUFFER: i32 = 80;
-const POOP_LAYER: usize = 42;
-const TEACH_LAYER: usize = 43;
-const BIRD_LAYER: usize = 44;
-const SCRATCH_LAYER: usize = 45;
-const NERD_LAYER: usize = 46;
+/// Opacity she fades to as a ghost.
+const GHOST_OPACITY: f32 = 0.3;
+/// How much her opacity changes each second while fading.
+const FADE_RATE: f32 = 2.0;
+/// Seconds she takes to fade out, and back in, hopping between monitors.
+const TELEPORT_FADE_SECS: f32 = 0.4;
+/// How far from the edges of the monitor random walks keep, room allowing.
+const WALK_EDGE_BUFFER: i32 = 150;
+/// How many walk targets to try before settling for one over another window.
+const WALK_TARGET_ATTEMPTS: usize = 5;
+/// Gap between the render layers of consecutive Bonnies.
+const LAYER_STRIDE: usize = 16;
+const BONNIE_LAYER: usize = 41;
+pub const MIN_SPEED_MULTIPLIER: f32 = 0.1;
+pub const MAX_SPEED_MULTIPLIER: f32 = 5.0;
+/// Furthest ahead of the cursor she'll aim, so she doesn't overshoot wildly.
+const MAX_CHASE_LEAD: f32 = 200.0;
+/// How much of her top speed she gains each second when setting off on a chase.
+const CHASE_ACCELERATION: f32 = 3.0;
+/// How quickly she slows as she closes in, as a fraction of the distance left per second.
+const CHASE_ARRIVE_RATE: f32 = 4.0;
+/// Logical pixels from her target that count as having arrived.
+const ARRIVE_DISTANCE: f32 = 1.0;
+/// How close her feet have to get to the cursor, or a landed bird, to catch it.
+pub const CATCH_DISTANCE: f32 = 35.0;
+
+/// Render layer for a per-Bonnie layer `base`, offset by the Bonnie's index.
+pub fn layer_for(base: usize, index: usize) -> RenderLayers {
+    RenderLayers::layer(base + index * LAYER_STRIDE)
+}
+
+/// Render layer that Bonnie number `index` is drawn on.
+pub fn bonnie_layer(index: usize) -> RenderLayers {
+    layer_for(BONNIE_LAYER, index)
+}
 
 ////////
 // Resources
 ////////
 
 #[derive(Resource)]
-struct GlobalRng(StdRng);
+pub struct GlobalRng(pub StdRng);
 
 impl Default for GlobalRng {
     fn default() -> Self {
@@ -52,22 +112,452 @@ impl Default for GlobalRng {
     }
 }
 
+/// How fast everything moves.
+#[derive(Resource, Debug, Clone)]
+pub struct MovementConfig {
+    /// Scales every movement speed, kept within `MIN_SPEED_MULTIPLIER..=MAX_SPEED_MULTIPLIER`.
+    pub speed_multiplier: f32,
+    /// Whether she fades out and back in on another monitor rather than
+    /// walking across the gap to it.
+    pub fade_between_monitors: bool,
+}
+
+impl Default for MovementConfig {
+    fn default() -> Self {
+        Self {
+            speed_multiplier: 1.0,
+            fade_between_monitors: false,
+        }
+    }
+}
+
+/// How fast each thing moves before the speed multiplier, in fractions of the
+/// screen's diagonal per second.
+#[derive(Resource, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SpeedConfig {
+    /// The screen diagonal covered each second at a base speed of 1.0.
+    pub screen_fraction: f32,
+    pub chasing: f32,
+    /// The chalkboard catching up with her while teaching.
+    pub teaching: f32,
+    pub bird: f32,
+    /// Drifting over to dock.
+    pub idle: f32,
+    /// Everything else, like walking.
+    pub other: f32,
+}
+
+impl Default for SpeedConfig {
+    fn default() -> Self {
+        Self {
+            screen_fraction: 0.15,
+            chasing: 2.0,
+            teaching: 3.0,
+            bird: 1.5,
+            idle: 0.5,
+            other: 1.0,
+        }
+    }
+}
+
+/// When she changes state on her own, going by whether anyone's using the
+/// computer. Outside those times she settles back to idling and waits.
+#[derive(Resource, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TransitionTiming {
+    #[default]
+    Always,
+    /// Only while nobody's moved the cursor for a while, so she's calm while
+    /// they work.
+    OnlyWhenIdle,
+    /// Only while the cursor's being moved.
+    OnlyWhenActive,
+}
+
+impl TransitionTiming {
+    /// Whether she can pick a new state, `away` being whether the user is.
+    fn allows(self, away: bool) -> bool {
+        match self {
+            Self::Always => true,
+            Self::OnlyWhenIdle => away,
+            Self::OnlyWhenActive => !away,
+        }
+    }
+}
+
+/// How Bonnie gets back to the start of her patrol route.
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PatrolMode {
+    /// Straight from the last waypoint back to the first.
+    #[default]
+    Loop,
+    /// Back through the waypoints in reverse.
+    PingPong,
+}
+
+/// Where Bonnie's window goes, in order, while she's patrolling.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct PatrolRoute {
+    pub waypoints: Vec<IVec2>,
+    pub mode: PatrolMode,
+}
+
+impl PatrolRoute {
+    /// The waypoint she's heading for on the `leg`th leg of her patrol.
+    fn waypoint(&self, leg: usize) -> Option<IVec2> {
+        let len = self.waypoints.len();
+        let index = match self.mode {
+            _ if len < 2 => 0,
+            PatrolMode::Loop => leg % len,
+            PatrolMode::PingPong => {
+                let leg = leg % (2 * (len - 1));
+                if leg < len { leg } else { 2 * (len - 1) - leg }
+            }
+        };
+
+        self.waypoints.get(index).copied()
+    }
+}
+
+/// The part of the monitor Bonnie walks around, all of it if unset.
+#[derive(Resource, Default, Debug, Clone, Copy)]
+pub struct WanderRegion(pub Option<IRect>);
+
+impl WanderRegion {
+    /// Where her window's top left can go while keeping it in the region.
+    fn positions(&self, window_size: IVec2) -> Option<IRect> {
+        self.0.map(|region| {
+            IRect::from_corners(region.min, (region.max - window_size).max(region.min))
+        })
+    }
+}
+
+/// How far Bonnie wanders from her leash anchor.
+#[derive(Resource, Debug, Clone)]
+pub struct LeashConfig {
+    /// Furthest her window gets from the anchor, in pixels.
+    pub radius: f32,
+}
+
+impl Default for LeashConfig {
+    fn default() -> Self {
+        Self { radius: 300.0 }
+    }
+}
+
+/// Where a leashed Bonnie's window stays near.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct LeashAnchor(pub IVec2);
+
+/// How see-through Bonnie is.
+#[derive(Resource, Debug, Clone)]
+pub struct OpacityConfig {
+    /// Her usual opacity from 0.0 to 1.0.
+    pub opacity: f32,
+    /// Whether she's faded out to a ghost.
+    pub ghost: bool,
+}
+
+impl Default for OpacityConfig {
+    fn default() -> Self {
+        Self {
+            opacity: 1.0,
+            ghost: false,
+        }
+    }
+}
+
+impl OpacityConfig {
+    /// The opacity she should be fading towards.
+    pub fn target(&self) -> f32 {
+        if self.ghost {
+            self.opacity.min(GHOST_OPACITY)
+        } else {
+            self.opacity
+        }
+    }
+}
+
+/// Every sprite a skin can replace.
+const SKIN_SPRITES: [&str; 10] = [
+    "BonNormal.png",
+    "BonNormalMouth.png",
+    "BonAngry.png",
+    "BonAngryMouth.png",
+    "BonSleep.png",
+    "BonPaw.png",
+    "BonPoop.png",
+    "BonNerd.png",
+    "BonScratch.png",
+    "Bird.png",
+];
+
+/// The asset source skins are read from as `skin://`, which has to be
+/// registered before the asset plugin is added. It's watched for changes like
+/// the bundled assets when `--watch-assets` is on.
+pub fn skin_source(dir: PathBuf) -> AssetSourceBuilder {
+    let watched = dir.to_string_lossy().into_owned();
+
+    AssetSource::build()
+        .with_reader(move || Box::new(FileAssetReader::new(dir.clone())))
+        .with_watcher(AssetSource::get_default_watcher(
+            watched,
+            Duration::from_millis(300),
+        ))
+}
+
+/// The files in `dir`, and in its `night` folder as `night/<name>`.
+fn list_art(dir: &Path) -> HashSet<String> {
+    let list = |dir: &Path| -> Vec<String> {
+        fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .filter_map(|entry| entry.file_name().into_string().ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let night = list(&dir.join("night"))
+        .into_iter()
+        .map(|name| format!("night/{name}"));
+    list(dir).into_iter().chain(night).collect()
+}
+
+/// Loads Bonnie's own sprites from her skin if she has one, falling back to
+/// the bundled ones for anything it's missing, and swapping in the ones from
+/// a `night` folder during night hours where there's art for them.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct BonnieSprites {
+    night_hours: Option<TimeRange>,
+    /// Bundled sprites, including the night versions.
+    bundled_art: HashSet<String>,
+    skin: Option<PathBuf>,
+    /// Sprites the skin has, including the night versions.
+    skin_art: HashSet<String>,
+}
+
+impl BonnieSprites {
+    pub fn new(night_hours: Option<TimeRange>, skin: Option<PathBuf>) -> Self {
+        Self {
+            night_hours,
+            bundled_art: list_art(&FileAssetReader::get_base_path().join("assets")),
+            skin_art: skin.as_deref().map(list_art).unwrap_or_default(),
+            skin,
+        }
+    }
+
+    /// The sprite called `name`, or its night version if it's night.
+    pub fn load(&self, asset_server: &AssetServer, name: &str) -> Handle<Image> {
+        let night = self
+            .night_hours
+            .is_some_and(|hours| hours.contains(chrono::Local::now().time()));
+        let night_name = format!("night/{name}");
+
+        // the skin's own art wins over bundled night art, so they don't mix
+        if night && self.skin_art.contains(&night_name) {
+            asset_server.load(format!("skin://{night_name}"))
+        } else if self.skin_art.contains(name) {
+            asset_server.load(format!("skin://{name}"))
+        } else if night && self.bundled_art.contains(&night_name) {
+            asset_server.load(night_name)
+        } else {
+            asset_server.load(name.to_string())
+        }
+    }
+}
+
+/// Loads Bonnie's sprites, see [`BonnieSprites::load`].
+#[derive(SystemParam)]
+pub struct Sprites<'w> {
+    asset_server: Res<'w, AssetServer>,
+    bonnie_sprites: Res<'w, BonnieSprites>,
+}
+
+impl Sprites<'_> {
+    /// The sprite called `name`, or its night version if it's night.
+    pub fn load(&self, name: &str) -> Handle<Image> {
+        self.bonnie_sprites.load(&self.asset_server, name)
+    }
+}
+
+fn log_skin(bonnie_sprites: Res<BonnieSprites>) {
+    let Some(skin) = &bonnie_sprites.skin else {
+        return;
+    };
+
+    info!("Using the skin in {}.", skin.display());
+
+    for name in SKIN_SPRITES {
+        if !bonnie_sprites.skin_art.contains(name) {
+            info!("Skin has no {}, using the bundled one.", name);
+        }
+    }
+}
+
+/// How often Bonnie changes state and what she's likely to change to.
+#[derive(Resource, Debug, Clone)]
+pub struct BehaviourConfig {
+    /// How likely each state is to be picked relative to the others, 1.0 if missing.
+    pub weights: HashMap<BonnieStateDiscriminants, f32>,
+    /// Shortest and longest seconds between state changes.
+    pub timer_range: (f32, f32),
+}
+
+impl Default for BehaviourConfig {
+    fn default() -> Self {
+        Self {
+            weights: HashMap::default(),
+            timer_range: (1.0, 4.0),
+        }
+    }
+}
+
+impl BehaviourConfig {
+    pub fn weight(&self, state: BonnieStateDiscriminants) -> f32 {
+        self.weights.get(&state).copied().unwrap_or(1.0).max(0.0)
+    }
+
+    /// A random time until the next state change.
+    pub fn random_duration(&self, rng: &mut impl Rng) -> Duration {
+        let (min, max) = self.timer_range;
+        let min = min.max(0.1);
+        let max = max.max(min);
+
+        Duration::from_secs_f32(rng.random_range(min..=max))
+    }
+}
+
+/// How lively Bonnie is from 0.0 to 1.0, 0.5 leaving the behaviour settings as
+/// they are. More energy means shorter waits between states and more of the
+/// active ones, less means more napping.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct Energy(pub f32);
+
+impl Default for Energy {
+    fn default() -> Self {
+        Self(0.5)
+    }
+}
+
+impl Energy {
+    /// Between 0.5x and 2x, going up with energy.
+    fn scale(self) -> f32 {
+        2.0_f32.powf((self.0.clamp(0.0, 1.0) - 0.5) * 2.0)
+    }
+
+    /// How much more or less likely `state` gets.
+    pub fn weight_scale(self, state: BonnieStateDiscriminants) -> f32 {
+        match state {
+            BonnieStateDiscriminants::Walking
+            | BonnieStateDiscriminants::Patrolling
+            | BonnieStateDiscriminants::Chasing
+            | BonnieStateDiscriminants::Bird
+            | BonnieStateDiscriminants::Dancing => self.scale(),
+            BonnieStateDiscriminants::Idle => 1.0 / self.scale(),
+            _ => 1.0,
+        }
+    }
+
+    /// How much longer or shorter the waits between states get.
+    pub fn timer_scale(self) -> f32 {
+        1.0 / self.scale()
+    }
+}
+
+/// The states Bonnie is allowed to pick from, always including Idle so she has
+/// somewhere safe to fall back to.
+#[derive(Resource, Debug, Clone)]
+pub struct EnabledStates(HashSet<BonnieStateDiscriminants>);
+
+impl Default for EnabledStates {
+    fn default() -> Self {
+        Self(BonnieStateDiscriminants::iter().collect())
+    }
+}
+
+impl EnabledStates {
+    /// Only `enabled` if given, otherwise everything, minus `disabled`.
+    pub fn new(
+        enabled: Option<&[BonnieStateDiscriminants]>,
+        disabled: &[BonnieStateDiscriminants],
+    ) -> Self {
+        let mut states: HashSet<_> = match enabled {
+            Some(enabled) => enabled.iter().copied().collect(),
+            None => BonnieStateDiscriminants::iter().collect(),
+        };
+        states.retain(|state| !disabled.contains(state));
+        states.insert(BonnieStateDiscriminants::Idle);
+
+        Self(states)
+    }
+
+    pub fn contains(&self, state: BonnieStateDiscriminants) -> bool {
+        self.0.contains(&state)
+    }
+}
+
+/// The last few states each Bonnie went into, which she steers clear of when
+/// picking what to do next so she doesn't flip between the same two.
+#[derive(Resource, Debug, Clone)]
+pub struct StateHistory {
+    /// How many recent states to remember.
+    length: usize,
+    recent: HashMap<Entity, Vec<BonnieStateDiscriminants>>,
+}
+
+impl Default for StateHistory {
+    fn default() -> Self {
+        Self::new(2)
+    }
+}
+
+impl StateHistory {
+    pub fn new(length: usize) -> Self {
+        Self {
+            length,
+            recent: HashMap::default(),
+        }
+    }
+
+    fn recent(&self, bonnie: Entity) -> &[BonnieStateDiscriminants] {
+        self.recent.get(&bonnie).map_or(&[], Vec::as_slice)
+    }
+
+    fn push(&mut self, bonnie: Entity, state: BonnieStateDiscriminants) {
+        let recent = self.recent.entry(bonnie).or_default();
+        recent.push(state);
+
+        let excess = recent.len().saturating_sub(self.length);
+        recent.drain(..excess);
+    }
+}
+
 ////////
 // States
 ////////
 
-#[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash, EnumIter, EnumDiscriminants)]
-#[strum_discriminants(derive(EnumIter))]
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash, EnumIter, EnumDiscriminants)]
+#[strum_discriminants(derive(EnumIter, EnumString, Hash, PartialOrd, Ord, Serialize, Deserialize))]
+#[strum_discriminants(serde(rename_all = "lowercase"))]
+#[strum_discriminants(strum(serialize_all = "lowercase"))]
 pub enum BonnieState {
     #[default]
     Idle,
     Walking(IVec2),
+    /// Following the patrol route, counting the legs walked so far.
+    Patrolling(usize),
     Pooping,
     Chasing,
     Teaching,
     Meowing,
     Bird,
     Scratch,
+    /// Bobbing along to whatever's playing.
+    Dancing,
 }
 
 impl From<BonnieStateDiscriminants> for BonnieState {
@@ -75,16 +565,52 @@ impl From<BonnieStateDiscriminants> for BonnieState {
         match value {
             BonnieStateDiscriminants::Idle => BonnieState::Idle,
             BonnieStateDiscriminants::Walking => BonnieState::Walking(IVec2::ZERO),
+            BonnieStateDiscriminants::Patrolling => BonnieState::Patrolling(0),
             BonnieStateDiscriminants::Pooping => BonnieState::Pooping,
             BonnieStateDiscriminants::Chasing => BonnieState::Chasing,
             BonnieStateDiscriminants::Teaching => BonnieState::Teaching,
             BonnieStateDiscriminants::Meowing => BonnieState::Meowing,
             BonnieStateDiscriminants::Bird => BonnieState::Bird,
             BonnieStateDiscriminants::Scratch => BonnieState::Scratch,
+            BonnieStateDiscriminants::Dancing => BonnieState::Dancing,
         }
     }
 }
 
+////////
+// Events
+////////
+
+/// Sent whenever a Bonnie changes state.
+#[derive(Event, Debug, Clone)]
+pub struct BonnieTransition {
+    pub bonnie: Entity,
+    pub from: BonnieState,
+    pub to: BonnieState,
+}
+
+/// Bonnies that entered `state` in the transitions not yet read.
+pub fn entered<'a>(
+    transitions: &'a mut EventReader<BonnieTransition>,
+    state: BonnieStateDiscriminants,
+) -> impl Iterator<Item = Entity> + 'a {
+    transitions
+        .read()
+        .filter(move |t| BonnieStateDiscriminants::from(&t.to) == state)
+        .map(|t| t.bonnie)
+}
+
+/// Bonnies that exited `state` in the transitions not yet read.
+pub fn exited<'a>(
+    transitions: &'a mut EventReader<BonnieTransition>,
+    state: BonnieStateDiscriminants,
+) -> impl Iterator<Item = Entity> + 'a {
+    transitions
+        .read()
+        .filter(move |t| BonnieStateDiscriminants::from(&t.from) == state)
+        .map(|t| t.bonnie)
+}
+
 ///////
 // Plugin
 ///////
@@ -93,123 +619,400 @@ pub struct BonnieStatePlugin;
 
 impl Plugin for BonnieStatePlugin {
     fn build(&self, app: &mut App) {
-        app.init_state::<BonnieState>()
+        app.add_event::<BonnieTransition>()
+            .add_event::<CalmRequest>()
             .init_resource::<GlobalRng>()
+            .init_resource::<BehaviourConfig>()
+            .init_resource::<TransitionTiming>()
+            .init_resource::<MovementConfig>()
+            .init_resource::<SpeedConfig>()
+            .init_resource::<Energy>()
+            .init_resource::<EnabledStates>()
+            .init_resource::<StateHistory>()
+            .init_resource::<StateTimers>()
+            .init_resource::<PatrolRoute>()
+            .init_resource::<WanderRegion>()
+            .init_resource::<LeashConfig>()
+            .init_resource::<OpacityConfig>()
+            .init_resource::<BonnieSprites>()
+            .init_resource::<EdgeBehaviour>()
+            .init_resource::<Stacking>()
+            .configure_sets(PreUpdate, (StateSystems::Exit, StateSystems::Enter).chain())
+            .configure_sets(Update, (StateSystems::Input, StateSystems::Behave).chain())
+            .add_systems(Startup, log_skin)
+            .add_systems(Update, record_state_history)
             .add_systems(
-                Startup,
-                (setup_poop_sprite, setup_scratch_sprite, setup_nerd_sprite),
+                PostUpdate,
+                (handle_state_transitions, mirror_state_timers).chain(),
             )
-            .add_systems(PostUpdate, handle_state_transitions)
+            .add_systems(First, fit_wander_region)
             .add_systems(
                 Update,
-                (
-                    handle_window_closing::<PoopWindow>,
-                    handle_window_closing::<TeachWindow>,
-                    handle_window_closing::<BirdWindow>,
-                    handle_movement,
-                    handle_teaching,
-                    handle_chasing,
-                    update_birds,
-                    handle_idling,
-                )
-                    .chain(),
+                (handle_calm, handle_movement, handle_teleports)
+                    .chain()
+                    .after(StateSystems::Input)
+                    .before(StateSystems::Behave),
             )
-            .add_systems(OnEnter(BonnieState::Meowing), do_meow)
-            .add_systems(
-                OnEnter(BonnieState::Teaching),
-                (block_state, setup_teaching).chain(),
-            )
-            .add_systems(OnEnter(BonnieState::Chasing), (block_state, setup_chase))
-            .add_systems(OnEnter(BonnieState::Pooping), setup_pooping)
-            .add_systems(OnEnter(BonnieState::Bird), setup_bird)
-            .add_systems(OnEnter(BonnieState::Scratch), create_scratch)
-            .add_systems(OnEnter(BonnieState::Idle), (block_state, setup_idling))
-            .add_systems(OnExit(BonnieState::Idle), exit_idling)
-            .add_systems(OnExit(BonnieState::Chasing), exit_chase);
+            .add_systems(Update, apply_opacity.after(StateSystems::Behave))
+            .add_systems(Last, teardown_on_exit);
     }
 }
 
+/// Where each state's own plugin puts its systems, so every exit runs before
+/// the next state's setup and what she does in a state sees where she's moved to.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StateSystems {
+    /// Tidying up after the state she's left, in `PreUpdate`.
+    Exit,
+    /// Setting up the state she's gone into, in `PreUpdate`.
+    Enter,
+    /// Clicks on her windows, in `Update` before she moves.
+    Input,
+    /// Whatever she does while in the state, in `Update` after she's moved.
+    Behave,
+}
+
 ///////
 // State Management
 ///////
 
+/// Trims the wander region to the monitor it's most on, dropping it if it's
+/// off every monitor entirely.
+fn fit_wander_region(monitor_info: Res<MonitorInfo>, mut wander_region: ResMut<WanderRegion>) {
+    if !monitor_info.is_changed() || monitor_info.monitors.is_empty() {
+        return;
+    }
+    let Some(region) = wander_region.0 else {
+        return;
+    };
+
+    let area = |rect: IRect| rect.width() * rect.height();
+    let fitted = monitor_info
+        .monitors
+        .iter()
+        .map(|monitor| region.intersect(monitor.rect()))
+        .max_by_key(|fitted| if fitted.is_empty() { 0 } else { area(*fitted) })
+        .unwrap_or_default();
+
+    if fitted.is_empty() {
+        warn!(
+            "Wander region {:?} is off the monitors, ignoring it.",
+            region
+        );
+        wander_region.0 = None;
+    } else if fitted != region {
+        warn!(
+            "Wander region {:?} doesn't fit on a monitor, using {:?} instead.",
+            region, fitted
+        );
+        wander_region.0 = Some(fitted);
+    }
+}
+
+/// Copies every state machine into [`StateTimers`] once it's done for the frame.
+fn mirror_state_timers(
+    mut state_timers: ResMut<StateTimers>,
+    machine_query: Query<(Entity, &StateMachine)>,
+) {
+    state_timers.0.clear();
+    state_timers.0.extend(
+        machine_query
+            .iter()
+            .map(|(entity, machine)| (entity, StateTimer::from(machine))),
+    );
+}
+
+/// Everything that sways which state she picks next.
+#[derive(SystemParam)]
+struct StateOdds<'w> {
+    behaviour: Res<'w, BehaviourConfig>,
+    energy: Res<'w, Energy>,
+    cpu_load: Res<'w, CpuLoad>,
+    hunger: Res<'w, Hunger>,
+    mood: Res<'w, Mood>,
+    typing: Res<'w, TypingActivity>,
+    boredom: Res<'w, Boredom>,
+}
+
+impl StateOdds<'_> {
+    /// How likely `state` is to be picked, relative to the others.
+    fn weight(&self, state: BonnieStateDiscriminants) -> f32 {
+        self.behaviour.weight(state)
+            * self.energy.weight_scale(state)
+            * self.cpu_load.weight_scale(state)
+            * self.hunger.weight_scale(state)
+            * self.mood.weight_scale(state)
+            * self.typing.weight_scale(state)
+            * self.boredom.weight_scale(state)
+    }
+
+    /// How long to spend in the next state.
+    fn random_duration(&self, rng: &mut impl Rng) -> Duration {
+        self.behaviour
+            .random_duration(rng)
+            .mul_f32(self.energy.timer_scale())
+    }
+}
+
+/// Which states she can go into at the moment.
+#[derive(SystemParam)]
+pub struct StateRules<'w> {
+    enabled_states: Res<'w, EnabledStates>,
+    cursor_tracking: Res<'w, CursorTracking>,
+    patrol_route: Res<'w, PatrolRoute>,
+    audio_config: Res<'w, AudioConfig>,
+}
+
+impl StateRules<'_> {
+    pub fn allowed(&self) -> impl Fn(BonnieStateDiscriminants) -> bool + '_ {
+        let quiet = self.audio_config.is_quiet();
+
+        move |state| {
+            self.enabled_states.contains(state)
+            // she can't chase a cursor that can't be found
+            && (self.cursor_tracking.available || state != BonnieStateDiscriminants::Chasing)
+            // no route, nothing to patrol
+            && (!self.patrol_route.waypoints.is_empty()
+                || state != BonnieStateDiscriminants::Patrolling)
+            // no meowing during quiet hours
+            && (!quiet || state != BonnieStateDiscriminants::Meowing)
+        }
+    }
+}
+
+/// Windows of hers she'd rather not walk over.
+type PetWindowFilter = Or<(With<PoopWindow>, With<BirdWindow>, With<ScratchWindow>)>;
+
+/// Picks each Bonnie's next state once she's done with the last.
+#[derive(SystemParam)]
+struct StatePicker<'w, 's> {
+    rng: ResMut<'w, GlobalRng>,
+    history: Res<'w, StateHistory>,
+    odds: StateOdds<'w>,
+    rules: StateRules<'w>,
+    timing: Res<'w, TransitionTiming>,
+    screen_lock: Res<'w, ScreenLock>,
+    wander_region: Res<'w, WanderRegion>,
+    leash_config: Res<'w, LeashConfig>,
+    pet_windows: Query<'w, 's, &'static Window, PetWindowFilter>,
+}
+
+impl StatePicker<'_, '_> {
+    /// Whether it's her time to be lively rather than settle down.
+    fn lively(&self, now: Duration) -> bool {
+        // with no cursor to watch there's no telling, so she carries on as usual
+        !self.rules.cursor_tracking.available || self.timing.allows(self.screen_lock.user_away(now))
+    }
+
+    /// Where `bonnie` goes next from `current`, with her window being
+    /// `window` on `monitor`. Idling unless she's `lively`, though she'll
+    /// always head back to her leash.
+    fn pick(
+        &mut self,
+        bonnie: Entity,
+        current: &BonnieState,
+        monitor: IRect,
+        window: Option<&Window>,
+        leash_anchor: Option<&LeashAnchor>,
+        lively: bool,
+    ) -> BonnieState {
+        // where her other windows are, so she doesn't walk over them
+        let occupied: Vec<IRect> = self
+            .pet_windows
+            .iter()
+            .filter_map(|window| match window.position {
+                WindowPosition::At(position) => Some(IRect::from_corners(
+                    position,
+                    position + window.physical_size().as_ivec2(),
+                )),
+                _ => None,
+            })
+            .collect();
+
+        let window_size = window.map_or(IVec2::splat(100), |window| {
+            window.physical_size().as_ivec2()
+        });
+        let leash = leash_anchor.map(|anchor| (anchor.0, self.leash_config.radius));
+        let area = WalkArea {
+            monitor,
+            wander_area: window.and_then(|window| {
+                self.wander_region
+                    .positions(window.physical_size().as_ivec2())
+            }),
+            leash,
+            window_size,
+            occupied: &occupied,
+        };
+
+        let next = if !lively {
+            BonnieState::Idle
+        } else {
+            random_state(
+                current,
+                self.history.recent(bonnie),
+                &mut self.rng.0,
+                &area,
+                self.rules.allowed(),
+                |state| self.odds.weight(state),
+            )
+        };
+
+        // thrown or moved off her leash, so head back first
+        if let (Some((anchor, radius)), Some(WindowPosition::At(position))) =
+            (leash, window.map(|window| window.position))
+            && position.as_vec2().distance(anchor.as_vec2()) > radius
+        {
+            return BonnieState::Walking(anchor);
+        }
+
+        next
+    }
+
+    /// How long to spend in the next state.
+    fn random_duration(&mut self) -> Duration {
+        self.odds.random_duration(&mut self.rng.0)
+    }
+}
+
 fn handle_state_transitions(
     time: Res<Time>,
-    mut bonnie: Query<&mut Bonnie>,
-    mut machine: Query<&mut StateMachine>,
-    monitor_query: Query<&Monitor>,
-    mut next_state: ResMut<NextState<BonnieState>>,
-    mut rng: ResMut<GlobalRng>,
+    mut bonnies: Query<(
+        Entity,
+        &mut Bonnie,
+        &mut StateMachine,
+        &BonnieWindow,
+        Option<&LeashAnchor>,
+    )>,
+    window_query: Query<&Window>,
+    monitor_info: Res<MonitorInfo>,
+    mut transitions: EventWriter<BonnieTransition>,
+    mut picker: StatePicker,
+    replay: Option<Res<Replay>>,
 ) {
-    // get machine and bonnie
-    let mut machine = machine.single_mut();
-    let mut bonnie = bonnie.single_mut();
+    // the recording says what she does next
+    if replay.is_some() {
+        return;
+    }
 
-    // tick the machine timer
-    machine.timer.tick(time.delta());
+    let lively = picker.lively(time.elapsed());
 
-    // if the machine can change state and is finished
-    if machine.can_change && machine.timer.finished() {
-        // get the monitor
-        let monitor = monitor_query.single();
+    for (entity, mut bonnie, mut machine, bonnie_window, leash_anchor) in &mut bonnies {
+        // tick the machine timer
+        machine.timer.tick(time.delta());
 
-        // generate a new random state
-        let new_state = random_state(&bonnie.state, &mut rng.0, monitor.physical_size());
-        info!("Changing state from {:?} to {:?}.", bonnie.state, new_state);
+        // if the machine can change state and is finished
+        if machine.can_change() && machine.timer.finished() {
+            // the monitor she's on, so she wanders about that one
+            let window = window_query.get(bonnie_window.0).ok();
+            let Some(monitor) = monitor_info.for_window(window) else {
+                continue;
+            };
 
-        // set the state
-        next_state.set(new_state.clone());
-        bonnie.state = new_state;
+            // not her time to be lively, so settle down until it is
+            if !lively && bonnie.state == BonnieState::Idle {
+                continue;
+            }
 
-        // reset timer
-        machine.timer.reset();
-        machine
-            .timer
-            .set_duration(Duration::from_secs_f32(rng.0.random_range(1.0..4.0)));
-        info!("Timer reset to: {:?}", machine.timer.remaining());
+            // generate a new random state
+            let new_state = picker.pick(
+                entity,
+                &bonnie.state,
+                monitor.rect(),
+                window,
+                leash_anchor,
+                lively,
+            );
+
+            info!(
+                "Changing state of {} from {:?} to {:?}.",
+                entity, bonnie.state, new_state
+            );
+
+            // set the state
+            transitions.send(BonnieTransition {
+                bonnie: entity,
+                from: bonnie.state.clone(),
+                to: new_state.clone(),
+            });
+            bonnie.state = new_state;
+
+            // reset timer
+            machine.timer.reset();
+            machine.timer.set_duration(picker.random_duration());
+            info!("Timer reset to: {:?}", machine.timer.remaining());
+        }
     }
 }
 
-fn random_state(current: &BonnieState, rng: &mut impl Rng, monitor_size: UVec2) -> BonnieState {
-    let mut next_state = BonnieStateDiscriminants::iter()
+fn record_state_history(
+    mut transitions: EventReader<BonnieTransition>,
+    mut history: ResMut<StateHistory>,
+) {
+    for transition in transitions.read() {
+        history.push(
+            transition.bonnie,
+            BonnieStateDiscriminants::from(&transition.to),
+        );
+    }
+}
+
+/// Where a Bonnie could walk to next.
+struct WalkArea<'a> {
+    /// Where the monitor she's on covers on the desktop.
+    monitor: IRect,
+    wander_area: Option<IRect>,
+    leash: Option<(IVec2, f32)>,
+    window_size: IVec2,
+    /// Her other windows, to try not to land on.
+    occupied: &'a [IRect],
+}
+
+impl WalkArea<'_> {
+    /// The corners her window can be placed between, in the wander area if
+    /// there is one and otherwise anywhere it fits on the monitor.
+    fn bounds(&self) -> IRect {
+        self.wander_area.unwrap_or_else(|| {
+            IRect::from_corners(
+                self.monitor.min,
+                (self.monitor.max - self.window_size).max(self.monitor.min),
+            )
+        })
+    }
+}
+
+fn random_state(
+    current: &BonnieState,
+    recent: &[BonnieStateDiscriminants],
+    rng: &mut impl Rng,
+    area: &WalkArea,
+    allowed: impl Fn(BonnieStateDiscriminants) -> bool,
+    weight: impl Fn(BonnieStateDiscriminants) -> f32,
+) -> BonnieState {
+    let candidates: Vec<_> = BonnieStateDiscriminants::iter()
         .filter(|d| *d != BonnieStateDiscriminants::from(current))
-        .choose(rng)
+        .filter(|d| allowed(*d))
+        .collect();
+
+    // skip what she's done lately too, unless that leaves nothing
+    let fresh: Vec<_> = candidates
+        .iter()
+        .copied()
+        .filter(|d| !recent.contains(d))
+        .collect();
+    let candidates = if fresh.is_empty() { candidates } else { fresh };
+
+    // nothing left to pick falls back to idling
+    let next_state = candidates
+        .choose_weighted(rng, |d| weight(*d))
+        .ok()
+        .copied()
         .map_or(BonnieState::Idle, |disc| match disc {
             BonnieStateDiscriminants::Walking => {
-                let x_range = WINDOW_SIZE_BUFFER..(monitor_size.x - WINDOW_SIZE_BUFFER);
-                let y_range = WINDOW_SIZE_BUFFER..(monitor_size.y - WINDOW_SIZE_BUFFER);
-                BonnieState::Walking(IVec2::new(
-                    rng.random_range(x_range) as i32,
-                    rng.random_range(y_range) as i32,
-                ))
+                BonnieState::Walking(unoccupied_walk_target(rng, area))
             }
             _ => BonnieState::from(disc),
         });
 
-    next_state = match next_state {
-        BonnieState::Walking(_) => {
-            // randomly generate a coordinate to go to with some buffer
-            let x_min = 150;
-            let x_max = monitor_size.x.saturating_sub(150);
-            let x_to = if x_max > x_min {
-                rng.random_range(x_min..x_max)
-            } else {
-                rng.random_range(0..monitor_size.x)
-            };
-
-            let y_min = 150;
-            let y_max = monitor_size.y.saturating_sub(150);
-            let y_to = if y_max > y_min {
-                rng.random_range(y_min..y_max)
-            } else {
-                rng.random_range(0..monitor_size.y)
-            };
-
-            BonnieState::Walking((x_to as i32, y_to as i32).into())
-        }
-        _ => next_state,
-    };
-
     info!(
         "Current: {:?}, Next: {:?}",
         BonnieStateDiscriminants::from(current),
@@ -219,698 +1022,897 @@ fn random_state(current: &BonnieState, rng: &mut impl Rng, monitor_size: UVec2)
     next_state
 }
 
-fn block_state(mut machine_query: Query<&mut StateMachine>) {
-    if let Ok(mut machine) = machine_query.get_single_mut() {
-        machine.block();
+/// Somewhere random to walk to that tries not to end up on top of her other
+/// windows, giving up after a few goes.
+fn unoccupied_walk_target(rng: &mut impl Rng, area: &WalkArea) -> IVec2 {
+    let mut target = walk_target(rng, area);
+
+    for _ in 1..WALK_TARGET_ATTEMPTS {
+        let landing = IRect::from_corners(target, target + area.window_size);
+        if !area
+            .occupied
+            .iter()
+            .any(|rect| !rect.intersect(landing).is_empty())
+        {
+            break;
+        }
+
+        target = walk_target(rng, area);
     }
+
+    target
 }
 
-///////
-// Window management
-///////
+/// Somewhere random on `monitor` for a Bonnie with a `window_size` window to
+/// walk to, keeping to the wander region.
+pub fn random_walk_target(
+    rng: &mut impl Rng,
+    monitor: IRect,
+    wander_region: &WanderRegion,
+    window_size: IVec2,
+) -> IVec2 {
+    walk_target(
+        rng,
+        &WalkArea {
+            monitor,
+            wander_area: wander_region.positions(window_size),
+            leash: None,
+            window_size,
+            occupied: &[],
+        },
+    )
+}
 
-#[derive(Component)]
-struct PoopWindow;
+/// Somewhere random for her window to walk to, within the wander area or her
+/// leash if she has either.
+fn walk_target(rng: &mut impl Rng, area: &WalkArea) -> IVec2 {
+    // somewhere evenly spread over her leash, as far as her window fits, so
+    // nothing has to pull it back in later and take her off the leash
+    if let Some((anchor, radius)) = area.leash {
+        let angle = rng.random_range(0.0..TAU);
+        let distance = radius * rng.random::<f32>().sqrt();
+        let bounds = area.bounds();
+        return (anchor + (Vec2::from_angle(angle) * distance).round().as_ivec2())
+            .clamp(bounds.min, bounds.max);
+    }
+
+    if let Some(wander_area) = area.wander_area {
+        return IVec2::new(
+            rng.random_range(wander_area.min.x..=wander_area.max.x),
+            rng.random_range(wander_area.min.y..=wander_area.max.y),
+        );
+    }
 
-#[derive(Component)]
-struct TeachWindow;
+    // randomly generate a coordinate to go to with some buffer, as far as
+    // her whole window fits
+    let bounds = area.bounds();
+    IVec2::new(
+        random_along(rng, bounds.min.x, bounds.max.x),
+        random_along(rng, bounds.min.y, bounds.max.y),
+    )
+}
 
-#[derive(Component)]
-struct NerdWindow;
+/// Somewhere between `min` and `max`, keeping `WALK_EDGE_BUFFER` clear of
+/// either end when there's room to.
+fn random_along(rng: &mut impl Rng, min: i32, max: i32) -> i32 {
+    if max - min > WALK_EDGE_BUFFER * 2 {
+        rng.random_range(min + WALK_EDGE_BUFFER..max - WALK_EDGE_BUFFER)
+    } else if max > min {
+        rng.random_range(min..max)
+    } else {
+        min
+    }
+}
 
-#[derive(Component, Hash)]
-struct BirdWindow;
+///////
+// Window management
+///////
 
-#[derive(Component, Debug, Default)]
-struct BirdDirection {
-    v: IVec2,
+/// The Bonnie that spawned a transient window.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct OwnedBy(pub Entity);
+
+/// A transient window, who opened it and what sort it is.
+type TransientWindow = (
+    Entity,
+    Option<&'static OwnedBy>,
+    Has<PoopWindow>,
+    Has<TeachWindow>,
+    Has<Fading>,
+);
+
+/// Every sort of window a Bonnie opens.
+type TransientWindowFilter = Or<(
+    With<PoopWindow>,
+    With<TeachWindow>,
+    With<NerdWindow>,
+    With<BirdWindow>,
+    With<ScratchWindow>,
+)>;
+
+/// Every transient window a Bonnie opens, with the cameras and sprites
+/// that draw them.
+#[derive(SystemParam)]
+struct TransientWindows<'w, 's> {
+    windows: Query<'w, 's, TransientWindow, TransientWindowFilter>,
+    cameras: Query<'w, 's, (Entity, &'static Camera, &'static RenderLayers)>,
+    layered: Query<'w, 's, (Entity, &'static RenderLayers)>,
 }
 
-#[derive(Component)]
-struct ScratchWindow;
+impl TransientWindows<'_, '_> {
+    /// Closes the windows, their cameras and anything drawn on a layer only
+    /// they use, just `owner`'s if given. Poops are left alone unless `poops`
+    /// is set.
+    fn despawn(&self, commands: &mut Commands, owner: Option<Entity>, poops: bool) {
+        self.despawn_matching(commands, |owned_by, is_poop| {
+            (poops || !is_poop)
+                && owner.is_none_or(|owner| owned_by.is_some_and(|owned_by| owned_by.0 == owner))
+        });
+    }
 
-fn handle_window_closing<T: Component>(
-    mut commands: Commands,
-    mut mouse_events: EventReader<MouseButtonInput>,
-    windows: Query<(), With<T>>,
-    mut machine: Query<&mut StateMachine>,
-    render_layer_query: Query<(Entity, &RenderLayers)>,
-    nerd_query: Query<Entity, With<NerdWindow>>,
-    asset_server: Res<AssetServer>,
-) {
-    for event in mouse_events.read() {
-        if event.button == MouseButton::Left
-            && event.state == ButtonState::Pressed
-            && windows.get(event.window).is_ok()
-        {
-            commands.entity(event.window).despawn_recursive();
+    /// Closes the windows `closes` picks out by who opened them and whether
+    /// they're poops, along with their cameras and anything drawn on a layer
+    /// only they use. Returns whether there were any.
+    fn despawn_matching(
+        &self,
+        commands: &mut Commands,
+        closes: impl Fn(Option<&OwnedBy>, bool) -> bool,
+    ) -> bool {
+        // windows closing, and whether their layer is theirs alone
+        let mut closing = HashMap::new();
+        for (window, owned_by, is_poop, is_teach, is_fading) in &self.windows {
+            if !closes(owned_by, is_poop) {
+                continue;
+            }
 
-            if TypeId::of::<T>() == TypeId::of::<TeachWindow>() {
-                // finish state machine
-                if let Ok(mut machine) = machine.get_single_mut() {
-                    machine.finish();
-                }
+            commands.entity(window).despawn_recursive();
+            closing.insert(window, is_teach || is_fading);
+        }
 
-                // kill nerd window
-                commands.entity(nerd_query.single()).despawn_recursive();
+        let mut layers = Vec::new();
+        for (camera_entity, camera, render_layers) in &self.cameras {
+            let RenderTarget::Window(WindowRef::Entity(target)) = camera.target else {
+                continue;
+            };
 
-                // clear render layer ready for next image
-                for (entity, render_layers) in &render_layer_query {
-                    if *render_layers == RenderLayers::layer(TEACH_LAYER) {
-                        commands.entity(entity).despawn_recursive();
-                    }
-                }
-            } else if TypeId::of::<T>() == TypeId::of::<PoopWindow>() {
-                commands.spawn((
-                    AudioPlayer::new(asset_server.load("munch.ogg")),
-                    PlaybackSettings {
-                        mode: PlaybackMode::Once,
-                        ..default()
-                    },
-                ));
-            } else if TypeId::of::<T>() == TypeId::of::<BirdWindow>() {
-                commands.spawn((
-                    AudioPlayer::new(asset_server.load("kakapo-death.ogg")),
-                    PlaybackSettings {
-                        mode: PlaybackMode::Once,
-                        ..default()
-                    },
-                ));
+            match closing.get(&target) {
+                Some(true) => layers.push(render_layers),
+                Some(false) => commands.entity(camera_entity).despawn_recursive(),
+                None => {}
+            }
+        }
+
+        // teach and fade layers, the rest share sprites between windows
+        for (entity, render_layers) in &self.layered {
+            if layers.contains(&render_layers) {
+                commands.entity(entity).despawn_recursive();
             }
         }
+
+        !closing.is_empty()
     }
 }
 
-///////
-// Movement system
-///////
-
-fn handle_movement(
-    time: Res<Time>,
-    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
-    monitor_query: Query<&Monitor>,
-    state: Res<State<BonnieState>>,
-    cursor_pos: Res<GlobalCursorPosition>,
+/// Closes every window but Bonnie's own and silences her before the app
+/// closes, so nothing's left rendering or playing into a closing app.
+fn teardown_on_exit(
+    mut commands: Commands,
+    mut exit_events: EventReader<AppExit>,
+    transient_windows: TransientWindows,
+    other_transients: Query<Entity, With<Transient>>,
+    audio_query: Query<(Entity, Option<&AudioSink>), With<AudioPlayer>>,
 ) {
-    let Ok(mut window) = window_query.get_single_mut() else {
+    if exit_events.read().next().is_none() {
         return;
-    };
-
-    let monitor = monitor_query.single();
-
-    let target_position = match *state.get() {
-        BonnieState::Walking(target) => target,
-        BonnieState::Chasing => cursor_pos
-            .0
-            .map(|v| v.as_ivec2() - IVec2::new(90, 147))
-            .expect("Cursor position not available"),
-        _ => return,
-    };
-
-    let current_position = match window.position {
-        WindowPosition::At(pos) => pos,
-        _ => IVec2::ZERO,
-    };
+    }
 
-    let direction = (target_position - current_position).as_vec2().normalize();
-    let speed = calculate_movement_speed(monitor.physical_size(), state.get());
-    let delta = direction * speed * time.delta_secs_f64() as f32;
+    transient_windows.despawn(&mut commands, None, true);
 
-    let remaining_vector = target_position - current_position;
-    let remaining_length = remaining_vector.as_vec2().length();
-    let step_length = delta.length();
+    // the laser, confetti, debug overlay and the like, quietly skipping the
+    // ones already closed above
+    for entity in &other_transients {
+        commands.entity(entity).try_despawn_recursive();
+    }
 
-    if remaining_length <= step_length {
-        window.position = WindowPosition::At(target_position);
-    } else {
-        window.position = WindowPosition::At(current_position + delta.round().as_ivec2());
+    for (entity, sink) in &audio_query {
+        if let Some(sink) = sink {
+            sink.stop();
+        }
+        commands.entity(entity).despawn();
     }
 }
 
-fn calculate_movement_speed(resolution: UVec2, state: &BonnieState) -> f32 {
-    let diagonal = ((resolution.x.pow(2) + resolution.y.pow(2)) as f32).sqrt();
-    let base_speed = match state {
-        BonnieState::Chasing => 2.0,
-        BonnieState::Teaching => 3.0,
-        BonnieState::Bird => 1.5,
-        _ => 1.0,
+/// Stereo pan for a sound coming from `window`, across the monitor it's on.
+pub fn window_pan(window: &Window, monitor_info: &MonitorInfo) -> f32 {
+    let Some(centre) = window_centre(window) else {
+        return 0.0;
+    };
+    let Some(monitor) = monitor_info
+        .monitor_at(centre)
+        .or_else(|| monitor_info.active())
+    else {
+        return 0.0;
     };
-    diagonal * 0.15 * base_speed
-}
 
-///////
-// State-Specific Behaviour
-///////
+    stereo_pan(centre.x - monitor.origin.x, monitor.size.x)
+}
 
-/////// Idling
-fn setup_idling(
-    mut bonnie_query: Query<(&mut Bonnie, &mut Sprite)>,
-    asset_server: Res<AssetServer>,
-) {
-    let bonnie_asset = asset_server.load("BonSleep.png");
+/// Windows clicked with the left mouse button in the events not yet read.
+pub fn left_clicked<'a>(
+    mouse_events: &'a mut EventReader<MouseButtonInput>,
+) -> impl Iterator<Item = Entity> + 'a {
+    mouse_events
+        .read()
+        .filter(|event| event.button == MouseButton::Left && event.state == ButtonState::Pressed)
+        .map(|event| event.window)
+}
 
-    for (_, mut sprite) in &mut bonnie_query {
-        sprite.image = bonnie_asset.clone();
+/// Where the middle of `window` is on the desktop, if it's been placed.
+pub fn window_centre(window: &Window) -> Option<IVec2> {
+    match window.position {
+        WindowPosition::At(position) => Some(position + window.physical_size().as_ivec2() / 2),
+        _ => None,
     }
 }
 
-fn handle_idling(
-    mut machine: Query<&mut StateMachine>,
-    bonnie_query: Query<&mut Bonnie>,
-    global_cursor_pos: Res<GlobalCursorPosition>,
-    window_query: Query<&mut Window, With<PrimaryWindow>>,
+/// Despawns a window along with the camera drawing into it.
+pub fn despawn_window(
+    commands: &mut Commands,
+    window: Entity,
+    camera_query: &Query<(Entity, &Camera)>,
 ) {
-    let bonnie = bonnie_query.get_single().expect("Failed to get Bonnie.");
-    if let BonnieState::Idle = bonnie.state {
-        // get window and machine
-        let window = window_query.single();
-        let mut machine = machine.single_mut();
-
-        // if cursor near bonnie, wake her up
-        // get global cursor pos
-        if let Some(cursor_pos) = global_cursor_pos.0 {
-            // get bonnie position
-            if let WindowPosition::At(bonnie_pos) = window.position {
-                let diff = (bonnie_pos + IVec2::new(90, 147)).as_vec2() - cursor_pos;
-                let dist = diff.length();
-
-                // if cursor near bonnie, change state
-                if dist < 70.0 {
-                    info!("Waking up...");
-                    machine.finish();
-                }
-            }
+    commands.entity(window).despawn_recursive();
+
+    for (camera_entity, camera) in camera_query {
+        if let RenderTarget::Window(WindowRef::Entity(target)) = camera.target
+            && target == window
+        {
+            commands.entity(camera_entity).despawn_recursive();
         }
     }
 }
 
-fn exit_idling(
-    mut bonnie_query: Query<(&mut Bonnie, &mut Sprite)>,
-    asset_server: Res<AssetServer>,
-) {
-    let bonnie_asset = asset_server.load("BonNormal.png");
-
-    for (_, mut sprite) in &mut bonnie_query {
-        sprite.image = bonnie_asset.clone();
-    }
+/// Stopping a Bonnie mid-state and sending her somewhere else, for anything
+/// acting on input rather than waiting for her timer.
+///
+/// An interrupt lands in a fixed order: her transient windows are queued to
+/// close first, then her own state block is lifted, the transition sent and
+/// her state and timer set in place. The windows are gone by the time the old
+/// state's exit and the new state's setup run on the transition next
+/// `PreUpdate`, so they never see each other's windows. Blocks for pausing, a
+/// locked screen or a fullscreen app are left alone, so she'll still hold
+/// still in the new state until those lift. Interrupt each Bonnie at most
+/// once per system run.
+#[derive(SystemParam)]
+pub struct Interrupts<'w, 's> {
+    commands: Commands<'w, 's>,
+    transient_windows: TransientWindows<'w, 's>,
+    transitions: EventWriter<'w, BonnieTransition>,
+    windows_closed: WindowsClosed<'w>,
 }
 
-/////// Pooping
+impl Interrupts<'_, '_> {
+    /// Drops whatever `entity` is doing for `state`, starting its timer
+    /// afresh. Interrupting to the state she's already in leaves her windows
+    /// and block as they are and only restarts her timer.
+    pub fn interrupt_to(
+        &mut self,
+        entity: Entity,
+        bonnie: &mut Bonnie,
+        machine: &mut StateMachine,
+        state: BonnieState,
+    ) {
+        machine.timer.reset();
 
-fn setup_poop_sprite(mut commands: Commands, asset_server: Res<AssetServer>) {
-    // get the sprite
-    let mut poop_sprite = Sprite::from_image(asset_server.load("BonPoop.png"));
-    poop_sprite.custom_size = Some(Vec2::new(40.0, 40.0));
+        if bonnie.state == state {
+            return;
+        }
 
-    // add to poop render layer
-    commands.spawn((poop_sprite, RenderLayers::layer(POOP_LAYER)));
-}
+        self.tear_down(entity, machine);
+        self.transitions.send(BonnieTransition {
+            bonnie: entity,
+            from: bonnie.state.clone(),
+            to: state.clone(),
+        });
+        bonnie.state = state;
+    }
 
-fn setup_pooping(
-    mut commands: Commands,
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    mut machine: Query<&mut StateMachine>,
-) {
-    let window = window_query.single();
-
-    let poop_window = commands
-        .spawn((
-            Window {
-                transparent: true,
-                composite_alpha_mode: get_composite_mode(),
-                decorations: false,
-                resizable: false,
-                has_shadow: false,
-                titlebar_shown: false,
-                titlebar_transparent: false,
-                titlebar_show_buttons: false,
-                titlebar_show_title: false,
-                title: "Poop!".to_string(),
-                name: Some("bonnie.buddy".into()),
-                resolution: (40.0, 40.0).into(),
-                resize_constraints: WindowResizeConstraints {
-                    min_width: 40.0,
-                    min_height: 40.0,
-                    max_width: 40.0,
-                    max_height: 40.0,
-                },
-                present_mode: PresentMode::AutoNoVsync,
-                window_level: WindowLevel::AlwaysOnTop,
-                position: window.position,
-                ..default()
-            },
-            PoopWindow,
-        ))
-        .id();
+    /// Like [`Self::interrupt_to`], but sets `state` up afresh even if she's
+    /// already in it, for a state with new windows to open.
+    pub fn restart_to(
+        &mut self,
+        entity: Entity,
+        bonnie: &mut Bonnie,
+        machine: &mut StateMachine,
+        state: BonnieState,
+    ) {
+        if bonnie.state != state {
+            self.interrupt_to(entity, bonnie, machine, state);
+            return;
+        }
 
-    commands.spawn((
-        Camera2d,
-        Camera {
-            target: RenderTarget::Window(WindowRef::Entity(poop_window)),
-            ..default()
-        },
-        RenderLayers::layer(POOP_LAYER),
-    ));
+        self.tear_down(entity, machine);
+        machine.timer.reset();
+        self.transitions.send(BonnieTransition {
+            bonnie: entity,
+            from: state.clone(),
+            to: state,
+        });
+    }
+
+    /// Closes `entity`'s transient windows and lifts her own state block.
+    fn tear_down(&mut self, entity: Entity, machine: &mut StateMachine) {
+        self.transient_windows
+            .despawn(&mut self.commands, Some(entity), false);
+        machine.unblock();
+    }
 
-    machine.single_mut().finish();
+    /// Closes every Bonnie's windows, poops too if `poops` is set, which she
+    /// doesn't like if there were any.
+    fn dismiss_windows(&mut self, poops: bool) {
+        let closed = self
+            .transient_windows
+            .despawn_matching(&mut self.commands, |owned_by, is_poop| {
+                (poops || !is_poop) && owned_by.is_some()
+            });
+
+        if closed {
+            self.windows_closed.on_her();
+        }
+    }
 }
 
-/////// Chasing
+/// Asks every Bonnie to stop what she's doing and go back to idling.
+#[derive(Event, Debug)]
+pub struct CalmRequest {
+    /// Clear away her poops too, dismissing every window she's opened.
+    pub poops: bool,
+}
 
-fn setup_chase(
-    mut bonnie_query: Query<(&mut Bonnie, &mut Sprite)>,
-    asset_server: Res<AssetServer>,
+fn handle_calm(
+    mut calm_requests: EventReader<CalmRequest>,
+    mut bonnies: Query<(Entity, &mut Bonnie, &mut StateMachine)>,
+    mut interrupts: Interrupts,
 ) {
-    let bonnie_asset = asset_server.load("BonAngryMouth.png");
+    let Some(poops) = calm_requests
+        .read()
+        .map(|request| request.poops)
+        .reduce(|a, b| a || b)
+    else {
+        return;
+    };
+
+    if poops {
+        info!("Dismissing every window, sending every Bonnie back to idle.");
+    } else {
+        info!("Manual calm issued, sending every Bonnie back to idle.");
+    }
+    interrupts.dismiss_windows(poops);
 
-    for (_, mut sprite) in &mut bonnie_query {
-        sprite.image = bonnie_asset.clone();
+    for (entity, mut bonnie, mut machine) in &mut bonnies {
+        interrupts.interrupt_to(entity, &mut bonnie, &mut machine, BonnieState::Idle);
     }
 }
 
-fn handle_chasing(
-    mut machine: Query<&mut StateMachine>,
-    bonnie_query: Query<&mut Bonnie>,
-    global_cursor_pos: Res<GlobalCursorPosition>,
-    window_query: Query<&mut Window, With<PrimaryWindow>>,
-) {
-    let bonnie = bonnie_query.get_single().expect("Failed to get Bonnie.");
-    if let BonnieState::Chasing = bonnie.state {
-        // get window and machine
-        let window = window_query.single();
-        let mut machine = machine.single_mut();
-
-        // get global cursor pos
-        if let Some(cursor_pos) = global_cursor_pos.0 {
-            // get bonnie position
-            if let WindowPosition::At(bonnie_pos) = window.position {
-                let diff = (bonnie_pos + IVec2::new(90, 147)).as_vec2() - cursor_pos;
-                let dist = diff.length();
-
-                // if cursor near bonnie, change state
-                if dist < 35.0 {
-                    info!("Close enough, finishing...");
-                    machine.finish();
+///////
+// Movement system
+///////
+
+/// Where each moving state is taking her.
+#[derive(SystemParam)]
+struct MovementTargets<'w, 's> {
+    cursor_pos: Res<'w, GlobalCursorPosition>,
+    cursor_history: Res<'w, CursorHistory>,
+    chase_config: Res<'w, ChaseConfig>,
+    laser_query: Query<'w, 's, &'static LaserDot>,
+    patrol_route: Res<'w, PatrolRoute>,
+}
+
+impl MovementTargets<'_, '_> {
+    /// Where her window's heading in `state`, if anywhere.
+    fn target(
+        &self,
+        state: &BonnieState,
+        dock_target: Option<&DockTarget>,
+        stalking: Option<&Stalking>,
+    ) -> Option<IVec2> {
+        match state {
+            BonnieState::Walking(target) => Some(*target),
+            BonnieState::Patrolling(leg) => self.patrol_route.waypoint(*leg),
+            BonnieState::Chasing => match (self.laser_query.get_single(), self.cursor_pos.0) {
+                // the laser's exactly where she should go
+                (Ok(dot), _) => Some(dot.0.as_ivec2() - FEET_OFFSET),
+                (Err(_), Some(cursor)) => {
+                    // aim where the cursor's heading
+                    let lead = (self.cursor_history.velocity() * self.chase_config.lead)
+                        .clamp_length_max(MAX_CHASE_LEAD);
+                    Some((cursor + lead).as_ivec2() - FEET_OFFSET)
                 }
-            }
+                (Err(_), None) => None,
+            },
+            BonnieState::Idle => dock_target.map(|dock| dock.0),
+            BonnieState::Bird => stalking.map(|stalk| stalk.0),
+            _ => None,
         }
     }
 }
 
-fn exit_chase(mut bonnie_query: Query<(&mut Bonnie, &mut Sprite)>, asset_server: Res<AssetServer>) {
-    let bonnie_asset = asset_server.load("BonNormal.png");
-
-    for (_, mut sprite) in &mut bonnie_query {
-        sprite.image = bonnie_asset.clone();
-    }
+/// How she's allowed to move, and how fast.
+#[derive(SystemParam)]
+struct MovementRules<'w> {
+    movement_config: Res<'w, MovementConfig>,
+    speed_config: Res<'w, SpeedConfig>,
+    physics_config: Res<'w, PhysicsConfig>,
+    edge_behaviour: Res<'w, EdgeBehaviour>,
+    wander_region: Res<'w, WanderRegion>,
 }
 
-/////// Teaching
+/// A Bonnie and what she needs to walk about.
+type Walker = (
+    Entity,
+    &'static mut Bonnie,
+    &'static BonnieWindow,
+    &'static mut FractionalPosition,
+    &'static mut ChaseVelocity,
+    Option<&'static DockTarget>,
+    Option<&'static Stalking>,
+);
+
+/// Bonnies free to walk, not flying, held, hopping monitors or pouncing.
+type FreeToWalk = (
+    Without<Airborne>,
+    Without<Dragging>,
+    Without<Teleport>,
+    Without<Pounce>,
+);
 
-fn handle_teaching(
-    mut teach_window: Query<&mut Window, (With<TeachWindow>, Without<PrimaryWindow>)>,
-    bonnie_window: Query<&Window, With<PrimaryWindow>>,
+fn handle_movement(
+    mut commands: Commands,
     time: Res<Time>,
-    monitor_query: Query<&Monitor>,
+    mut bonnie_query: Query<Walker, FreeToWalk>,
+    mut window_query: Query<&mut Window>,
+    monitor_info: Res<MonitorInfo>,
+    targets: MovementTargets,
+    rules: MovementRules,
 ) {
-    // get the teach window
-    let Ok(mut window) = teach_window.get_single_mut() else {
-        return;
-    };
+    for (
+        entity,
+        mut bonnie,
+        bonnie_window,
+        mut fractional_position,
+        mut chase_velocity,
+        dock_target,
+        stalking,
+    ) in &mut bonnie_query
+    {
+        let Ok(mut window) = window_query.get_mut(bonnie_window.0) else {
+            continue;
+        };
 
-    // get bonnies position
-    let bonnie_pos = match bonnie_window.single().position {
-        WindowPosition::At(pos) => pos,
-        _ => IVec2::ZERO,
-    };
+        // her own monitor's size and scale, which can differ between Bonnies
+        let Some(monitor) = monitor_info.for_window(Some(&window)) else {
+            continue;
+        };
 
-    let target = bonnie_pos + IVec2::new(-170, 200);
+        let Some(target_position) = targets.target(&bonnie.state, dock_target, stalking) else {
+            continue;
+        };
 
-    // get the current teach position
-    let current_pos = match window.position {
-        WindowPosition::At(pos) => pos,
-        _ => IVec2::ZERO,
-    };
+        let current_position = match window.position {
+            WindowPosition::At(pos) => pos,
+            _ => IVec2::ZERO,
+        };
+
+        // something else moved her, pick up from where she is now
+        if fractional_position.0.round().as_ivec2() != current_position {
+            fractional_position.0 = current_position.as_vec2();
+        }
+
+        // with gravity on she can only walk along the ground
+        let mut target_position = if rules.physics_config.gravity {
+            IVec2::new(target_position.x, current_position.y)
+        } else {
+            target_position
+        };
+
+        let window_size = window.physical_size().as_ivec2();
+        let target_monitor = monitor_info.monitor_at(target_position + window_size / 2);
+
+        // rather than walking across the gap, hop over to the nearest spot on
+        // the target's monitor and walk the rest of the way there
+        if rules.movement_config.fade_between_monitors
+            && matches!(
+                bonnie.state,
+                BonnieState::Walking(_) | BonnieState::Patrolling(_)
+            )
+            && let Some(destination) = target_monitor
+            && monitor_info.monitor_at(current_position + window_size / 2) != Some(destination)
+        {
+            let bounds = destination.rect();
+            let arrival =
+                current_position.clamp(bounds.min, (bounds.max - window_size).max(bounds.min));
+
+            info!("Bonnie's hopping over to monitor {:?}.", destination.name);
+            commands.entity(entity).insert(Teleport {
+                to: arrival,
+                timer: Timer::from_seconds(TELEPORT_FADE_SECS, TimerMode::Once),
+                arrived: false,
+            });
+            continue;
+        }
 
-    let monitor = monitor_query.single();
+        // keep her whole window on the target's monitor, or in the wander
+        // region when walking
+        let (min, max) = match (&bonnie.state, rules.wander_region.positions(window_size)) {
+            (BonnieState::Walking(_), Some(area)) => (area.min, area.max),
+            _ => match target_monitor {
+                Some(on) => {
+                    let bounds = on.rect();
+                    (bounds.min, (bounds.max - window_size).max(bounds.min))
+                }
+                None => {
+                    let bounds = monitor.rect();
+                    (bounds.min, (bounds.max - window_size).max(bounds.min))
+                }
+            },
+        };
+        let inward = push_inward(target_position, min, max);
+
+        if inward != IVec2::ZERO {
+            match (*rules.edge_behaviour, &mut bonnie.state) {
+                // walk off the edge she was heading past, like the bird
+                (EdgeBehaviour::Bounce, BonnieState::Walking(target)) => {
+                    if inward.x != 0 {
+                        let edge = if inward.x > 0 { min.x } else { max.x };
+                        target.x = 2 * edge - target.x;
+                    }
+                    if inward.y != 0 {
+                        let edge = if inward.y > 0 { min.y } else { max.y };
+                        target.y = 2 * edge - target.y;
+                    }
+                    *target = target.clamp(min, max);
+                    target_position = *target;
+                }
+                _ => target_position = target_position.clamp(min, max),
+            }
+        }
 
-    // get direction and delta
-    let direction = (target - current_pos).as_vec2().normalize();
-    let speed = calculate_movement_speed(monitor.physical_size(), &BonnieState::Teaching);
-    let delta = direction * speed * (time.delta_secs_f64() as f32);
+        // measured from her fractional position, so tiny steps still add up
+        let remaining_vector = target_position.as_vec2() - fractional_position.0;
+        let direction = remaining_vector.normalize_or_zero();
+        let remaining_length = remaining_vector.length();
+        let speed = calculate_movement_speed(
+            monitor.size,
+            &bonnie.state,
+            rules.movement_config.speed_multiplier,
+            &rules.speed_config,
+        );
+        let dt = time.delta_secs_f64() as f32;
+
+        // pouncing on a bird's much like chasing the cursor
+        let delta = if bonnie.state == BonnieState::Chasing || stalking.is_some() {
+            // pounce up to speed, easing off as she closes in
+            let desired = direction * speed.min(remaining_length * CHASE_ARRIVE_RATE);
+            chase_velocity.0 = chase_velocity
+                .0
+                .move_towards(desired, speed * CHASE_ACCELERATION * dt);
+            chase_velocity.0 * dt
+        } else {
+            direction * speed * dt
+        };
 
-    // calculate remaining
-    let remaining_vector = target - current_pos;
-    let remaining_length = remaining_vector.as_vec2().length();
-    let step_length = delta.length();
+        fractional_position.0 = step(
+            fractional_position.0,
+            target_position.as_vec2(),
+            delta,
+            monitor.scale_factor as f32,
+        );
 
-    // only step if needed
-    if remaining_length <= step_length {
-        window.position = WindowPosition::At(target);
+        // on to the next waypoint
+        if fractional_position.0 == target_position.as_vec2()
+            && let BonnieState::Patrolling(leg) = &mut bonnie.state
+        {
+            *leg += 1;
+        }
+
+        // only round for placing the window
+        let new_position = fractional_position.0.round().as_ivec2();
+        if new_position != current_position {
+            window.position = WindowPosition::At(new_position);
+        }
+    }
+}
+
+/// Her fractional `position` after moving `delta` towards `target`, landing on
+/// it once she's close enough rather than overshooting. It's only rounded for
+/// placing her window, so steps of less than a pixel still add up.
+fn step(position: Vec2, target: Vec2, delta: Vec2, scale_factor: f32) -> Vec2 {
+    if arrived(position, target, delta.length(), scale_factor) {
+        target
     } else {
-        window.position = WindowPosition::At(current_pos + delta.round().as_ivec2());
+        position + delta
     }
 }
 
-fn setup_nerd_sprite(mut commands: Commands, asset_server: Res<AssetServer>) {
-    // get the sprite
-    let mut nerd_sprite = Sprite::from_image(asset_server.load("BonNerd.png"));
-    nerd_sprite.custom_size = Some(Vec2::new(35.0, 35.0));
+/// Whether `position` is close enough to `target` to count as there, taking
+/// steps of `step_length` at `scale_factor`.
+fn arrived(position: Vec2, target: Vec2, step_length: f32, scale_factor: f32) -> bool {
+    // within a logical pixel counts as there, or at fractional scale
+    // factors rounding to physical pixels leaves her shimmering about it
+    position.distance(target) <= step_length.max(ARRIVE_DISTANCE * scale_factor)
+}
 
-    // add to nerd render layer
-    commands.spawn((nerd_sprite, RenderLayers::layer(NERD_LAYER)));
+/// A Bonnie fading out to reappear at `to` on another monitor, then fading
+/// back in there.
+#[derive(Component, Debug)]
+struct Teleport {
+    to: IVec2,
+    timer: Timer,
+    arrived: bool,
 }
 
-fn setup_teaching(
+fn handle_teleports(
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut rng: ResMut<GlobalRng>,
-    mut machine: Query<&mut StateMachine>,
-    bonnie_window: Query<&Window, With<PrimaryWindow>>,
+    mut bonnie_query: Query<(
+        Entity,
+        &BonnieWindow,
+        &mut Teleport,
+        &mut Sprite,
+        &mut FractionalPosition,
+    )>,
+    mut window_query: Query<&mut Window>,
+    opacity: Res<OpacityConfig>,
+    time: Res<Time>,
 ) {
-    info!("Blocking state machine...");
-    machine.single_mut().block();
-
-    let pos = WindowPosition::At(IVec2::new(-1000, 300));
-
-    let teach_window = commands
-        .spawn((
-            Window {
-                transparent: true,
-                composite_alpha_mode: get_composite_mode(),
-                decorations: false,
-                resizable: false,
-                has_shadow: false,
-                titlebar_shown: false,
-                titlebar_transparent: false,
-                titlebar_show_buttons: false,
-                titlebar_show_title: false,
-                title: "Education!".to_string(),
-                name: Some("bonnie.buddy".into()),
-                resolution: (300.0, 300.0).into(),
-                resize_constraints: WindowResizeConstraints {
-                    min_width: 300.0,
-                    min_height: 300.0,
-                    max_width: 300.0,
-                    max_height: 300.0,
-                },
-                window_level: WindowLevel::AlwaysOnTop,
-                position: pos,
-                ..default()
-            },
-            TeachWindow,
-        ))
-        .id();
-
-    // spawn a camera2d on TEACH_LAYER
-    commands.spawn((
-        Camera2d,
-        Camera {
-            target: RenderTarget::Window(WindowRef::Entity(teach_window)),
-            ..default()
-        },
-        RenderLayers::layer(TEACH_LAYER),
-    ));
-    // get the sprite
-    let mut teach_sprite =
-        Sprite::from_image(asset_server.load(random_education_image(&mut rng.0)));
-    teach_sprite.custom_size = Some(Vec2::new(300.0, 300.0));
-
-    // spawn the sprite on the render layer 1
-    commands.spawn((teach_sprite, RenderLayers::layer(TEACH_LAYER)));
-
-    // get bonnies position
-    let bonnie_pos = match bonnie_window.single().position {
-        WindowPosition::At(pos) => pos,
-        _ => IVec2::ZERO,
-    };
+    for (entity, bonnie_window, mut teleport, mut sprite, mut fractional_position) in
+        &mut bonnie_query
+    {
+        teleport.timer.tick(time.delta());
+
+        let fraction = teleport.timer.fraction();
+        let visible = if teleport.arrived {
+            fraction
+        } else {
+            1.0 - fraction
+        };
+        sprite.color.set_alpha(visible * opacity.target());
 
-    let nerd_pos = WindowPosition::At(bonnie_pos + IVec2::new(140, 140));
-
-    let nerd_window = commands
-        .spawn((
-            Window {
-                transparent: true,
-                composite_alpha_mode: get_composite_mode(),
-                decorations: false,
-                resizable: false,
-                has_shadow: false,
-                titlebar_shown: false,
-                titlebar_transparent: false,
-                titlebar_show_buttons: false,
-                titlebar_show_title: false,
-                title: "Education!".to_string(),
-                name: Some("bonnie.buddy".into()),
-                resolution: (35.0, 35.0).into(),
-                resize_constraints: WindowResizeConstraints {
-                    min_width: 35.0,
-                    min_height: 35.0,
-                    max_width: 35.0,
-                    max_height: 35.0,
-                },
-                window_level: WindowLevel::AlwaysOnTop,
-                position: nerd_pos,
-                ..default()
-            },
-            NerdWindow,
-        ))
-        .id();
-
-    // spawn a camera2d on NERD_LAYER
-    commands.spawn((
-        Camera2d,
-        Camera {
-            target: RenderTarget::Window(WindowRef::Entity(nerd_window)),
-            ..default()
-        },
-        RenderLayers::layer(NERD_LAYER),
-    ));
-}
-
-fn random_education_image(rng: &mut impl Rng) -> String {
-    const IMAGES: &[&str] = &[
-        "educational/meme1.png",
-        "educational/meme2.png",
-        "educational/meme3.png",
-        "educational/meme4.png",
-        "educational/meme5.png",
-        "educational/text/tip1.png",
-        "educational/text/tip2.png",
-        "educational/text/tip3.png",
-        "educational/text/tip4.png",
-        "educational/text/tip5.png",
-        "educational/text/tip6.png",
-        "educational/text/tip7.png",
-    ];
-    IMAGES.choose(rng).unwrap().to_string()
-}
-
-/////// Meowing
-
-fn do_meow(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut rng: ResMut<GlobalRng>,
-    mut machine: Query<&mut StateMachine>,
-) {
-    commands.spawn((
-        AudioPlayer::new(asset_server.load(random_meow(&mut rng.0))),
-        PlaybackSettings {
-            mode: PlaybackMode::Once,
-            ..default()
-        },
-    ));
-
-    machine.single_mut().finish();
-}
-
-fn random_meow(rng: &mut impl Rng) -> String {
-    const MEOWS: &[&str] = &[
-        "meows/anais.ogg",
-        "meows/bella.ogg",
-        "meows/ben.ogg",
-        "meows/caroline.ogg",
-        "meows/dimitra.ogg",
-        "meows/dom.ogg",
-        "meows/helen-long-quack.ogg",
-        "meows/helen.ogg",
-        "meows/helen-quack.ogg",
-        "meows/julian.ogg",
-        "meows/kenneth.ogg",
-        "meows/kian.ogg",
-        "meows/laura.ogg",
-        "meows/maddie.ogg",
-        "meows/manya.ogg",
-        "meows/nehal.ogg",
-        "meows/phoebe.ogg",
-        "meows/rose.ogg",
-        "meows/stemple.ogg",
-        "meows/tanmay.ogg",
-        "meows/tiff.ogg",
-        "meows/will-sasaki.ogg",
-        "meows/zoe.ogg",
-    ];
-    MEOWS.choose(rng).unwrap().to_string()
-}
-
-/////// Birds
-
-fn setup_bird(
-    mut commands: Commands,
-    mut machine: Query<&mut StateMachine>,
-    asset_server: Res<AssetServer>,
-) {
-    let pos = WindowPosition::At(IVec2::new(100, 100));
-
-    let mut bird_sprite = Sprite::from_image(asset_server.load("Bird.png"));
-    bird_sprite.custom_size = Some(Vec2::new(55.0, 55.0));
-
-    let bird_window = commands
-        .spawn((
-            Window {
-                transparent: true,
-                composite_alpha_mode: get_composite_mode(),
-                decorations: false,
-                resizable: false,
-                has_shadow: false,
-                titlebar_shown: false,
-                titlebar_transparent: false,
-                titlebar_show_buttons: false,
-                titlebar_show_title: false,
-                title: "Bird!".to_string(),
-                name: Some("bonnie.buddy".into()),
-                resolution: (55.0, 55.0).into(),
-                resize_constraints: WindowResizeConstraints {
-                    min_width: 55.0,
-                    min_height: 55.0,
-                    max_width: 55.0,
-                    max_height: 55.0,
-                },
-                window_level: WindowLevel::AlwaysOnTop,
-                position: pos,
-                ..default()
-            },
-            BirdWindow,
-            bird_sprite,
-            BirdDirection { v: IVec2::ONE },
-            RenderLayers::layer(BIRD_LAYER),
-        ))
-        .id();
-
-    // spawn a camera2d on BIRD_LAYER
-    commands.spawn((
-        Camera2d,
-        Camera {
-            target: RenderTarget::Window(WindowRef::Entity(bird_window)),
-            ..default()
-        },
-        RenderLayers::layer(BIRD_LAYER),
-    ));
+        if !teleport.timer.finished() {
+            continue;
+        }
 
-    machine.single_mut().finish();
+        if teleport.arrived {
+            commands.entity(entity).remove::<Teleport>();
+            continue;
+        }
+
+        // out of sight, so she can jump straight there
+        if let Ok(mut window) = window_query.get_mut(bonnie_window.0) {
+            window.position = WindowPosition::At(teleport.to);
+            fractional_position.0 = teleport.to.as_vec2();
+        }
+        teleport.arrived = true;
+        teleport.timer.reset();
+    }
 }
 
-fn update_birds(
-    mut bird_windows: Query<(&mut Window, &mut BirdDirection, &mut Sprite)>,
-    monitor_query: Query<&Monitor>,
+pub fn calculate_movement_speed(
+    resolution: UVec2,
+    state: &BonnieState,
+    multiplier: f32,
+    speeds: &SpeedConfig,
+) -> f32 {
+    let diagonal = ((resolution.x.pow(2) + resolution.y.pow(2)) as f32).sqrt();
+    let base_speed = match state {
+        BonnieState::Chasing => speeds.chasing,
+        BonnieState::Teaching => speeds.teaching,
+        BonnieState::Bird => speeds.bird,
+        BonnieState::Idle => speeds.idle,
+        _ => speeds.other,
+    };
+    diagonal * speeds.screen_fraction * base_speed * multiplier
+}
+
+///////
+// Opacity
+///////
+
+/// Fades every Bonnie towards her opacity. Sprite swaps only change the image,
+/// so this keeps the colour right whatever she's showing.
+fn apply_opacity(
+    config: Res<OpacityConfig>,
+    mut bonnie_query: Query<&mut Sprite, (With<Bonnie>, Without<Teleport>)>,
+    mut alpha: Local<Option<f32>>,
     time: Res<Time>,
 ) {
-    let monitor_size = monitor_query.single().physical_size();
+    let target = config.target();
+    let step = FADE_RATE * time.delta_secs();
 
-    for (mut bird_window, mut bird_direction, mut bird_sprite) in &mut bird_windows {
-        let current_pos = match bird_window.position {
-            WindowPosition::At(pos) => pos,
-            _ => IVec2::ZERO,
-        };
+    // start at the target rather than fading in from nothing
+    let current = alpha.map_or(target, |alpha| alpha + (target - alpha).clamp(-step, step));
+    *alpha = Some(current);
 
-        match current_pos {
-            IVec2 { x, .. } if x < BIRD_SIZE_BUFFER => {
-                bird_direction.v.x = 1;
-            }
-            IVec2 { x, .. } if x + BIRD_SIZE_BUFFER > monitor_size.x as i32 => {
-                bird_direction.v.x = -1;
-            }
+    for mut sprite in &mut bonnie_query {
+        if sprite.color.alpha() != current {
+            sprite.color.set_alpha(current);
+        }
+    }
+}
 
-            // Vertical boundaries
-            IVec2 { y, .. } if y < BIRD_SIZE_BUFFER => {
-                bird_direction.v.y = 1;
-            }
-            IVec2 { y, .. } if y + BIRD_SIZE_BUFFER > monitor_size.y as i32 => {
-                bird_direction.v.y = -1;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{plugins::mood::MoodChange, window::PetWindow};
+
+    #[test]
+    fn clearing_poops_closes_their_cameras_too() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_event::<BonnieTransition>()
+            .add_event::<CalmRequest>()
+            .add_event::<MoodChange>()
+            .add_systems(Update, handle_calm);
+        app.update();
+
+        let bonnie = app.world_mut().spawn_empty().id();
+        let baseline = app.world().entities().len();
+
+        for _ in 0..3 {
+            let world = app.world_mut();
+            for _ in 0..20 {
+                PetWindow::new("Poop!", Vec2::splat(40.0), RenderLayers::layer(42)).spawn(
+                    &mut world.commands(),
+                    Stacking::default(),
+                    (PoopWindow, OwnedBy(bonnie)),
+                );
             }
-            _ => {}
+            world.flush();
+            assert_eq!(world.entities().len(), baseline + 40);
+
+            world.send_event(CalmRequest { poops: true });
+            app.update();
+            assert_eq!(app.world().entities().len(), baseline);
         }
+    }
 
-        bird_sprite.flip_x = bird_direction.v.x > 0;
+    #[test]
+    fn leash_targets_near_an_edge_stay_on_the_monitor_and_the_leash() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let anchor = IVec2::new(10, 890);
+        let area = WalkArea {
+            monitor: IRect::new(0, 0, 1920, 1080),
+            wander_area: None,
+            leash: Some((anchor, 300.0)),
+            window_size: IVec2::splat(180),
+            occupied: &[],
+        };
+        let bounds = area.bounds();
 
-        let speed = (calculate_movement_speed(monitor_size, &BonnieState::Bird) as f64
-            * time.delta_secs_f64()) as f32;
-        bird_window.position =
-            WindowPosition::At(current_pos + (bird_direction.v.as_vec2() * speed).as_ivec2());
+        for _ in 0..1000 {
+            let target = walk_target(&mut rng, &area);
+
+            assert!(
+                target.cmpge(bounds.min).all() && target.cmple(bounds.max).all(),
+                "{target} is off the monitor"
+            );
+            assert!(target.as_vec2().distance(anchor.as_vec2()) <= 301.0);
+        }
+    }
+
+    #[test]
+    fn walks_on_a_small_monitor_stay_on_it() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let area = WalkArea {
+            monitor: IRect::new(0, 0, 320, 240),
+            wander_area: None,
+            leash: None,
+            window_size: IVec2::splat(180),
+            occupied: &[],
+        };
+
+        for _ in 0..100 {
+            let next = random_state(
+                &BonnieState::Idle,
+                &[],
+                &mut rng,
+                &area,
+                |state| state == BonnieStateDiscriminants::Walking,
+                |_| 1.0,
+            );
+
+            let BonnieState::Walking(target) = next else {
+                panic!("expected a walk, got {next:?}");
+            };
+            assert!(
+                target.cmpge(IVec2::ZERO).all()
+                    && (target + area.window_size)
+                        .cmple(IVec2::new(320, 240))
+                        .all(),
+                "her window at {target} goes off the monitor"
+            );
+        }
     }
-}
 
-/////// Scratch
+    #[test]
+    fn walks_keep_to_the_monitor_shes_on() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let monitor = IRect::new(1920, -200, 3200, 824);
+        let area = WalkArea {
+            monitor,
+            wander_area: None,
+            leash: None,
+            window_size: IVec2::splat(180),
+            occupied: &[],
+        };
 
-fn setup_scratch_sprite(mut commands: Commands, asset_server: Res<AssetServer>) {
-    // get the sprite
-    let mut scratch_sprite = Sprite::from_image(asset_server.load("BonScratch.png"));
-    scratch_sprite.custom_size = Some(Vec2::new(60.0, 60.0));
+        for _ in 0..1000 {
+            let target = walk_target(&mut rng, &area);
+            assert!(
+                monitor.contains(target) && monitor.contains(target + area.window_size),
+                "her window at {target} goes off the monitor"
+            );
+        }
 
-    // add to scratch render layer
-    commands.spawn((scratch_sprite, RenderLayers::layer(SCRATCH_LAYER)));
-}
+        // before the monitors are known
+        let nowhere = WalkArea {
+            monitor: IRect::default(),
+            ..area
+        };
+        assert_eq!(walk_target(&mut rng, &nowhere), IVec2::ZERO);
+    }
 
-fn create_scratch(
-    mut commands: Commands,
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    mut machine: Query<&mut StateMachine>,
-) {
-    let pos = window_query.single().position;
-
-    let scratch_window = commands
-        .spawn((
-            Window {
-                transparent: true,
-                composite_alpha_mode: get_composite_mode(),
-                decorations: false,
-                resizable: false,
-                has_shadow: false,
-                titlebar_shown: false,
-                titlebar_transparent: false,
-                titlebar_show_buttons: false,
-                titlebar_show_title: false,
-                title: "Scratch!".to_string(),
-                name: Some("bonnie.buddy".into()),
-                resolution: (60.0, 60.0).into(),
-                resize_constraints: WindowResizeConstraints {
-                    min_width: 60.0,
-                    min_height: 60.0,
-                    max_width: 60.0,
-                    max_height: 60.0,
-                },
-                window_level: WindowLevel::AlwaysOnTop,
-                position: pos,
-                cursor_options: CursorOptions {
-                    hit_test: false,
-                    ..default()
-                },
-                ..default()
-            },
-            ScratchWindow,
-        ))
-        .id();
-
-    // spawn a camera2d on SCRATCH_LAYER
-    commands.spawn((
-        Camera2d,
-        Camera {
-            target: RenderTarget::Window(WindowRef::Entity(scratch_window)),
-            ..default()
-        },
-        RenderLayers::layer(SCRATCH_LAYER),
-    ));
+    #[test]
+    fn tiny_steps_add_up_to_arriving() {
+        let target = Vec2::new(120.0, 45.0);
+        let mut position = Vec2::ZERO;
 
-    // finish state
-    machine.single_mut().finish();
+        // a few hundredths of a pixel a frame, which rounding every frame would lose
+        let (speed, dt) = (30.0, 0.001);
+        for _ in 0..10_000 {
+            let delta = (target - position).normalize_or_zero() * speed * dt;
+            position = step(position, target, delta, 1.0);
+        }
+
+        assert_eq!(position, target);
+    }
+
+    #[test]
+    fn within_a_logical_pixel_counts_as_arrived() {
+        let target = Vec2::new(300.0, 200.0);
+
+        for scale_factor in [1.25, 1.5] {
+            let near = target + Vec2::splat(scale_factor - 0.05) / 2.0_f32.sqrt();
+            assert!(
+                arrived(near, target, 0.1, scale_factor),
+                "at {scale_factor}"
+            );
+
+            let far = target + Vec2::new(scale_factor + 0.05, 0.0);
+            assert!(
+                !arrived(far, target, 0.1, scale_factor),
+                "at {scale_factor}"
+            );
+        }
+    }
+
+    #[test]
+    fn settles_without_shimmering_at_fractional_scales() {
+        let target = Vec2::new(300.0, 200.0);
+
+        for scale_factor in [1.25, 1.5] {
+            let mut position = Vec2::new(290.3, 207.1);
+            let mut distance = position.distance(target);
+
+            for _ in 0..200 {
+                let delta = (target - position).normalize_or_zero() * 0.7;
+                position = step(position, target, delta, scale_factor);
+
+                let new_distance = position.distance(target);
+                assert!(new_distance <= distance, "backed off at {scale_factor}");
+                distance = new_distance;
+            }
+
+            assert_eq!(position, target, "at {scale_factor}");
+        }
+    }
 }
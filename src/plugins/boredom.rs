@@ -0,0 +1,169 @@
+//! Bonnie getting bored when nobody's paying her any attention.
+//!
+//! The longer it's been since she was last petted, picked up, summoned or
+//! fed, the more she goes looking for attention, each threshold crossed making
+//! her meow and wander more and come over to the cursor more often. Paying
+//! her some attention settles her down again gradually rather than all at once.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::bonnie::{Bonnie, FEET_OFFSET, StateMachine};
+
+use super::{
+    bonnie_state::{BonnieState, BonnieStateDiscriminants, GlobalRng, Interrupts, StateRules},
+    control::{Action, KeyBindings},
+    drag::Dragging,
+    global_cursor::GlobalCursorPosition,
+    mood::Mood,
+};
+
+/// How many boredom levels she calms down by each minute after some attention.
+const SETTLE_RATE: f32 = 1.0 / 60.0;
+/// Chance each second, per level past the first, that she comes over to the cursor.
+const SEEK_CHANCE: f64 = 0.01;
+
+pub struct BoredomPlugin;
+
+impl Plugin for BoredomPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BoredomConfig>()
+            .init_resource::<Boredom>()
+            .add_systems(
+                Update,
+                (notice_attention, get_bored, seek_attention)
+                    .chain()
+                    .run_if(|config: Res<BoredomConfig>| !config.thresholds.is_empty()),
+            );
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct BoredomConfig {
+    /// How long without attention before each step up in boredom. Empty
+    /// turns boredom off.
+    pub thresholds: Vec<Duration>,
+}
+
+impl Default for BoredomConfig {
+    fn default() -> Self {
+        Self {
+            thresholds: [15, 30, 60]
+                .into_iter()
+                .map(|mins| Duration::from_secs(mins * 60))
+                .collect(),
+        }
+    }
+}
+
+/// How bored she is, from 0.0 up to the number of thresholds.
+#[derive(Resource, Default, Debug)]
+pub struct Boredom {
+    pub level: f32,
+    /// How long since she last got any attention.
+    ignored_for: Duration,
+}
+
+impl Boredom {
+    /// How much more or less likely `state` gets, more attention seeking the
+    /// more bored she is.
+    pub fn weight_scale(&self, state: BonnieStateDiscriminants) -> f32 {
+        match state {
+            BonnieStateDiscriminants::Meowing => 1.0 + self.level * 0.5,
+            BonnieStateDiscriminants::Walking => 1.0 + self.level * 0.3,
+            BonnieStateDiscriminants::Idle => 1.0 / (1.0 + self.level * 0.5),
+            _ => 1.0,
+        }
+    }
+}
+
+/// Resets the clock on petting, picking her up, summoning and feeding.
+fn notice_attention(
+    mut boredom: ResMut<Boredom>,
+    mood: Res<Mood>,
+    picked_up: Query<(), Added<Dragging>>,
+    key_input: Res<ButtonInput<KeyCode>>,
+    keys: Res<KeyBindings>,
+) {
+    let attention = mood.being_petted()
+        || !picked_up.is_empty()
+        || keys.just_pressed(&key_input, Action::Summon)
+        || keys.just_pressed(&key_input, Action::Feed);
+
+    if attention {
+        boredom.ignored_for = Duration::ZERO;
+    }
+}
+
+fn get_bored(mut boredom: ResMut<Boredom>, config: Res<BoredomConfig>, time: Res<Time>) {
+    boredom.ignored_for += time.delta();
+
+    let crossed = config
+        .thresholds
+        .iter()
+        .filter(|threshold| boredom.ignored_for >= **threshold)
+        .count() as f32;
+
+    let before = boredom.level.floor();
+    // boredom steps straight up, but wears off slowly
+    boredom.level = if crossed >= boredom.level {
+        crossed
+    } else {
+        (boredom.level - SETTLE_RATE * time.delta_secs()).max(crossed)
+    };
+
+    if boredom.level.floor() != before {
+        info!("Bonnie's boredom is now {:.0}.", boredom.level.floor());
+    }
+}
+
+/// Now and then walks an idle, properly bored Bonnie over to the cursor.
+fn seek_attention(
+    boredom: Res<Boredom>,
+    mut bonnie_query: Query<(Entity, &mut Bonnie, &mut StateMachine)>,
+    mut interrupts: Interrupts,
+    cursor_pos: Res<GlobalCursorPosition>,
+    mut rng: ResMut<GlobalRng>,
+    rules: StateRules,
+    time: Res<Time>,
+) {
+    // just a bit bored only changes what she picks
+    let level = boredom.level.floor() - 1.0;
+    if level <= 0.0 {
+        return;
+    }
+
+    // she only comes over if she'd walk anywhere at the moment
+    if !rules.allowed()(BonnieStateDiscriminants::Walking) {
+        return;
+    }
+
+    let chance = (SEEK_CHANCE * level as f64 * time.delta_secs_f64()).clamp(0.0, 1.0);
+    if !rng.0.random_bool(chance) {
+        return;
+    }
+
+    let Some(cursor) = cursor_pos.0 else {
+        return;
+    };
+
+    let Some((entity, mut bonnie, mut machine)) = bonnie_query
+        .iter_mut()
+        .find(|(_, bonnie, _)| bonnie.state == BonnieState::Idle)
+    else {
+        return;
+    };
+
+    info!("Bonnie's bored, coming over for some attention.");
+
+    // right by the cursor, where she can't be missed
+    let target = cursor.as_ivec2() - FEET_OFFSET + IVec2::new(60, 0);
+    interrupts.interrupt_to(
+        entity,
+        &mut bonnie,
+        &mut machine,
+        BonnieState::Walking(target),
+    );
+}
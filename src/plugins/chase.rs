@@ -0,0 +1,194 @@
+//! The Chasing state.
+//!
+//! She runs after the cursor, or the laser dot while it's out, aiming a little
+//! ahead of where it's heading. Catching the cursor ends in a pounce, a short
+//! lunge and hop onto it, and the chase is over when she lands.
+
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+
+use crate::bonnie::{
+    Bonnie, BonnieWindow, ChaseVelocity, FEET_OFFSET, FractionalPosition, StateMachine,
+};
+
+use super::{
+    bonnie_state::{
+        BonnieState, BonnieStateDiscriminants, BonnieTransition, CATCH_DISTANCE, Sprites,
+        StateSystems, entered, exited,
+    },
+    drag::Dragging,
+    global_cursor::GlobalCursorPosition,
+    laser::LaserDot,
+};
+
+/// How long her pounce on a caught cursor lasts.
+const POUNCE_SECS: f32 = 0.3;
+/// How far forward she lunges when pouncing, in pixels.
+const POUNCE_LUNGE: f32 = 40.0;
+/// How high she hops at the top of a pounce, in pixels.
+const POUNCE_HOP: f32 = 25.0;
+
+pub struct ChasePlugin;
+
+impl Plugin for ChasePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChaseConfig>()
+            .add_systems(PreUpdate, exit_chase.in_set(StateSystems::Exit))
+            .add_systems(PreUpdate, setup_chase.in_set(StateSystems::Enter))
+            .add_systems(
+                Update,
+                (handle_chasing, handle_pounces)
+                    .chain()
+                    .in_set(StateSystems::Behave),
+            );
+    }
+}
+
+/// How Bonnie chases the cursor.
+#[derive(Resource, Debug, Clone)]
+pub struct ChaseConfig {
+    /// How many seconds ahead of a moving cursor she aims.
+    pub lead: f32,
+}
+
+impl Default for ChaseConfig {
+    fn default() -> Self {
+        Self { lead: 0.15 }
+    }
+}
+
+fn setup_chase(
+    mut transitions: EventReader<BonnieTransition>,
+    mut bonnie_query: Query<(&mut Sprite, &mut StateMachine, &mut ChaseVelocity), With<Bonnie>>,
+    sprites: Sprites,
+) {
+    let bonnie_asset = sprites.load("BonAngryMouth.png");
+
+    for bonnie in entered(&mut transitions, BonnieStateDiscriminants::Chasing) {
+        if let Ok((mut sprite, mut machine, mut chase_velocity)) = bonnie_query.get_mut(bonnie) {
+            // every chase starts from a standstill
+            chase_velocity.0 = Vec2::ZERO;
+            machine.block();
+            sprite.image = bonnie_asset.clone();
+        }
+    }
+}
+
+/// Bonnies with their paws free, not already pouncing or being held.
+type FreeToPounce = (Without<Pounce>, Without<Dragging>);
+
+fn handle_chasing(
+    mut commands: Commands,
+    mut bonnie_query: Query<(Entity, &Bonnie, &BonnieWindow, &mut Sprite), FreeToPounce>,
+    global_cursor_pos: Res<GlobalCursorPosition>,
+    window_query: Query<&Window>,
+    laser_query: Query<&LaserDot>,
+    sprites: Sprites,
+) {
+    // she keeps chasing the laser until it's put away
+    if !laser_query.is_empty() {
+        return;
+    }
+
+    for (entity, bonnie, bonnie_window, mut sprite) in &mut bonnie_query {
+        if let BonnieState::Chasing = bonnie.state {
+            // get window
+            let Ok(window) = window_query.get(bonnie_window.0) else {
+                continue;
+            };
+
+            // get global cursor pos
+            if let Some(cursor_pos) = global_cursor_pos.0 {
+                // get bonnie position
+                if let WindowPosition::At(bonnie_pos) = window.position {
+                    let diff = (bonnie_pos + FEET_OFFSET).as_vec2() - cursor_pos;
+                    let dist = diff.length();
+
+                    // if cursor near bonnie, pounce on it before finishing
+                    if dist < CATCH_DISTANCE {
+                        info!("Close enough, pouncing...");
+                        let lunge = -diff.normalize_or_zero() * POUNCE_LUNGE;
+                        sprite.image = sprites.load("BonPaw.png");
+                        commands.entity(entity).insert(Pounce {
+                            from: bonnie_pos,
+                            to: bonnie_pos + lunge.as_ivec2(),
+                            timer: Timer::from_seconds(POUNCE_SECS, TimerMode::Once),
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A Bonnie lunging forward and hopping on the cursor she's caught, finishing
+/// the chase when she lands.
+#[derive(Component, Debug)]
+pub struct Pounce {
+    from: IVec2,
+    to: IVec2,
+    timer: Timer,
+}
+
+/// A pouncing Bonnie, and what she needs to see it through.
+type Pouncer = (
+    Entity,
+    &'static Bonnie,
+    &'static BonnieWindow,
+    &'static mut StateMachine,
+    &'static mut FractionalPosition,
+    &'static mut Pounce,
+);
+
+fn handle_pounces(
+    mut commands: Commands,
+    mut bonnie_query: Query<Pouncer, Without<Dragging>>,
+    mut window_query: Query<&mut Window>,
+    time: Res<Time>,
+) {
+    for (entity, bonnie, bonnie_window, mut machine, mut fractional_position, mut pounce) in
+        &mut bonnie_query
+    {
+        // interrupted, whatever she's doing now has its own say over her
+        if bonnie.state != BonnieState::Chasing {
+            commands.entity(entity).remove::<Pounce>();
+            continue;
+        }
+
+        pounce.timer.tick(time.delta());
+        let t = pounce.timer.fraction();
+
+        // out fast and easing in, up and back down along the way
+        let along = 1.0 - (1.0 - t).powi(2);
+        let hop = Vec2::new(0.0, -(t * PI).sin() * POUNCE_HOP);
+        let position = pounce.from.as_vec2().lerp(pounce.to.as_vec2(), along) + hop;
+
+        if let Ok(mut window) = window_query.get_mut(bonnie_window.0) {
+            window.position = WindowPosition::At(position.round().as_ivec2());
+            fractional_position.0 = position;
+        }
+
+        if pounce.timer.finished() {
+            commands.entity(entity).remove::<Pounce>();
+            machine.finish();
+        }
+    }
+}
+
+fn exit_chase(
+    mut commands: Commands,
+    mut transitions: EventReader<BonnieTransition>,
+    mut bonnie_query: Query<&mut Sprite, With<Bonnie>>,
+    sprites: Sprites,
+) {
+    let bonnie_asset = sprites.load("BonNormal.png");
+
+    for bonnie in exited(&mut transitions, BonnieStateDiscriminants::Chasing) {
+        if let Ok(mut sprite) = bonnie_query.get_mut(bonnie) {
+            sprite.image = bonnie_asset.clone();
+            // cut off mid pounce
+            commands.entity(bonnie).remove::<Pounce>();
+        }
+    }
+}
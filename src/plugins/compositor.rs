@@ -0,0 +1,283 @@
+//! Checking the desktop can do what Bonnie's windows need.
+//!
+//! Without a compositor, transparent windows are drawn as black boxes, and
+//! native Wayland doesn't let windows move themselves, so warn about either
+//! rather than leaving people wondering.
+//!
+//! How a window's transparency is blended with the desktop, its composite
+//! alpha mode, is also picked here. Some setups only get it partly right and
+//! show a faint box around her, so rather than guessing per platform, the first
+//! window's surface is asked what it supports and the best of post-multiplied,
+//! pre-multiplied and opaque is used for every window. The config can force a
+//! mode for troubleshooting.
+
+use std::sync::OnceLock;
+
+use bevy::{
+    ecs::entity::EntityHashSet,
+    prelude::*,
+    render::{
+        Render, RenderApp,
+        renderer::{RenderAdapter, RenderInstance},
+        view::{ExtractedWindow, ExtractedWindows, create_surfaces},
+    },
+    window::{CompositeAlphaMode, PrimaryWindow},
+    winit::WinitWindows,
+};
+use serde::Deserialize;
+
+/// Best first, the modes tried for Bonnie's windows.
+const PREFERRED_MODES: [CompositeAlphaMode; 3] = [
+    CompositeAlphaMode::PostMultiplied,
+    CompositeAlphaMode::PreMultiplied,
+    CompositeAlphaMode::Opaque,
+];
+
+/// The mode every window uses, once the first one's been probed.
+static COMPOSITE_MODE: OnceLock<CompositeAlphaMode> = OnceLock::new();
+
+pub struct CompositorPlugin;
+
+impl Plugin for CompositorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CompositorConfig>()
+            .add_systems(Startup, check_compositor)
+            .add_systems(Update, check_window_positioning);
+    }
+
+    fn finish(&self, app: &mut App) {
+        // the config's only inserted after the plugins are added
+        let config = app.world().resource::<CompositorConfig>().clone();
+
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .insert_resource(config)
+                .add_systems(Render, pick_composite_mode.before(create_surfaces));
+        }
+    }
+}
+
+#[derive(Resource, Default, Debug, Clone)]
+pub struct CompositorConfig {
+    /// A mode to use instead of probing for the best one.
+    pub composite_mode: Option<CompositeMode>,
+}
+
+/// A composite alpha mode as named in the config file.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CompositeMode {
+    Auto,
+    Opaque,
+    PreMultiplied,
+    PostMultiplied,
+    Inherit,
+}
+
+impl From<CompositeMode> for CompositeAlphaMode {
+    fn from(mode: CompositeMode) -> Self {
+        match mode {
+            CompositeMode::Auto => Self::Auto,
+            CompositeMode::Opaque => Self::Opaque,
+            CompositeMode::PreMultiplied => Self::PreMultiplied,
+            CompositeMode::PostMultiplied => Self::PostMultiplied,
+            CompositeMode::Inherit => Self::Inherit,
+        }
+    }
+}
+
+/// The composite alpha mode to make a window with. Until the first window's
+/// been probed, that's the usual one for the platform, and windows made before
+/// then are switched over as their surfaces are created.
+pub fn get_composite_mode() -> CompositeAlphaMode {
+    COMPOSITE_MODE
+        .get()
+        .copied()
+        .unwrap_or_else(platform_composite_mode)
+}
+
+#[cfg(target_os = "macos")]
+fn platform_composite_mode() -> CompositeAlphaMode {
+    CompositeAlphaMode::PostMultiplied
+}
+
+#[cfg(not(target_os = "macos"))]
+fn platform_composite_mode() -> CompositeAlphaMode {
+    CompositeAlphaMode::default()
+}
+
+/// Gives every new window the probed mode, just before its surface is made.
+fn pick_composite_mode(
+    // surfaces can only be made on the main thread on some platforms, as with
+    // bevy's own `create_surfaces`
+    #[cfg(any(target_os = "macos", target_os = "ios"))] _marker: Option<
+        NonSend<bevy::core::NonSendMarker>,
+    >,
+    mut extracted_windows: ResMut<ExtractedWindows>,
+    render_instance: Res<RenderInstance>,
+    render_adapter: Res<RenderAdapter>,
+    config: Res<CompositorConfig>,
+    mut seen: Local<EntityHashSet>,
+) {
+    seen.retain(|entity| extracted_windows.windows.contains_key(entity));
+
+    for window in extracted_windows.windows.values_mut() {
+        if !seen.insert(window.entity) {
+            continue;
+        }
+
+        window.alpha_mode = *COMPOSITE_MODE.get_or_init(|| {
+            probe_composite_mode(window, &render_instance, &render_adapter, &config)
+        });
+    }
+}
+
+/// Asks `window`'s surface which modes it supports and picks the best, or the
+/// config's if it's among them.
+fn probe_composite_mode(
+    window: &ExtractedWindow,
+    render_instance: &RenderInstance,
+    render_adapter: &RenderAdapter,
+    config: &CompositorConfig,
+) -> CompositeAlphaMode {
+    // SAFETY: extracted window handles stay valid while their window's open,
+    // and this surface is dropped before bevy makes the window's own
+    let surface = unsafe {
+        render_instance.create_surface_unsafe(wgpu::SurfaceTargetUnsafe::RawHandle {
+            raw_display_handle: window.handle.display_handle,
+            raw_window_handle: window.handle.window_handle,
+        })
+    };
+    let supported: Vec<CompositeAlphaMode> = match surface {
+        Ok(surface) => surface
+            .get_capabilities(render_adapter)
+            .alpha_modes
+            .into_iter()
+            .map(bevy_composite_mode)
+            .collect(),
+        Err(err) => {
+            warn!(
+                "Couldn't check which transparency modes are supported: {}",
+                err
+            );
+            return config
+                .composite_mode
+                .map_or_else(platform_composite_mode, CompositeAlphaMode::from);
+        }
+    };
+
+    if let Some(mode) = config.composite_mode.map(CompositeAlphaMode::from) {
+        // anything unsupported would fail making the window's surface
+        if supported.contains(&mode) {
+            info!("Using {:?} transparency, as set in the config.", mode);
+            return mode;
+        }
+        warn!(
+            "{:?} transparency isn't supported here, only {:?}, probing for another.",
+            mode, supported
+        );
+    }
+
+    let mode = PREFERRED_MODES
+        .into_iter()
+        .find(|mode| supported.contains(mode))
+        .or(supported.first().copied())
+        .unwrap_or_else(platform_composite_mode);
+    info!("Using {:?} transparency, out of {:?}.", mode, supported);
+
+    mode
+}
+
+fn bevy_composite_mode(mode: wgpu::CompositeAlphaMode) -> CompositeAlphaMode {
+    match mode {
+        wgpu::CompositeAlphaMode::Auto => CompositeAlphaMode::Auto,
+        wgpu::CompositeAlphaMode::Opaque => CompositeAlphaMode::Opaque,
+        wgpu::CompositeAlphaMode::PreMultiplied => CompositeAlphaMode::PreMultiplied,
+        wgpu::CompositeAlphaMode::PostMultiplied => CompositeAlphaMode::PostMultiplied,
+        wgpu::CompositeAlphaMode::Inherit => CompositeAlphaMode::Inherit,
+    }
+}
+
+fn check_compositor() {
+    match compositor_running() {
+        Some(true) => {}
+        Some(false) => warn!(
+            "No compositor is running, so Bonnie will show up as a black box instead of \
+             being transparent. Start one (e.g. picom) or turn on compositing in your \
+             window manager's settings."
+        ),
+        None => info!(
+            "Couldn't tell whether a compositor is running. If Bonnie shows up as a black \
+             box, transparent windows aren't supported on this desktop."
+        ),
+    }
+}
+
+/// Warns once if Bonnie's window can't be moved, checked as soon as it exists.
+fn check_window_positioning(
+    winit_windows: NonSend<WinitWindows>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+    mut checked: Local<bool>,
+) {
+    if *checked {
+        return;
+    }
+
+    let Some(window) = primary_window
+        .get_single()
+        .ok()
+        .and_then(|entity| winit_windows.get_window(entity))
+    else {
+        return;
+    };
+    *checked = true;
+
+    // wayland windows have no position to read or set
+    if window.outer_position().is_err() {
+        let wayland = std::env::var("XDG_SESSION_TYPE").is_ok_and(|session| session == "wayland")
+            || std::env::var_os("WAYLAND_DISPLAY").is_some();
+
+        if wayland {
+            warn!(
+                "Wayland doesn't let Bonnie move her own window, so she'll stay put. Run her \
+                 through XWayland instead (e.g. unset WAYLAND_DISPLAY) to let her roam."
+            );
+        } else {
+            warn!("This desktop doesn't let Bonnie move her own window, so she'll stay put.");
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn compositor_running() -> Option<bool> {
+    use x11rb::protocol::xproto::ConnectionExt;
+
+    // wayland compositors always composite
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        return Some(true);
+    }
+
+    // on X11 a compositor owns the _NET_WM_CM_S<screen> selection
+    let (conn, screen) = x11rb::connect(None).ok()?;
+    let name = format!("_NET_WM_CM_S{}", screen);
+    let atom = conn
+        .intern_atom(false, name.as_bytes())
+        .ok()?
+        .reply()
+        .ok()?
+        .atom;
+    let owner = conn.get_selection_owner(atom).ok()?.reply().ok()?.owner;
+
+    Some(owner != x11rb::NONE)
+}
+
+// both always composite
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn compositor_running() -> Option<bool> {
+    Some(true)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn compositor_running() -> Option<bool> {
+    None
+}
@@ -1,57 +1,883 @@
 //! Keyboard controls for debugging.
 //!
-//! Arrow keys move the window, q will quit.
+//! By default arrow keys move the window, c toggles clicking through Bonnie, l
+//! cycles whether she's above or below other windows, b cycles behaviour
+//! profiles, = and - raise and lower her energy, ] and [ speed up and slow
+//! down everything, a leashes her to where she is or lets her off, d toggles
+//! her docking against the screen edge when idle, g fades her in and out like
+//! a ghost, h toggles window shadows (macOS only), s summons her to the cursor,
+//! t forces her into the configured state, f feeds her, holding p shines a
+//! laser for her to chase, w starts placing her patrol route, which Enter
+//! saves and Escape throws away, otherwise Escape closes every window she's
+//! opened and calms her back to idle without quitting, Space pauses
+//! everything, m mutes her, PageUp and PageDown turn her up and down, F3
+//! toggles the debug overlay, F12 saves a snapshot of her and q will quit.
+//!
+//! Any of them can be rebound in the config file's `[keys]` table, using
+//! the action names from [`Action`] and key names like `"q"`, `"Space"` or
+//! `"ArrowLeft"`.
+//!
+//! A gamepad's left stick moves the window too, and its face buttons summon
+//! her to the cursor (south), calm her (east) or make her meow (west).
+
+use std::fmt::Write;
+
+use bevy::{
+    diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin},
+    input::gamepad::{GamepadConnection, GamepadConnectionEvent},
+    prelude::*,
+    reflect::{DynamicEnum, DynamicVariant},
+    render::view::RenderLayers,
+    utils::HashMap,
+};
+use serde::Deserialize;
+
+use crate::{
+    bonnie::{BlockReason, Bonnie, BonnieWindow, FEET_OFFSET, StateMachine, StateTimers},
+    profile::Profile,
+    stacking::Stacking,
+    window::PetWindow,
+};
+
+use super::{
+    audio::AudioConfig,
+    bonnie_state::{
+        BehaviourConfig, BonnieState, BonnieStateDiscriminants, CalmRequest, Energy, GlobalRng,
+        Interrupts, LeashAnchor, MAX_SPEED_MULTIPLIER, MIN_SPEED_MULTIPLIER, MovementConfig,
+        OpacityConfig, StateRules,
+    },
+    global_cursor::GlobalCursorPosition,
+    idle::DockConfig,
+    ipc::Destinations,
+    mood::Mood,
+    waypoints::{WaypointPlacement, WaypointSystems},
+};
+
+/// How much each press of = or - changes her energy.
+const ENERGY_STEP: f32 = 0.1;
+/// How much each press of ] or [ scales the speed by.
+const SPEED_STEP: f32 = 1.25;
+/// How much each press of PageUp or PageDown changes the volume by.
+const VOLUME_STEP: f32 = 0.1;
+/// Loudest the volume can be turned up to.
+const MAX_VOLUME: f32 = 2.0;
 
-use bevy::{prelude::*, window::PrimaryWindow};
+/// Stick deflection ignored so a resting stick doesn't drift her about.
+const STICK_DEADZONE: f32 = 0.2;
+/// Pixels per second a fully pushed stick moves her.
+const STICK_SPEED: f32 = 600.0;
+
+/// Pixels per second an arrow key moves her when first pressed.
+const MOVE_SPEED_MIN: f32 = 200.0;
+/// Pixels per second an arrow key moves her once held long enough.
+const MOVE_SPEED_MAX: f32 = 2400.0;
+/// Seconds an arrow key has to be held to get up to full speed.
+const MOVE_RAMP_SECS: f32 = 1.5;
+
+/// Render layer the debug overlay is drawn on.
+const DEBUG_OVERLAY_LAYER: usize = 39;
 
 pub struct BonnieControlPlugin;
 
 impl Plugin for BonnieControlPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (move_window, quit_on_q));
+        if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+            app.add_plugins(FrameTimeDiagnosticsPlugin);
+        }
+
+        app.init_resource::<Profile>()
+            .init_resource::<KeyBindings>()
+            .init_resource::<ShadowConfig>()
+            .init_resource::<ForceStateConfig>()
+            .add_systems(Startup, warn_key_collisions)
+            .add_systems(
+                Update,
+                (
+                    move_window,
+                    toggle_click_through,
+                    cycle_stacking,
+                    cycle_profile,
+                    adjust_energy,
+                    adjust_speed,
+                    adjust_volume,
+                    toggle_pause,
+                    toggle_leash,
+                    toggle_docking,
+                    toggle_ghost,
+                    (toggle_shadow, apply_window_shadows).chain(),
+                    summon_to_cursor,
+                    force_state,
+                    (log_gamepad_connections, gamepad_move, gamepad_control),
+                    // before placing waypoints can finish on the same escape
+                    calm_on_escape.before(WaypointSystems),
+                    (toggle_debug_overlay, update_debug_overlay).chain(),
+                    quit_on_q,
+                ),
+            );
+    }
+}
+
+/// Whether Bonnie's windows draw a drop shadow.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct ShadowConfig {
+    pub enabled: bool,
+}
+
+/// The state the force-state key drops everything for.
+#[derive(Resource, Debug, Clone)]
+pub struct ForceStateConfig {
+    pub state: BonnieStateDiscriminants,
+}
+
+impl Default for ForceStateConfig {
+    fn default() -> Self {
+        Self {
+            state: BonnieStateDiscriminants::Meowing,
+        }
+    }
+}
+
+/// Everything a key can be bound to.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    ClickThrough,
+    Stacking,
+    Profile,
+    EnergyUp,
+    EnergyDown,
+    SpeedUp,
+    SpeedDown,
+    VolumeUp,
+    VolumeDown,
+    Mute,
+    Pause,
+    Leash,
+    Dock,
+    Ghost,
+    Shadow,
+    Summon,
+    ForceState,
+    Feed,
+    Calm,
+    Laser,
+    Waypoints,
+    WaypointsSave,
+    WaypointsCancel,
+    DebugOverlay,
+    Snapshot,
+    Quit,
+}
+
+/// A key named in the config file, either a bevy `KeyCode` like `"ArrowLeft"`
+/// or just the letter or digit for those, like `"q"`.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub struct Key(pub KeyCode);
+
+impl TryFrom<String> for Key {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        let variant = match name.as_str() {
+            letter if letter.len() == 1 && letter.chars().all(|c| c.is_ascii_alphabetic()) => {
+                format!("Key{}", letter.to_ascii_uppercase())
+            }
+            digit if digit.len() == 1 && digit.chars().all(|c| c.is_ascii_digit()) => {
+                format!("Digit{digit}")
+            }
+            _ => name.clone(),
+        };
+
+        // every key without a payload is a unit variant
+        KeyCode::from_reflect(&DynamicEnum::new(variant, DynamicVariant::Unit))
+            .map(Key)
+            .ok_or_else(|| format!("unknown key {:?}", name))
+    }
+}
+
+/// Which key does what.
+#[derive(Resource, Debug, Clone)]
+pub struct KeyBindings {
+    keys: HashMap<Action, KeyCode>,
+    /// Keys left doing more than one thing, warned about at startup.
+    collisions: Vec<(KeyCode, Vec<Action>)>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            keys: HashMap::from_iter([
+                (Action::MoveLeft, KeyCode::ArrowLeft),
+                (Action::MoveRight, KeyCode::ArrowRight),
+                (Action::MoveUp, KeyCode::ArrowUp),
+                (Action::MoveDown, KeyCode::ArrowDown),
+                (Action::ClickThrough, KeyCode::KeyC),
+                (Action::Stacking, KeyCode::KeyL),
+                (Action::Profile, KeyCode::KeyB),
+                (Action::EnergyUp, KeyCode::Equal),
+                (Action::EnergyDown, KeyCode::Minus),
+                (Action::SpeedUp, KeyCode::BracketRight),
+                (Action::SpeedDown, KeyCode::BracketLeft),
+                (Action::VolumeUp, KeyCode::PageUp),
+                (Action::VolumeDown, KeyCode::PageDown),
+                (Action::Mute, KeyCode::KeyM),
+                (Action::Pause, KeyCode::Space),
+                (Action::Leash, KeyCode::KeyA),
+                (Action::Dock, KeyCode::KeyD),
+                (Action::Ghost, KeyCode::KeyG),
+                (Action::Shadow, KeyCode::KeyH),
+                (Action::Summon, KeyCode::KeyS),
+                (Action::ForceState, KeyCode::KeyT),
+                (Action::Feed, KeyCode::KeyF),
+                (Action::Calm, KeyCode::Escape),
+                (Action::Laser, KeyCode::KeyP),
+                (Action::Waypoints, KeyCode::KeyW),
+                (Action::WaypointsSave, KeyCode::Enter),
+                (Action::WaypointsCancel, KeyCode::Escape),
+                (Action::DebugOverlay, KeyCode::F3),
+                (Action::Snapshot, KeyCode::F12),
+                (Action::Quit, KeyCode::KeyQ),
+            ]),
+            collisions: Vec::new(),
+        }
+    }
+}
+
+impl KeyBindings {
+    /// The defaults with `overrides` from the config file on top, noting
+    /// any key left doing two things.
+    pub fn new<'a>(overrides: impl IntoIterator<Item = (&'a Action, &'a Key)>) -> Self {
+        let mut bindings = Self::default();
+        bindings
+            .keys
+            .extend(overrides.into_iter().map(|(action, key)| (*action, key.0)));
+
+        let mut actions_by_key: HashMap<KeyCode, Vec<Action>> = HashMap::default();
+        for (action, key) in &bindings.keys {
+            actions_by_key.entry(*key).or_default().push(*action);
+        }
+
+        bindings.collisions = actions_by_key
+            .into_iter()
+            .filter(|(_, actions)| actions.len() > 1 && !can_share(actions))
+            .collect();
+
+        bindings
+    }
+
+    pub fn key(&self, action: Action) -> KeyCode {
+        self.keys[&action]
+    }
+
+    pub fn pressed(&self, input: &ButtonInput<KeyCode>, action: Action) -> bool {
+        input.pressed(self.key(action))
+    }
+
+    pub fn just_pressed(&self, input: &ButtonInput<KeyCode>, action: Action) -> bool {
+        input.just_pressed(self.key(action))
+    }
+
+    pub fn just_released(&self, input: &ButtonInput<KeyCode>, action: Action) -> bool {
+        input.just_released(self.key(action))
+    }
+}
+
+/// Whether `actions` can all go on one key, as calming her is held off while
+/// placing waypoints so the same key can cancel that.
+fn can_share(actions: &[Action]) -> bool {
+    actions.len() == 2
+        && actions.contains(&Action::Calm)
+        && actions.contains(&Action::WaypointsCancel)
+}
+
+fn warn_key_collisions(keys: Res<KeyBindings>) {
+    for (key, actions) in &keys.collisions {
+        warn!("{:?} is bound to more than one action: {:?}", key, actions);
     }
 }
 
 fn move_window(
     key_input: Res<ButtonInput<KeyCode>>,
-    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+    keys: Res<KeyBindings>,
+    bonnie_query: Query<&BonnieWindow>,
+    mut window_query: Query<&mut Window>,
+    mut held_for: Local<f32>,
+    mut remainder: Local<Vec2>,
+    time: Res<Time<Real>>,
+) {
+    // get direction
+    let mut direction = Vec2::ZERO;
+    if keys.pressed(&key_input, Action::MoveLeft) {
+        direction.x -= 1.0;
+    }
+    if keys.pressed(&key_input, Action::MoveRight) {
+        direction.x += 1.0;
+    }
+    if keys.pressed(&key_input, Action::MoveUp) {
+        direction.y -= 1.0;
+    }
+    if keys.pressed(&key_input, Action::MoveDown) {
+        direction.y += 1.0;
+    }
+
+    if direction == Vec2::ZERO {
+        *held_for = 0.0;
+        *remainder = Vec2::ZERO;
+        return;
+    }
+
+    // starts slow for nudging, speeding up the longer it's held for big moves,
+    // in real time so she can still be moved while paused
+    *held_for += time.delta_secs();
+    let ramp = (*held_for / MOVE_RAMP_SECS).min(1.0);
+    let speed = MOVE_SPEED_MIN.lerp(MOVE_SPEED_MAX, ramp * ramp);
+
+    // keep what's left over from whole pixels, so slow moves aren't lost
+    let exact = *remainder + direction * speed * time.delta_secs();
+    let offset = exact.trunc();
+    *remainder = exact - offset;
+    let offset = offset.as_ivec2();
+
+    // move every bonnie's window
+    for bonnie_window in &bonnie_query {
+        if let Ok(mut window) = window_query.get_mut(bonnie_window.0) {
+            // get current window position
+            let current_pos = match window.position {
+                WindowPosition::At(pos) => pos,
+                _ => IVec2::new(100, 100),
+            };
+
+            // update the position
+            window.position = WindowPosition::At(current_pos + offset);
+        }
+    }
+}
+
+/// Lets clicks pass through every Bonnie's window to whatever's underneath,
+/// which also stops her being dragged.
+fn toggle_click_through(
+    key_input: Res<ButtonInput<KeyCode>>,
+    keys: Res<KeyBindings>,
+    bonnie_query: Query<&BonnieWindow>,
+    mut window_query: Query<&mut Window>,
+    mut click_through: Local<bool>,
+) {
+    if !keys.just_pressed(&key_input, Action::ClickThrough) {
+        return;
+    }
+
+    *click_through = !*click_through;
+
+    for bonnie_window in &bonnie_query {
+        if let Ok(mut window) = window_query.get_mut(bonnie_window.0) {
+            window.cursor_options.hit_test = !*click_through;
+        }
+    }
+
+    if *click_through {
+        info!("Click-through on, clicks now pass through Bonnie.");
+    } else {
+        info!("Click-through off, Bonnie can be clicked again.");
+    }
+}
+
+/// Moves every window, Bonnie's and the ones she's made, to the next stacking.
+fn cycle_stacking(
+    key_input: Res<ButtonInput<KeyCode>>,
+    keys: Res<KeyBindings>,
+    mut stacking: ResMut<Stacking>,
+    mut window_query: Query<&mut Window>,
+) {
+    if !keys.just_pressed(&key_input, Action::Stacking) {
+        return;
+    }
+
+    *stacking = stacking.next();
+
+    for mut window in &mut window_query {
+        window.window_level = stacking.level();
+    }
+
+    info!("Bonnie's windows are now stacked {:?}.", *stacking);
+}
+
+fn log_gamepad_connections(mut connection_events: EventReader<GamepadConnectionEvent>) {
+    for event in connection_events.read() {
+        match &event.connection {
+            GamepadConnection::Connected { name, .. } => info!("Gamepad {} connected.", name),
+            GamepadConnection::Disconnected => info!("Gamepad disconnected."),
+        }
+    }
+}
+
+/// Moves Bonnie's window with the left stick.
+fn gamepad_move(
+    gamepads: Query<&Gamepad>,
+    bonnie_query: Query<&BonnieWindow>,
+    mut window_query: Query<&mut Window>,
+    mut remainder: Local<Vec2>,
+    time: Res<Time<Real>>,
+) {
+    // up on the stick is up the screen
+    let direction: Vec2 = gamepads
+        .iter()
+        .map(|gamepad| gamepad.left_stick())
+        .filter(|stick| stick.length() > STICK_DEADZONE)
+        .map(|stick| Vec2::new(stick.x, -stick.y))
+        .sum();
+
+    if direction == Vec2::ZERO {
+        *remainder = Vec2::ZERO;
+        return;
+    }
+
+    // keep what's left over from whole pixels, as with the arrow keys, in
+    // real time so she can still be moved while paused
+    let exact = *remainder + direction * STICK_SPEED * time.delta_secs();
+    let offset = exact.trunc();
+    *remainder = exact - offset;
+    let offset = offset.as_ivec2();
+
+    for bonnie_window in &bonnie_query {
+        if let Ok(mut window) = window_query.get_mut(bonnie_window.0)
+            && let WindowPosition::At(position) = window.position
+        {
+            window.position = WindowPosition::At(position + offset);
+        }
+    }
+}
+
+/// Summons, calms or meows her with the gamepad's face buttons.
+fn gamepad_control(
+    gamepads: Query<&Gamepad>,
+    mut bonnie_query: Query<(Entity, &mut Bonnie, &mut StateMachine)>,
+    mut interrupts: Interrupts,
+    mut calm_requests: EventWriter<CalmRequest>,
+    cursor_pos: Res<GlobalCursorPosition>,
+    rules: StateRules,
+) {
+    let pressed = |button| gamepads.iter().any(|gamepad| gamepad.just_pressed(button));
+
+    if pressed(GamepadButton::East) {
+        calm_requests.send(CalmRequest { poops: false });
+    }
+
+    // one state between every gamepad, so each Bonnie's only interrupted once
+    let forced = if pressed(GamepadButton::South) {
+        cursor_pos.0.map(summon_state)
+    } else if pressed(GamepadButton::West) {
+        Some(BonnieState::Meowing)
+    } else {
+        None
+    };
+
+    let Some(state) = forced else {
+        return;
+    };
+
+    let discriminant = BonnieStateDiscriminants::from(&state);
+    if !rules.allowed()(discriminant) {
+        warn!("Bonnie can't go into {:?} at the moment.", discriminant);
+        return;
+    }
+
+    for (entity, mut bonnie, mut machine) in &mut bonnie_query {
+        interrupts.interrupt_to(entity, &mut bonnie, &mut machine, state.clone());
+    }
+}
+
+/// Walking over so she's standing on the cursor.
+pub fn summon_state(cursor: Vec2) -> BonnieState {
+    BonnieState::Walking(cursor.as_ivec2() - FEET_OFFSET)
+}
+
+fn summon_to_cursor(
+    key_input: Res<ButtonInput<KeyCode>>,
+    keys: Res<KeyBindings>,
+    mut bonnie_query: Query<(Entity, &mut Bonnie, &mut StateMachine)>,
+    mut interrupts: Interrupts,
+    cursor_pos: Res<GlobalCursorPosition>,
+) {
+    if !keys.just_pressed(&key_input, Action::Summon) {
+        return;
+    }
+
+    let Some(cursor) = cursor_pos.0 else {
+        return;
+    };
+
+    let state = summon_state(cursor);
+    for (entity, mut bonnie, mut machine) in &mut bonnie_query {
+        interrupts.interrupt_to(entity, &mut bonnie, &mut machine, state.clone());
+    }
+}
+
+/// Sets every Bonnie up afresh in the configured state, unless it's one she
+/// can't go into at the moment.
+fn force_state(
+    key_input: Res<ButtonInput<KeyCode>>,
+    keys: Res<KeyBindings>,
+    config: Res<ForceStateConfig>,
+    mut bonnie_query: Query<(Entity, &mut Bonnie, &BonnieWindow, &mut StateMachine)>,
+    mut interrupts: Interrupts,
+    destinations: Destinations,
+    rules: StateRules,
+    mut rng: ResMut<GlobalRng>,
 ) {
-    // get window
-    if let Ok(mut window) = window_query.get_single_mut() {
-        // pixels/frame
-        let move_speed = 10;
+    if !keys.just_pressed(&key_input, Action::ForceState) {
+        return;
+    }
 
-        // get current window position
-        let current_pos = match window.position {
-            WindowPosition::At(pos) => pos,
-            _ => IVec2::new(100, 100),
+    if !rules.allowed()(config.state) {
+        warn!("Bonnie can't go into {:?} at the moment.", config.state);
+        return;
+    }
+
+    for (entity, mut bonnie, bonnie_window, mut machine) in &mut bonnie_query {
+        let state = match config.state {
+            BonnieStateDiscriminants::Walking => {
+                destinations.random_walk(&mut rng.0, bonnie_window)
+            }
+            state => BonnieState::from(state),
         };
+        interrupts.restart_to(entity, &mut bonnie, &mut machine, state);
+    }
+}
+
+/// Anchors each Bonnie where she is, or lets her off if she's already leashed.
+fn toggle_leash(
+    mut commands: Commands,
+    key_input: Res<ButtonInput<KeyCode>>,
+    keys: Res<KeyBindings>,
+    bonnie_query: Query<(Entity, &BonnieWindow, Has<LeashAnchor>), With<Bonnie>>,
+    window_query: Query<&Window>,
+) {
+    if !keys.just_pressed(&key_input, Action::Leash) {
+        return;
+    }
 
-        // get new position
-        let mut new_pos = current_pos;
-        if key_input.pressed(KeyCode::ArrowLeft) {
-            new_pos.x -= move_speed;
+    for (entity, bonnie_window, leashed) in &bonnie_query {
+        if leashed {
+            commands.entity(entity).remove::<LeashAnchor>();
+            info!("Let {} off her leash.", entity);
+            continue;
         }
-        if key_input.pressed(KeyCode::ArrowRight) {
-            new_pos.x += move_speed;
+
+        let Ok(WindowPosition::At(position)) =
+            window_query.get(bonnie_window.0).map(|w| w.position)
+        else {
+            continue;
+        };
+
+        commands.entity(entity).insert(LeashAnchor(position));
+        info!("Leashed {} at {}.", entity, position);
+    }
+}
+
+fn toggle_docking(
+    key_input: Res<ButtonInput<KeyCode>>,
+    keys: Res<KeyBindings>,
+    mut dock_config: ResMut<DockConfig>,
+) {
+    if !keys.just_pressed(&key_input, Action::Dock) {
+        return;
+    }
+
+    dock_config.enabled = !dock_config.enabled;
+    if dock_config.enabled {
+        info!("Bonnie will dock at the screen edge when idle.");
+    } else {
+        info!("Bonnie will idle wherever she stops.");
+    }
+}
+
+fn toggle_ghost(
+    key_input: Res<ButtonInput<KeyCode>>,
+    keys: Res<KeyBindings>,
+    mut opacity: ResMut<OpacityConfig>,
+) {
+    if keys.just_pressed(&key_input, Action::Ghost) {
+        opacity.ghost = !opacity.ghost;
+        info!("Bonnie's fading to {:.1} opacity.", opacity.target());
+    }
+}
+
+fn toggle_shadow(
+    key_input: Res<ButtonInput<KeyCode>>,
+    keys: Res<KeyBindings>,
+    mut shadow: ResMut<ShadowConfig>,
+) {
+    if !keys.just_pressed(&key_input, Action::Shadow) {
+        return;
+    }
+
+    shadow.enabled = !shadow.enabled;
+    info!(
+        "Window shadows {}.",
+        if shadow.enabled { "on" } else { "off" }
+    );
+}
+
+/// Gives every window, new ones included, the shadow setting. Only macOS draws
+/// window shadows, and bevy only applies them when a window's created, so
+/// existing windows there are updated directly.
+fn apply_window_shadows(
+    shadow: Res<ShadowConfig>,
+    mut window_query: Query<(Entity, &mut Window)>,
+    #[cfg(target_os = "macos")] winit_windows: NonSend<bevy::winit::WinitWindows>,
+) {
+    if shadow.is_changed() {
+        if cfg!(target_os = "macos") {
+            debug!("Applying window shadows.");
+        } else if shadow.enabled {
+            info!("Window shadows are only drawn on macOS, so they won't show up here.");
         }
-        if key_input.pressed(KeyCode::ArrowUp) {
-            new_pos.y -= move_speed;
+    }
+
+    for (_entity, mut window) in &mut window_query {
+        if window.has_shadow == shadow.enabled {
+            continue;
         }
-        if key_input.pressed(KeyCode::ArrowDown) {
-            new_pos.y += move_speed;
+        window.has_shadow = shadow.enabled;
+
+        #[cfg(target_os = "macos")]
+        if let Some(winit_window) = winit_windows.get_window(_entity) {
+            use winit::platform::macos::WindowExtMacOS;
+            winit_window.set_has_shadow(shadow.enabled);
         }
+    }
+}
+
+/// Swaps every setting the next profile covers in one go.
+fn cycle_profile(
+    key_input: Res<ButtonInput<KeyCode>>,
+    keys: Res<KeyBindings>,
+    mut profile: ResMut<Profile>,
+    mut behaviour: ResMut<BehaviourConfig>,
+    mut audio_config: ResMut<AudioConfig>,
+) {
+    if !keys.just_pressed(&key_input, Action::Profile) {
+        return;
+    }
 
-        // update the position
-        window.position = WindowPosition::At(new_pos);
+    *profile = profile.next();
+    *behaviour = profile.behaviour();
+    audio_config.volume = profile.volume();
+
+    info!("Switched to the {:?} profile.", *profile);
+}
+
+fn adjust_energy(
+    key_input: Res<ButtonInput<KeyCode>>,
+    keys: Res<KeyBindings>,
+    mut energy: ResMut<Energy>,
+) {
+    let step = if keys.just_pressed(&key_input, Action::EnergyUp) {
+        ENERGY_STEP
+    } else if keys.just_pressed(&key_input, Action::EnergyDown) {
+        -ENERGY_STEP
+    } else {
+        return;
+    };
+
+    energy.0 = (energy.0 + step).clamp(0.0, 1.0);
+    info!("Energy now {:.1}.", energy.0);
+}
+
+fn adjust_speed(
+    key_input: Res<ButtonInput<KeyCode>>,
+    keys: Res<KeyBindings>,
+    mut movement_config: ResMut<MovementConfig>,
+) {
+    let scale = if keys.just_pressed(&key_input, Action::SpeedUp) {
+        SPEED_STEP
+    } else if keys.just_pressed(&key_input, Action::SpeedDown) {
+        1.0 / SPEED_STEP
+    } else {
+        return;
+    };
+
+    movement_config.speed_multiplier = (movement_config.speed_multiplier * scale)
+        .clamp(MIN_SPEED_MULTIPLIER, MAX_SPEED_MULTIPLIER);
+    info!("Speed now {:.2}x.", movement_config.speed_multiplier);
+}
+
+fn adjust_volume(
+    key_input: Res<ButtonInput<KeyCode>>,
+    keys: Res<KeyBindings>,
+    mut audio_config: ResMut<AudioConfig>,
+) {
+    if keys.just_pressed(&key_input, Action::Mute) {
+        audio_config.muted = !audio_config.muted;
+        info!(
+            "Bonnie's {}.",
+            if audio_config.muted {
+                "muted"
+            } else {
+                "unmuted"
+            }
+        );
+        return;
+    }
+
+    let step = if keys.just_pressed(&key_input, Action::VolumeUp) {
+        VOLUME_STEP
+    } else if keys.just_pressed(&key_input, Action::VolumeDown) {
+        -VOLUME_STEP
+    } else {
+        return;
+    };
+
+    audio_config.volume = (audio_config.volume + step).clamp(0.0, MAX_VOLUME);
+    info!("Volume now {:.1}.", audio_config.volume);
+}
+
+/// Freezes game time, so she stops moving and her states stop changing.
+fn toggle_pause(
+    key_input: Res<ButtonInput<KeyCode>>,
+    keys: Res<KeyBindings>,
+    mut time: ResMut<Time<Virtual>>,
+    mut machines: Query<&mut StateMachine>,
+) {
+    if !keys.just_pressed(&key_input, Action::Pause) {
+        return;
+    }
+
+    if time.is_paused() {
+        time.unpause();
+        for mut machine in &mut machines {
+            machine.unblock_for(BlockReason::UserPause);
+        }
+        info!("Unpaused.");
+    } else {
+        time.pause();
+        // so a state finishing meanwhile doesn't let her move on
+        for mut machine in &mut machines {
+            machine.block_for(BlockReason::UserPause);
+        }
+        info!("Paused.");
+    }
+}
+
+/// Everything making up the debug overlay, so it can be cleared up in one go.
+#[derive(Component)]
+struct DebugOverlay;
+
+#[derive(Component)]
+struct DebugOverlayText;
+
+fn toggle_debug_overlay(
+    mut commands: Commands,
+    key_input: Res<ButtonInput<KeyCode>>,
+    keys: Res<KeyBindings>,
+    overlay_query: Query<Entity, With<DebugOverlay>>,
+    stacking: Res<Stacking>,
+) {
+    if !keys.just_pressed(&key_input, Action::DebugOverlay) {
+        return;
+    }
+
+    // already open, close it
+    if !overlay_query.is_empty() {
+        for entity in &overlay_query {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+
+    let overlay = PetWindow::new(
+        "Bonnie Debug",
+        Vec2::new(320.0, 160.0),
+        RenderLayers::layer(DEBUG_OVERLAY_LAYER),
+    )
+    .at(WindowPosition::At(IVec2::ZERO))
+    .click_through()
+    .clear_color(Color::srgba(0.0, 0.0, 0.0, 0.6))
+    .spawn(&mut commands, *stacking, DebugOverlay);
+    commands.entity(overlay.camera).insert(DebugOverlay);
+
+    commands.spawn((
+        Text2d::default(),
+        TextFont {
+            font_size: 14.0,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        RenderLayers::layer(DEBUG_OVERLAY_LAYER),
+        DebugOverlay,
+        DebugOverlayText,
+    ));
+}
+
+fn update_debug_overlay(
+    mut text_query: Query<&mut Text2d, With<DebugOverlayText>>,
+    bonnie_query: Query<(Entity, &Bonnie)>,
+    state_timers: Res<StateTimers>,
+    cursor_pos: Res<GlobalCursorPosition>,
+    mood: Res<Mood>,
+    diagnostics: Res<DiagnosticsStore>,
+) {
+    for mut text in &mut text_query {
+        let fps = diagnostics
+            .get(&FrameTimeDiagnosticsPlugin::FPS)
+            .and_then(|fps| fps.smoothed())
+            .unwrap_or_default();
+
+        let mut overlay = format!("FPS: {:.0}\nCursor: ", fps);
+        match cursor_pos.0 {
+            Some(cursor) => write!(overlay, "({:.0}, {:.0})", cursor.x, cursor.y),
+            None => write!(overlay, "unavailable"),
+        }
+        .ok();
+
+        write!(overlay, "\nMood: {:?} ({:.2})", mood.kind(), mood.level).ok();
+
+        for (entity, bonnie) in &bonnie_query {
+            let Some(timer) = state_timers.0.get(&entity) else {
+                continue;
+            };
+
+            write!(
+                overlay,
+                "\nBonnie {}: {:?} ({:.1}s)",
+                bonnie.index,
+                bonnie.state,
+                timer.remaining.as_secs_f32(),
+            )
+            .ok();
+
+            if !timer.blocked_by.is_empty() {
+                write!(overlay, " blocked by {:?}", timer.blocked_by).ok();
+            }
+        }
+
+        text.0 = overlay;
+    }
+}
+
+fn calm_on_escape(
+    key_input: Res<ButtonInput<KeyCode>>,
+    keys: Res<KeyBindings>,
+    mut calm_requests: EventWriter<CalmRequest>,
+    placement: Res<WaypointPlacement>,
+) {
+    // cancels placing waypoints instead
+    if keys.just_pressed(&key_input, Action::Calm) && !placement.active {
+        calm_requests.send(CalmRequest { poops: true });
     }
 }
 
 fn quit_on_q(
     key_input: Res<ButtonInput<KeyCode>>,
+    keys: Res<KeyBindings>,
     mut app_exit_events: EventWriter<bevy::app::AppExit>,
 ) {
-    if key_input.just_pressed(KeyCode::KeyQ) {
+    if keys.just_pressed(&key_input, Action::Quit) {
         app_exit_events.send(AppExit::Success);
     }
 }
@@ -0,0 +1,83 @@
+//! Bonnie getting stressed when the computer's working hard.
+//!
+//! When turned on in the config, overall CPU usage is sampled every
+//! `SAMPLE_INTERVAL` and a busy CPU makes her chase and pace about more.
+
+use std::time::Duration;
+
+use bevy::{prelude::*, time::common_conditions::on_timer};
+use sysinfo::System;
+
+use super::bonnie_state::BonnieStateDiscriminants;
+
+/// How often CPU usage is sampled, often enough to notice a long build
+/// without sampling costing anything noticeable.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+/// CPU usage from 0.0 to 1.0 above which she's stressed.
+const STRESS_THRESHOLD: f32 = 0.8;
+
+pub struct CpuLoadPlugin;
+
+impl Plugin for CpuLoadPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CpuLoadConfig>()
+            .init_resource::<CpuLoad>()
+            .add_systems(
+                Update,
+                sample_cpu_load.run_if(
+                    on_timer(SAMPLE_INTERVAL).and(|config: Res<CpuLoadConfig>| config.enabled),
+                ),
+            );
+    }
+}
+
+#[derive(Resource, Default, Debug, Clone)]
+pub struct CpuLoadConfig {
+    /// Whether to sample CPU usage at all.
+    pub enabled: bool,
+}
+
+/// The latest CPU usage from 0.0 to 1.0.
+#[derive(Resource, Default, Debug)]
+pub struct CpuLoad {
+    pub usage: f32,
+    system: Option<System>,
+}
+
+impl CpuLoad {
+    pub fn stressed(&self) -> bool {
+        self.usage > STRESS_THRESHOLD
+    }
+
+    /// How much more or less likely `state` gets, frantic states when stressed.
+    pub fn weight_scale(&self, state: BonnieStateDiscriminants) -> f32 {
+        if !self.stressed() {
+            return 1.0;
+        }
+
+        match state {
+            BonnieStateDiscriminants::Chasing => 3.0,
+            BonnieStateDiscriminants::Walking => 2.0,
+            BonnieStateDiscriminants::Idle => 0.5,
+            _ => 1.0,
+        }
+    }
+}
+
+fn sample_cpu_load(mut cpu_load: ResMut<CpuLoad>) {
+    // usage is measured between refreshes, so the first sample is just a baseline
+    let system = cpu_load.system.get_or_insert_with(System::new);
+    system.refresh_cpu_usage();
+    let usage = system.global_cpu_usage() / 100.0;
+
+    let was_stressed = cpu_load.stressed();
+    cpu_load.usage = usage;
+
+    if cpu_load.stressed() != was_stressed {
+        if cpu_load.stressed() {
+            info!("CPU at {:.0}%, Bonnie's stressed.", usage * 100.0);
+        } else {
+            info!("CPU at {:.0}%, Bonnie's calmed down.", usage * 100.0);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+//! The Dancing state.
+//!
+//! She hops as high as whatever's playing is loud, swaying side to side on
+//! the beat, or to a steady beat when she can't hear anything. She lands back
+//! where she started once she's done.
+
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+
+use crate::bonnie::{Bonnie, BonnieWindow};
+
+use super::{
+    bonnie_state::{
+        BonnieStateDiscriminants, BonnieTransition, Sprites, StateSystems, entered, exited,
+    },
+    drag::NotHeld,
+    loopback::AudioLevel,
+    physics::Airborne,
+};
+
+/// Highest she hops while dancing, in pixels.
+const DANCE_HOP: f32 = 30.0;
+/// Beats per minute she dances to when she can't hear anything playing.
+const DANCE_BPM: f32 = 120.0;
+
+pub struct DancePlugin;
+
+impl Plugin for DancePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PreUpdate, exit_dancing.in_set(StateSystems::Exit))
+            .add_systems(PreUpdate, setup_dancing.in_set(StateSystems::Enter))
+            .add_systems(Update, handle_dancing.in_set(StateSystems::Behave));
+    }
+}
+
+/// Where a dancing Bonnie's window rests between hops, and where it was last
+/// put mid-hop.
+#[derive(Component, Debug)]
+struct Dance {
+    rest: IVec2,
+    placed: IVec2,
+}
+
+fn setup_dancing(
+    mut commands: Commands,
+    mut transitions: EventReader<BonnieTransition>,
+    mut bonnie_query: Query<(&BonnieWindow, &mut Sprite), With<Bonnie>>,
+    window_query: Query<&Window>,
+    sprites: Sprites,
+) {
+    for bonnie in entered(&mut transitions, BonnieStateDiscriminants::Dancing) {
+        let Ok((bonnie_window, mut sprite)) = bonnie_query.get_mut(bonnie) else {
+            continue;
+        };
+
+        let Ok(WindowPosition::At(position)) =
+            window_query.get(bonnie_window.0).map(|w| w.position)
+        else {
+            continue;
+        };
+
+        sprite.image = sprites.load("BonNormalMouth.png");
+        commands.entity(bonnie).insert(Dance {
+            rest: position,
+            placed: position,
+        });
+    }
+}
+
+/// Bonnies with their feet on the ground, not held or thrown.
+type OnTheFloor = (NotHeld, Without<Airborne>);
+
+/// Hops her in time with the music, or a steady beat if there's none to hear.
+fn handle_dancing(
+    mut bonnie_query: Query<(&BonnieWindow, &mut Dance, &mut Sprite), OnTheFloor>,
+    mut window_query: Query<&mut Window>,
+    audio_level: Res<AudioLevel>,
+    time: Res<Time>,
+) {
+    let beats = time.elapsed_secs() * DANCE_BPM / 60.0;
+    // as high as the music's loud, or a hop a beat
+    let height = audio_level
+        .get()
+        .unwrap_or_else(|| (beats * PI).sin().abs());
+    // swaying side to side every beat
+    let flipped = beats as u32 % 2 == 1;
+
+    for (bonnie_window, mut dance, mut sprite) in &mut bonnie_query {
+        let Ok(mut window) = window_query.get_mut(bonnie_window.0) else {
+            continue;
+        };
+        let WindowPosition::At(position) = window.position else {
+            continue;
+        };
+
+        // something else moved her, so dance from there
+        if position != dance.placed {
+            dance.rest = position;
+        }
+
+        let placed = dance.rest - IVec2::new(0, (height * DANCE_HOP).round() as i32);
+        if placed != position {
+            window.position = WindowPosition::At(placed);
+        }
+        dance.placed = placed;
+
+        if sprite.flip_x != flipped {
+            sprite.flip_x = flipped;
+        }
+    }
+}
+
+fn exit_dancing(
+    mut commands: Commands,
+    mut transitions: EventReader<BonnieTransition>,
+    mut bonnie_query: Query<(&BonnieWindow, &mut Sprite, Option<&Dance>)>,
+    mut window_query: Query<&mut Window>,
+    sprites: Sprites,
+) {
+    for bonnie in exited(&mut transitions, BonnieStateDiscriminants::Dancing) {
+        let Ok((bonnie_window, mut sprite, dance)) = bonnie_query.get_mut(bonnie) else {
+            continue;
+        };
+
+        // land back where she started, unless she's been moved meanwhile
+        if let Some(dance) = dance
+            && let Ok(mut window) = window_query.get_mut(bonnie_window.0)
+            && window.position == WindowPosition::At(dance.placed)
+        {
+            window.position = WindowPosition::At(dance.rest);
+        }
+
+        sprite.image = sprites.load("BonNormal.png");
+        sprite.flip_x = false;
+        commands.entity(bonnie).remove::<Dance>();
+    }
+}
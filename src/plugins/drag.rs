@@ -0,0 +1,150 @@
+//! Picking Bonnie up and throwing her.
+//!
+//! Holding left click on Bonnie drags her window around with the cursor,
+//! letting go hands her the cursor's recent velocity.
+
+use std::collections::VecDeque;
+
+use bevy::{
+    input::{ButtonState, mouse::MouseButtonInput},
+    prelude::*,
+};
+
+use crate::bonnie::{Bonnie, BonnieWindow};
+
+use super::{
+    global_cursor::GlobalCursorPosition,
+    physics::{Airborne, Velocity},
+};
+
+/// How far back cursor samples are used to work out the throw velocity.
+const THROW_WINDOW_SECS: f32 = 0.1;
+/// Fastest she can be thrown in pixels/s, so she can't be flung away for good.
+const MAX_THROW_SPEED: f32 = 3000.0;
+
+pub struct DragPlugin;
+
+impl Plugin for DragPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (start_drag, update_drag, end_drag).chain());
+    }
+}
+
+/// A Bonnie being dragged by the cursor.
+#[derive(Component, Debug)]
+pub struct Dragging {
+    /// Cursor position relative to the window's top left.
+    offset: Vec2,
+    /// Recent (time, cursor position) samples.
+    samples: VecDeque<(f32, Vec2)>,
+}
+
+impl Dragging {
+    /// Velocity of the cursor over the last few samples, capped to `MAX_THROW_SPEED`.
+    fn release_velocity(&self) -> Vec2 {
+        let (Some((start_time, start)), Some((end_time, end))) =
+            (self.samples.front(), self.samples.back())
+        else {
+            return Vec2::ZERO;
+        };
+
+        let dt = end_time - start_time;
+        if dt <= f32::EPSILON {
+            return Vec2::ZERO;
+        }
+
+        ((end - start) / dt).clamp_length_max(MAX_THROW_SPEED)
+    }
+}
+
+/// Bonnies that aren't being held.
+pub type NotHeld = (With<Bonnie>, Without<Dragging>);
+
+fn start_drag(
+    mut commands: Commands,
+    mut mouse_events: EventReader<MouseButtonInput>,
+    bonnie_query: Query<(Entity, &BonnieWindow), NotHeld>,
+    window_query: Query<&Window>,
+    cursor_pos: Res<GlobalCursorPosition>,
+    time: Res<Time>,
+) {
+    for event in mouse_events.read() {
+        if event.button != MouseButton::Left || event.state != ButtonState::Pressed {
+            continue;
+        }
+
+        let Some(cursor) = cursor_pos.0 else {
+            continue;
+        };
+
+        for (entity, bonnie_window) in &bonnie_query {
+            if bonnie_window.0 != event.window {
+                continue;
+            }
+
+            let Ok(WindowPosition::At(position)) =
+                window_query.get(event.window).map(|w| w.position)
+            else {
+                continue;
+            };
+
+            debug!("Picked up {}.", entity);
+            commands
+                .entity(entity)
+                .remove::<(Airborne, Velocity)>()
+                .insert(Dragging {
+                    offset: cursor - position.as_vec2(),
+                    samples: VecDeque::from([(time.elapsed_secs(), cursor)]),
+                });
+        }
+    }
+}
+
+fn update_drag(
+    mut bonnie_query: Query<(&BonnieWindow, &mut Dragging)>,
+    mut window_query: Query<&mut Window>,
+    cursor_pos: Res<GlobalCursorPosition>,
+    time: Res<Time>,
+) {
+    let Some(cursor) = cursor_pos.0 else {
+        return;
+    };
+
+    let now = time.elapsed_secs();
+
+    for (bonnie_window, mut dragging) in &mut bonnie_query {
+        // only keep the samples needed for the throw
+        dragging.samples.push_back((now, cursor));
+        while dragging
+            .samples
+            .front()
+            .is_some_and(|(t, _)| now - t > THROW_WINDOW_SECS)
+        {
+            dragging.samples.pop_front();
+        }
+
+        if let Ok(mut window) = window_query.get_mut(bonnie_window.0) {
+            window.position = WindowPosition::At((cursor - dragging.offset).round().as_ivec2());
+        }
+    }
+}
+
+fn end_drag(
+    mut commands: Commands,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    bonnie_query: Query<(Entity, &Dragging)>,
+) {
+    if mouse_input.pressed(MouseButton::Left) {
+        return;
+    }
+
+    for (entity, dragging) in &bonnie_query {
+        let velocity = dragging.release_velocity();
+        debug!("Threw {} at {:?}.", entity, velocity);
+
+        commands
+            .entity(entity)
+            .remove::<Dragging>()
+            .insert((Airborne, Velocity(velocity)));
+    }
+}
@@ -0,0 +1,107 @@
+//! Footsteps while Bonnie's on the move.
+//!
+//! A loop starts when she sets off walking, patrolling or chasing and pauses
+//! when she stops, so going from one to another keeps the same loop going.
+
+use bevy::prelude::*;
+
+use crate::bonnie::Bonnie;
+
+use super::{
+    audio::{AudioConfig, audio_enabled},
+    bonnie_state::{BonnieState, BonnieTransition, StateSystems},
+};
+
+pub struct FootstepsPlugin;
+
+impl Plugin for FootstepsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PreUpdate,
+            handle_footsteps
+                .run_if(audio_enabled)
+                .in_set(StateSystems::Enter),
+        )
+        .add_systems(Update, follow_volume.run_if(audio_enabled));
+    }
+}
+
+const FOOTSTEP_VOLUME: f32 = 0.4;
+
+/// The footstep loop a Bonnie plays while on the move.
+#[derive(Component)]
+struct Footsteps(Entity);
+
+fn makes_footsteps(state: &BonnieState) -> bool {
+    matches!(
+        state,
+        BonnieState::Walking(_) | BonnieState::Patrolling(_) | BonnieState::Chasing
+    )
+}
+
+fn handle_footsteps(
+    mut commands: Commands,
+    mut transitions: EventReader<BonnieTransition>,
+    bonnie_query: Query<Option<&Footsteps>, With<Bonnie>>,
+    players: Query<Option<&AudioSink>>,
+    asset_server: Res<AssetServer>,
+    audio_config: Res<AudioConfig>,
+) {
+    for transition in transitions.read() {
+        let was_moving = makes_footsteps(&transition.from);
+        let is_moving = makes_footsteps(&transition.to);
+
+        // walking into chasing keeps the same loop going
+        if was_moving == is_moving {
+            continue;
+        }
+
+        let Ok(footsteps) = bonnie_query.get(transition.bonnie) else {
+            continue;
+        };
+
+        // reuse the existing player if it's still around
+        let player = footsteps.and_then(|f| players.get(f.0).ok().map(|sink| (f.0, sink)));
+
+        match (is_moving, player) {
+            // resume the paused loop
+            (true, Some((_, Some(sink)))) => {
+                sink.set_volume(audio_config.volume(FOOTSTEP_VOLUME).get());
+                sink.play();
+            }
+            // already starting up, leave it be
+            (true, Some((_, None))) => {}
+            // nothing playing, start a loop
+            (true, None) => {
+                let player = commands
+                    .spawn((
+                        AudioPlayer::new(asset_server.load("footsteps.wav")),
+                        PlaybackSettings::LOOP.with_volume(audio_config.volume(FOOTSTEP_VOLUME)),
+                    ))
+                    .id();
+                commands.entity(transition.bonnie).insert(Footsteps(player));
+            }
+            // pause so the loop can be picked up again
+            (false, Some((_, Some(sink)))) => sink.pause(),
+            // stopped before it started, get rid of it
+            (false, Some((player, None))) => {
+                commands.entity(player).despawn();
+                commands.entity(transition.bonnie).remove::<Footsteps>();
+            }
+            (false, None) => {}
+        }
+    }
+}
+
+/// Keeps a running loop up with muting and volume changes, like the purr does.
+fn follow_volume(
+    footsteps_query: Query<&Footsteps>,
+    players: Query<&AudioSink>,
+    audio_config: Res<AudioConfig>,
+) {
+    for footsteps in &footsteps_query {
+        if let Ok(sink) = players.get(footsteps.0) {
+            sink.set_volume(audio_config.volume(FOOTSTEP_VOLUME).get());
+        }
+    }
+}
@@ -0,0 +1,305 @@
+//! Pausing Bonnie while a fullscreen app is in front.
+//!
+//! Whether the focused window is fullscreen is polled from the OS on a
+//! background thread, and while it is she stops changing state and can be
+//! hidden too, so she doesn't wander over games or presentations.
+//!
+//! - Linux: X11's `_NET_WM_STATE_FULLSCREEN` on the active window, so not on
+//!   native Wayland.
+//! - macOS: asking System Events, which needs accessibility permission.
+//! - Windows: the foreground window covering its whole monitor.
+
+#[cfg(target_os = "macos")]
+use std::process::Command;
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU8, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    bonnie::{BlockReason, BonnieWindow, StateMachine},
+    window::Transient,
+};
+
+/// How often the OS is asked whether a fullscreen app is in front.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+const UNKNOWN: u8 = 0;
+const WINDOWED: u8 = 1;
+const FULLSCREEN: u8 = 2;
+
+pub struct FullscreenPlugin;
+
+impl Plugin for FullscreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FullscreenConfig>()
+            .init_resource::<Fullscreen>()
+            .add_systems(
+                Startup,
+                spawn_fullscreen_poller.run_if(|config: Res<FullscreenConfig>| config.enabled),
+            )
+            .add_systems(
+                Update,
+                (
+                    handle_fullscreen,
+                    hide_for_fullscreen.run_if(|config: Res<FullscreenConfig>| config.hide),
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct FullscreenConfig {
+    /// Whether to look out for fullscreen apps at all.
+    pub enabled: bool,
+    /// Whether to hide her windows as well as pausing her.
+    pub hide: bool,
+}
+
+impl Default for FullscreenConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            hide: false,
+        }
+    }
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct Fullscreen {
+    /// Whether Bonnie is currently paused for a fullscreen app.
+    pub active: bool,
+    /// Latest answer from the OS poller.
+    native: Arc<AtomicU8>,
+}
+
+fn spawn_fullscreen_poller(fullscreen: Res<Fullscreen>) {
+    let native = fullscreen.native.clone();
+
+    let spawned = thread::Builder::new()
+        .name("fullscreen-poller".into())
+        .spawn(move || {
+            loop {
+                let state = match foreground_fullscreen() {
+                    Some(true) => FULLSCREEN,
+                    Some(false) => WINDOWED,
+                    None => UNKNOWN,
+                };
+                native.store(state, Ordering::Relaxed);
+
+                // no point polling a platform that can't tell us
+                if state == UNKNOWN {
+                    info!(
+                        "Fullscreen detection unavailable, Bonnie won't pause for fullscreen apps."
+                    );
+                    return;
+                }
+
+                thread::sleep(POLL_INTERVAL);
+            }
+        });
+
+    if let Err(err) = spawned {
+        warn!("Couldn't start fullscreen poller: {}", err);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn foreground_fullscreen() -> Option<bool> {
+    use x11rb::{
+        connection::Connection,
+        protocol::xproto::{AtomEnum, ConnectionExt},
+    };
+
+    let (conn, screen) = x11rb::connect(None).ok()?;
+    let root = conn.setup().roots.get(screen)?.root;
+    let atom = |name: &str| -> Option<u32> {
+        Some(
+            conn.intern_atom(false, name.as_bytes())
+                .ok()?
+                .reply()
+                .ok()?
+                .atom,
+        )
+    };
+
+    let active = conn
+        .get_property(
+            false,
+            root,
+            atom("_NET_ACTIVE_WINDOW")?,
+            AtomEnum::WINDOW,
+            0,
+            1,
+        )
+        .ok()?
+        .reply()
+        .ok()?
+        .value32()?
+        .next();
+    let Some(active) = active.filter(|window| *window != 0) else {
+        return Some(false);
+    };
+
+    // her own windows, like placing waypoints, don't count
+    let class = conn
+        .get_property(false, active, AtomEnum::WM_CLASS, AtomEnum::STRING, 0, 256)
+        .ok()?
+        .reply()
+        .ok()?;
+    if String::from_utf8_lossy(&class.value).contains("bonnie.buddy") {
+        return Some(false);
+    }
+
+    let fullscreen = atom("_NET_WM_STATE_FULLSCREEN")?;
+    let state = conn
+        .get_property(false, active, atom("_NET_WM_STATE")?, AtomEnum::ATOM, 0, 64)
+        .ok()?
+        .reply()
+        .ok()?;
+
+    Some(
+        state
+            .value32()
+            .is_some_and(|mut atoms| atoms.any(|atom| atom == fullscreen)),
+    )
+}
+
+#[cfg(target_os = "macos")]
+fn foreground_fullscreen() -> Option<bool> {
+    let output = Command::new("osascript")
+        .arg("-e")
+        .arg(
+            "tell application \"System Events\" to get value of attribute \"AXFullScreen\" \
+             of front window of (first process whose frontmost is true)",
+        )
+        .output()
+        .ok()?;
+
+    match String::from_utf8_lossy(&output.stdout).trim() {
+        "true" => Some(true),
+        "false" => Some(false),
+        // no front window, or no permission to ask
+        _ if output.status.success() => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn foreground_fullscreen() -> Option<bool> {
+    use windows_sys::Win32::{
+        Foundation::RECT,
+        Graphics::Gdi::{
+            GetMonitorInfoW, MONITOR_DEFAULTTONEAREST, MONITORINFO, MonitorFromWindow,
+        },
+        UI::WindowsAndMessaging::{
+            GetDesktopWindow, GetForegroundWindow, GetShellWindow, GetWindowRect,
+            GetWindowThreadProcessId,
+        },
+    };
+
+    unsafe {
+        let window = GetForegroundWindow();
+        if window.is_null() || window == GetDesktopWindow() || window == GetShellWindow() {
+            return Some(false);
+        }
+
+        // her own windows, like placing waypoints, don't count
+        let mut pid = 0;
+        GetWindowThreadProcessId(window, &mut pid);
+        if pid == std::process::id() {
+            return Some(false);
+        }
+
+        let mut rect: RECT = std::mem::zeroed();
+        if GetWindowRect(window, &mut rect) == 0 {
+            return None;
+        }
+
+        let mut info: MONITORINFO = std::mem::zeroed();
+        info.cbSize = std::mem::size_of::<MONITORINFO>() as u32;
+        if GetMonitorInfoW(
+            MonitorFromWindow(window, MONITOR_DEFAULTTONEAREST),
+            &mut info,
+        ) == 0
+        {
+            return None;
+        }
+
+        let monitor = info.rcMonitor;
+        Some(
+            rect.left <= monitor.left
+                && rect.top <= monitor.top
+                && rect.right >= monitor.right
+                && rect.bottom >= monitor.bottom,
+        )
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn foreground_fullscreen() -> Option<bool> {
+    None
+}
+
+fn handle_fullscreen(mut fullscreen: ResMut<Fullscreen>, mut machines: Query<&mut StateMachine>) {
+    let active = fullscreen.native.load(Ordering::Relaxed) == FULLSCREEN;
+
+    if active == fullscreen.active {
+        return;
+    }
+    fullscreen.active = active;
+
+    if active {
+        info!("Fullscreen app in front, pausing Bonnie.");
+    } else {
+        info!("Fullscreen app gone, resuming Bonnie.");
+    }
+
+    for mut machine in &mut machines {
+        if active {
+            machine.block_for(BlockReason::Fullscreen);
+        } else {
+            machine.unblock_for(BlockReason::Fullscreen);
+        }
+    }
+}
+
+/// Hides her windows and every window she's opened while a fullscreen app's
+/// in front, including any she opens meanwhile, and shows them again after.
+fn hide_for_fullscreen(
+    fullscreen: Res<Fullscreen>,
+    bonnie_query: Query<&BonnieWindow>,
+    transient_query: Query<Entity, (With<Window>, With<Transient>)>,
+    mut window_query: Query<&mut Window>,
+    mut hidden: Local<Vec<Entity>>,
+) {
+    if !fullscreen.active {
+        // only the ones hidden here, skipping any closed meanwhile
+        for entity in hidden.drain(..) {
+            if let Ok(mut window) = window_query.get_mut(entity) {
+                window.visible = true;
+            }
+        }
+        return;
+    }
+
+    let windows = bonnie_query
+        .iter()
+        .map(|bonnie_window| bonnie_window.0)
+        .chain(&transient_query);
+    for entity in windows {
+        if let Ok(mut window) = window_query.get_mut(entity)
+            && window.visible
+        {
+            window.visible = false;
+            hidden.push(entity);
+        }
+    }
+}
@@ -1,30 +1,159 @@
-use bevy::prelude::*;
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, render::view::RenderLayers};
+#[cfg(not(target_os = "windows"))]
 use mouse_position::mouse_position::Mouse;
 
+use crate::{stacking::Stacking, window::PetWindow};
+
+/// Render layer the cursor debug overlay is drawn on.
+const CURSOR_DEBUG_LAYER: usize = 40;
+/// Size of the cursor debug overlay's window.
+const CURSOR_DEBUG_SIZE: f32 = 16.0;
+/// How far back cursor samples are kept to work out its velocity, short so
+/// it catches up quickly when the cursor changes direction.
+const HISTORY_SECS: f32 = 0.1;
+
 pub struct GlobalCursorPlugin;
 
 impl Plugin for GlobalCursorPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<GlobalCursorPosition>()
-            // .add_systems(Update, print_global_cursor_position)
-            .add_systems(Update, track_global_cursor_position);
+            .init_resource::<GlobalCursorConfig>()
+            .init_resource::<CursorTracking>()
+            .init_resource::<CursorHistory>()
+            .add_systems(
+                Startup,
+                (
+                    track_global_cursor_position,
+                    check_cursor_tracking,
+                    spawn_cursor_debug,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                (
+                    track_global_cursor_position,
+                    record_cursor_history,
+                    move_cursor_debug,
+                )
+                    .chain(),
+            );
     }
 }
 
 #[derive(Resource, Default, Debug)]
 pub struct GlobalCursorPosition(pub Option<Vec2>);
 
+#[derive(Resource, Default, Debug, Clone)]
+pub struct GlobalCursorConfig {
+    /// Show a dot where Bonnie thinks the cursor is.
+    pub debug_overlay: bool,
+    /// Keep cursor features on even if the cursor can't be found at startup.
+    pub assume_available: bool,
+}
+
+/// Recent (time, cursor position) samples.
+#[derive(Resource, Default, Debug)]
+pub struct CursorHistory {
+    samples: VecDeque<(f32, Vec2)>,
+}
+
+impl CursorHistory {
+    /// Velocity of the cursor over the recent samples, in pixels/s.
+    pub fn velocity(&self) -> Vec2 {
+        let (Some((start_time, start)), Some((end_time, end))) =
+            (self.samples.front(), self.samples.back())
+        else {
+            return Vec2::ZERO;
+        };
+
+        let dt = end_time - start_time;
+        if dt <= f32::EPSILON {
+            return Vec2::ZERO;
+        }
+
+        (end - start) / dt
+    }
+}
+
+/// Whether the cursor can be tracked, decided once at startup. Features that
+/// follow the cursor are turned off when it can't be.
+#[derive(Resource, Debug)]
+pub struct CursorTracking {
+    pub available: bool,
+}
+
+impl Default for CursorTracking {
+    fn default() -> Self {
+        Self { available: true }
+    }
+}
+
+/// The window showing where Bonnie thinks the cursor is.
+#[derive(Component)]
+struct CursorDebugWindow;
+
 #[cfg(target_os = "macos")]
 fn track_global_cursor_position(mut global_pos: ResMut<GlobalCursorPosition>) {
-    let mouse = Mouse::get_mouse_position();
+    use core_graphics::{
+        display::CGDisplay,
+        event::CGEvent,
+        event_source::{CGEventSource, CGEventSourceStateID},
+    };
 
-    if let Mouse::Position { x, y } = mouse {
-        // hack solution
-        global_pos.0 = Some(Vec2::new((x * 2) as f32, (y * 2) as f32));
+    let displays = CGDisplay::active_displays().unwrap_or_default();
+
+    if displays.len() <= 1 {
+        let mouse = Mouse::get_mouse_position();
+
+        if let Mouse::Position { x, y } = mouse {
+            // hack solution
+            global_pos.0 = Some(Vec2::new((x * 2) as f32, (y * 2) as f32));
+        }
+        return;
+    }
+
+    // in points from the main display's top left, y down
+    let Some(point) = CGEventSource::new(CGEventSourceStateID::CombinedSessionState)
+        .and_then(CGEvent::new)
+        .ok()
+        .map(|event| event.location())
+    else {
+        return;
+    };
+
+    // the display the cursor is on decides how points become pixels
+    let Some(display) = displays.into_iter().map(CGDisplay::new).find(|display| {
+        let bounds = display.bounds();
+        (bounds.origin.x..bounds.origin.x + bounds.size.width).contains(&point.x)
+            && (bounds.origin.y..bounds.origin.y + bounds.size.height).contains(&point.y)
+    }) else {
+        return;
+    };
+
+    let bounds = display.bounds();
+    let scale = display.pixels_high() as f64 / bounds.size.height;
+    global_pos.0 = Some(Vec2::new(
+        (point.x * scale) as f32,
+        (point.y * scale) as f32,
+    ));
+}
+
+#[cfg(target_os = "windows")]
+fn track_global_cursor_position(mut global_pos: ResMut<GlobalCursorPosition>) {
+    use windows_sys::Win32::{Foundation::POINT, UI::WindowsAndMessaging::GetPhysicalCursorPos};
+
+    // physical pixels across the whole desktop, the same as window positions,
+    // whatever each monitor's scale
+    let mut point = POINT { x: 0, y: 0 };
+    if unsafe { GetPhysicalCursorPos(&mut point) } != 0 {
+        global_pos.0 = Some(Vec2::new(point.x as f32, point.y as f32));
     }
 }
 
-#[cfg(not(target_os = "macos"))]
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
 fn track_global_cursor_position(mut global_pos: ResMut<GlobalCursorPosition>) {
     let mouse = Mouse::get_mouse_position();
 
@@ -33,7 +162,81 @@ fn track_global_cursor_position(mut global_pos: ResMut<GlobalCursorPosition>) {
     }
 }
 
-#[allow(dead_code)]
-fn print_global_cursor_position(global_pos: Res<GlobalCursorPosition>) {
-    info!("Position: {:?}", global_pos.0);
+fn record_cursor_history(
+    global_pos: Res<GlobalCursorPosition>,
+    mut history: ResMut<CursorHistory>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_secs();
+
+    match global_pos.0 {
+        Some(cursor) => history.samples.push_back((now, cursor)),
+        None => history.samples.clear(),
+    }
+
+    while history
+        .samples
+        .front()
+        .is_some_and(|(t, _)| now - t > HISTORY_SECS)
+    {
+        history.samples.pop_front();
+    }
+}
+
+pub fn check_cursor_tracking(
+    global_pos: Res<GlobalCursorPosition>,
+    config: Res<GlobalCursorConfig>,
+    mut tracking: ResMut<CursorTracking>,
+) {
+    if global_pos.0.is_some() || config.assume_available {
+        return;
+    }
+
+    tracking.available = false;
+    warn!(
+        "Couldn't find the cursor, so Bonnie won't chase it or wake up when it's near. \
+         Pass --assume-cursor if you know it works."
+    );
+}
+
+fn spawn_cursor_debug(mut commands: Commands, config: Res<GlobalCursorConfig>) {
+    if !config.debug_overlay {
+        return;
+    }
+
+    // always on top, so the dot's never hidden behind what it's pointing at
+    PetWindow::new(
+        "Cursor",
+        Vec2::splat(CURSOR_DEBUG_SIZE),
+        RenderLayers::layer(CURSOR_DEBUG_LAYER),
+    )
+    .click_through()
+    .spawn(&mut commands, Stacking::Top, CursorDebugWindow);
+
+    commands.spawn((
+        Sprite::from_color(
+            Color::srgb(1.0, 0.0, 0.0),
+            Vec2::splat(CURSOR_DEBUG_SIZE / 2.0),
+        ),
+        RenderLayers::layer(CURSOR_DEBUG_LAYER),
+    ));
+}
+
+fn move_cursor_debug(
+    global_pos: Res<GlobalCursorPosition>,
+    mut debug_window: Query<&mut Window, With<CursorDebugWindow>>,
+) {
+    let Some(cursor) = global_pos.0 else {
+        return;
+    };
+
+    for mut window in &mut debug_window {
+        // centre the dot on the cursor
+        let half_size = window.physical_size().as_ivec2() / 2;
+        let position = WindowPosition::At(cursor.as_ivec2() - half_size);
+
+        if window.position != position {
+            window.position = position;
+        }
+    }
 }
@@ -0,0 +1,82 @@
+//! Bonnie getting hungry.
+//!
+//! When turned on in the config, her hunger slowly rises and a hungry Bonnie
+//! meows more and naps less. Pressing f (or whatever `feed` is bound to) feeds
+//! her. How hungry she is gets saved with the rest of her state, so she
+//! remembers next time.
+
+use bevy::prelude::*;
+
+use super::{
+    audio::{MunchSounds, PlaySound, Sound, Sounds},
+    bonnie_state::{BonnieStateDiscriminants, GlobalRng},
+    control::{Action, KeyBindings},
+};
+
+/// How much hungrier she gets each second, starving after about four hours.
+const HUNGER_RATE: f32 = 1.0 / (4.0 * 60.0 * 60.0);
+/// How much each feed takes off her hunger.
+const FEED_AMOUNT: f32 = 0.4;
+
+pub struct HungerPlugin;
+
+impl Plugin for HungerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HungerConfig>()
+            .init_resource::<Hunger>()
+            .add_systems(
+                Update,
+                (get_hungry, feed_on_key).chain().run_if(hunger_enabled),
+            );
+    }
+}
+
+#[derive(Resource, Default, Debug, Clone)]
+pub struct HungerConfig {
+    /// Whether she gets hungry at all.
+    pub enabled: bool,
+}
+
+fn hunger_enabled(config: Res<HungerConfig>) -> bool {
+    config.enabled
+}
+
+/// How hungry she is from 0.0, full, to 1.0, starving.
+#[derive(Resource, Default, Debug)]
+pub struct Hunger(pub f32);
+
+impl Hunger {
+    /// How much more or less likely `state` gets, more meowing and less
+    /// napping the hungrier she is.
+    pub fn weight_scale(&self, state: BonnieStateDiscriminants) -> f32 {
+        match state {
+            BonnieStateDiscriminants::Meowing => 1.0 + self.0 * 2.0,
+            BonnieStateDiscriminants::Idle => 1.0 - self.0 * 0.7,
+            _ => 1.0,
+        }
+    }
+}
+
+fn get_hungry(mut hunger: ResMut<Hunger>, time: Res<Time>) {
+    hunger.0 = (hunger.0 + time.delta_secs() * HUNGER_RATE).min(1.0);
+}
+
+fn feed_on_key(
+    key_input: Res<ButtonInput<KeyCode>>,
+    keys: Res<KeyBindings>,
+    mut hunger: ResMut<Hunger>,
+    mut sounds: Sounds,
+    munches: Res<MunchSounds>,
+    mut rng: ResMut<GlobalRng>,
+) {
+    if !keys.just_pressed(&key_input, Action::Feed) {
+        return;
+    }
+
+    hunger.0 = (hunger.0 - FEED_AMOUNT).max(0.0);
+    info!("Fed Bonnie, hunger now {:.2}.", hunger.0);
+
+    if sounds.try_play(Sound::Munch) {
+        sounds.play(PlaySound::new(munches.random(&mut rng.0)));
+    }
+}
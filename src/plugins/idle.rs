@@ -0,0 +1,421 @@
+//! The Idle state.
+//!
+//! She naps or just sits, picking from the idle sprites, or sulks if she's
+//! grumpy, with a yawn as she settles and a stretch when she gets up. Bringing
+//! the cursor close wakes her, and she purrs while it's nearby. With docking
+//! on she drifts over to the nearest edge of the screen first.
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use rand::prelude::IndexedRandom;
+
+use crate::bonnie::{Bonnie, BonnieWindow, FEET_OFFSET, StateMachine};
+
+use super::{
+    audio::{AudioConfig, audio_enabled},
+    bonnie_state::{
+        BonnieState, BonnieStateDiscriminants, BonnieTransition, GlobalRng, Sprites, StateSystems,
+        entered, exited,
+    },
+    global_cursor::{CursorTracking, GlobalCursorPosition},
+    monitor::MonitorInfo,
+    mood::{Mood, MoodKind},
+};
+
+const PURR_VOLUME: f32 = 0.5;
+/// How close the cursor has to be for her to purr, a little further than it
+/// takes to wake her.
+const PURR_DISTANCE: f32 = 140.0;
+/// How long each frame of a yawn or stretch shows for.
+const SEQUENCE_FRAME_SECS: f32 = 0.35;
+
+pub struct IdlePlugin;
+
+impl Plugin for IdlePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DockConfig>()
+            .init_resource::<IdleSprites>()
+            .add_systems(
+                PreUpdate,
+                // a stretch started on the way out of idling is left to play
+                (stop_sprite_sequences, exit_idling)
+                    .chain()
+                    .in_set(StateSystems::Exit),
+            )
+            .add_systems(
+                PreUpdate,
+                (setup_idling, dock_idling).in_set(StateSystems::Enter),
+            )
+            .add_systems(
+                Update,
+                (
+                    handle_idling,
+                    handle_purring.run_if(audio_enabled),
+                    play_sprite_sequences,
+                )
+                    .chain()
+                    .in_set(StateSystems::Behave),
+            );
+    }
+}
+
+/// Whether Bonnie heads for the nearest screen edge to idle.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct DockConfig {
+    pub enabled: bool,
+    /// Whether corners count too, not just the middle of each edge.
+    pub corners: bool,
+}
+
+/// Where an idle Bonnie's drifting to, to sit against the edge of the screen.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct DockTarget(pub IVec2);
+
+/// The nearest spot against an edge of the `min` to `max` area for a window at
+/// `position`, or in a corner if `corners` is set and one's nearer.
+fn dock_position(position: IVec2, min: IVec2, max: IVec2, corners: bool) -> IVec2 {
+    let position = position.clamp(min, max);
+
+    let edges = [
+        IVec2::new(min.x, position.y),
+        IVec2::new(max.x, position.y),
+        IVec2::new(position.x, min.y),
+        IVec2::new(position.x, max.y),
+    ];
+    let corner_spots = [min, IVec2::new(max.x, min.y), IVec2::new(min.x, max.y), max];
+    let corner_spots = if corners { &corner_spots[..] } else { &[] };
+
+    edges
+        .iter()
+        .chain(corner_spots)
+        .copied()
+        .min_by_key(|spot| spot.distance_squared(position))
+        .unwrap_or(position)
+}
+
+/// Sprites she picks between at random each time she idles, like napping or
+/// just sitting there. A skin can add its own, like a loaf.
+#[derive(Resource, Debug, Clone)]
+pub struct IdleSprites(pub Vec<String>);
+
+impl Default for IdleSprites {
+    fn default() -> Self {
+        Self(vec![
+            "BonSleep.png".to_string(),
+            "BonNormal.png".to_string(),
+        ])
+    }
+}
+
+/// Sprites shown one after another for a moment, before going back to the one
+/// she was showing when it started.
+#[derive(Component, Debug)]
+struct SpriteSequence {
+    frames: Vec<Handle<Image>>,
+    timer: Timer,
+    current: usize,
+    then: Option<Handle<Image>>,
+}
+
+impl SpriteSequence {
+    fn new(frames: Vec<Handle<Image>>) -> Self {
+        Self {
+            frames,
+            timer: Timer::from_seconds(SEQUENCE_FRAME_SECS, TimerMode::Repeating),
+            current: 0,
+            then: None,
+        }
+    }
+}
+
+/// Cuts short any sequence still playing when she changes state again, putting
+/// back what she showed before it so the new state's sprite isn't overwritten
+/// once it ends.
+fn stop_sprite_sequences(
+    mut commands: Commands,
+    mut transitions: EventReader<BonnieTransition>,
+    mut bonnie_query: Query<(&mut Sprite, &mut SpriteSequence)>,
+) {
+    for transition in transitions.read() {
+        let Ok((mut sprite, mut sequence)) = bonnie_query.get_mut(transition.bonnie) else {
+            continue;
+        };
+
+        if let Some(then) = sequence.then.take() {
+            sprite.image = then;
+        }
+        commands
+            .entity(transition.bonnie)
+            .remove::<SpriteSequence>();
+    }
+}
+
+fn play_sprite_sequences(
+    mut commands: Commands,
+    mut bonnie_query: Query<(Entity, &mut Sprite, &mut SpriteSequence)>,
+    time: Res<Time>,
+) {
+    for (entity, mut sprite, mut sequence) in &mut bonnie_query {
+        // whatever the state set goes back on at the end
+        if sequence.then.is_none() {
+            sequence.then = Some(sprite.image.clone());
+            if let Some(frame) = sequence.frames.first() {
+                sprite.image = frame.clone();
+            }
+        }
+
+        if !sequence.timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+
+        sequence.current += 1;
+        match sequence.frames.get(sequence.current) {
+            Some(frame) => sprite.image = frame.clone(),
+            None => {
+                if let Some(then) = sequence.then.take() {
+                    sprite.image = then;
+                }
+                commands.entity(entity).remove::<SpriteSequence>();
+            }
+        }
+    }
+}
+
+/// Picks how she looks while idling.
+#[derive(SystemParam)]
+struct IdleLook<'w> {
+    idle_sprites: Res<'w, IdleSprites>,
+    mood: Res<'w, Mood>,
+    rng: ResMut<'w, GlobalRng>,
+}
+
+impl IdleLook<'_> {
+    fn pick(&mut self) -> &str {
+        // a grumpy bonnie sulks instead
+        match self.mood.kind() {
+            MoodKind::Grumpy => "BonAngry.png",
+            _ => self
+                .idle_sprites
+                .0
+                .choose(&mut self.rng.0)
+                .map_or("BonSleep.png", String::as_str),
+        }
+    }
+}
+
+fn setup_idling(
+    mut commands: Commands,
+    mut transitions: EventReader<BonnieTransition>,
+    mut bonnie_query: Query<(&mut Sprite, &mut StateMachine), With<Bonnie>>,
+    sprites: Sprites,
+    mut look: IdleLook,
+    cursor_tracking: Res<CursorTracking>,
+) {
+    for bonnie in entered(&mut transitions, BonnieStateDiscriminants::Idle) {
+        if let Ok((mut sprite, mut machine)) = bonnie_query.get_mut(bonnie) {
+            // only the cursor can wake her, so without it she just naps
+            if cursor_tracking.available {
+                machine.block();
+            }
+            sprite.image = sprites.load(look.pick());
+
+            // a yawn before she nods off
+            commands.entity(bonnie).insert(SpriteSequence::new(vec![
+                sprites.load("BonNormalMouth.png"),
+                sprites.load("BonNormal.png"),
+            ]));
+        }
+    }
+}
+
+/// Settles her against the nearest edge rather than wherever she stopped.
+fn dock_idling(
+    mut commands: Commands,
+    mut transitions: EventReader<BonnieTransition>,
+    bonnie_query: Query<&BonnieWindow, With<Bonnie>>,
+    window_query: Query<&Window>,
+    monitor_info: Res<MonitorInfo>,
+    dock_config: Res<DockConfig>,
+) {
+    for bonnie in entered(&mut transitions, BonnieStateDiscriminants::Idle) {
+        if dock_config.enabled
+            && let Ok(bonnie_window) = bonnie_query.get(bonnie)
+            && let Ok(window) = window_query.get(bonnie_window.0)
+            && let WindowPosition::At(position) = window.position
+            && let Some(monitor) =
+                monitor_info.nearest(position + window.physical_size().as_ivec2() / 2)
+        {
+            // an edge of the monitor she's on
+            let bounds = monitor.rect();
+            let max = (bounds.max - window.physical_size().as_ivec2()).max(bounds.min);
+            let dock = dock_position(position, bounds.min, max, dock_config.corners);
+            commands.entity(bonnie).insert(DockTarget(dock));
+        }
+    }
+}
+
+fn handle_idling(
+    mut bonnie_query: Query<(&Bonnie, &BonnieWindow, &mut StateMachine)>,
+    global_cursor_pos: Res<GlobalCursorPosition>,
+    window_query: Query<&Window>,
+) {
+    for (bonnie, bonnie_window, mut machine) in &mut bonnie_query {
+        if let BonnieState::Idle = bonnie.state {
+            // get window
+            let Ok(window) = window_query.get(bonnie_window.0) else {
+                continue;
+            };
+
+            // if cursor near bonnie, wake her up
+            // get global cursor pos
+            if let Some(cursor_pos) = global_cursor_pos.0 {
+                // get bonnie position
+                if let WindowPosition::At(bonnie_pos) = window.position {
+                    let diff = (bonnie_pos + FEET_OFFSET).as_vec2() - cursor_pos;
+                    let dist = diff.length();
+
+                    // if cursor near bonnie, change state
+                    if dist < 70.0 {
+                        info!("Waking up...");
+                        machine.finish();
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The purr loop a Bonnie plays while dozing with the cursor nearby.
+#[derive(Component)]
+struct Purr(Entity);
+
+fn handle_purring(
+    mut commands: Commands,
+    bonnie_query: Query<(Entity, &Bonnie, &BonnieWindow, Option<&Purr>)>,
+    window_query: Query<&Window>,
+    players: Query<Option<&AudioSink>>,
+    global_cursor_pos: Res<GlobalCursorPosition>,
+    asset_server: Res<AssetServer>,
+    audio_config: Res<AudioConfig>,
+) {
+    for (entity, bonnie, bonnie_window, purr) in &bonnie_query {
+        let cursor_near = match (
+            global_cursor_pos.0,
+            window_query
+                .get(bonnie_window.0)
+                .map(|window| window.position),
+        ) {
+            (Some(cursor_pos), Ok(WindowPosition::At(bonnie_pos))) => {
+                let diff = (bonnie_pos + FEET_OFFSET).as_vec2() - cursor_pos;
+                diff.length() < PURR_DISTANCE
+            }
+            _ => false,
+        };
+        let purring = matches!(bonnie.state, BonnieState::Idle) && cursor_near;
+
+        // reuse the existing player if it's still around
+        let player = purr.and_then(|p| players.get(p.0).ok().map(|sink| (p.0, sink)));
+
+        match (purring, player) {
+            // keep up with volume changes, and pick the loop back up if paused
+            (true, Some((_, Some(sink)))) => {
+                sink.set_volume(audio_config.volume(PURR_VOLUME).get());
+                if sink.is_paused() {
+                    sink.play();
+                }
+            }
+            // already starting up, leave it be
+            (true, Some((_, None))) => {}
+            // nothing playing, start a loop
+            (true, None) => {
+                let player = commands
+                    .spawn((
+                        AudioPlayer::new(asset_server.load("purr.wav")),
+                        PlaybackSettings::LOOP.with_volume(audio_config.volume(PURR_VOLUME)),
+                    ))
+                    .id();
+                commands.entity(entity).insert(Purr(player));
+            }
+            // pause rather than stop, so a flickering cursor doesn't restart it
+            (false, Some((_, Some(sink)))) => sink.pause(),
+            // stopped before it started, get rid of it
+            (false, Some((player, None))) => {
+                commands.entity(player).despawn();
+                commands.entity(entity).remove::<Purr>();
+            }
+            (false, None) => {}
+        }
+    }
+}
+
+fn exit_idling(
+    mut commands: Commands,
+    mut transitions: EventReader<BonnieTransition>,
+    mut bonnie_query: Query<&mut Sprite, With<Bonnie>>,
+    sprites: Sprites,
+) {
+    let bonnie_asset = sprites.load("BonNormal.png");
+
+    for bonnie in exited(&mut transitions, BonnieStateDiscriminants::Idle) {
+        if let Ok(mut sprite) = bonnie_query.get_mut(bonnie) {
+            // whichever way she was idling, she gets up the same
+            sprite.image = bonnie_asset.clone();
+
+            // and a stretch when she wakes up
+            commands
+                .entity(bonnie)
+                .insert(SpriteSequence::new(vec![sprites.load("BonPaw.png")]));
+
+            // and undocks
+            commands.entity(bonnie).remove::<DockTarget>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+
+    #[test]
+    fn a_transition_mid_sequence_leaves_the_new_state_sprite_alone() {
+        let image = |id| Handle::<Image>::weak_from_u128(id);
+
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+                SEQUENCE_FRAME_SECS / 2.0,
+            )))
+            .add_event::<BonnieTransition>()
+            .add_systems(PreUpdate, stop_sprite_sequences)
+            .add_systems(Update, play_sprite_sequences);
+
+        let bonnie = app
+            .world_mut()
+            .spawn((
+                Sprite::from_image(image(1)),
+                SpriteSequence::new(vec![image(2), image(3)]),
+            ))
+            .id();
+        app.update();
+        assert_eq!(app.world().get::<Sprite>(bonnie).unwrap().image, image(2));
+
+        // she moves on, and the new state puts its own sprite on
+        app.world_mut().send_event(BonnieTransition {
+            bonnie,
+            from: BonnieState::Meowing,
+            to: BonnieState::Chasing,
+        });
+        app.update();
+        app.world_mut().get_mut::<Sprite>(bonnie).unwrap().image = image(4);
+
+        for _ in 0..10 {
+            app.update();
+        }
+
+        assert!(app.world().get::<SpriteSequence>(bonnie).is_none());
+        assert_eq!(app.world().get::<Sprite>(bonnie).unwrap().image, image(4));
+    }
+}
@@ -0,0 +1,412 @@
+//! Commanding a running Bonnie from the shell.
+//!
+//! She listens on a unix socket, `bonnie-buddy.sock` in the runtime directory,
+//! for newline separated commands, answering each with `ok` or `error: ...`:
+//!
+//! - `meow`: meow.
+//! - `calm`: stop and go back to idling.
+//! - `summon`: walk over to the cursor.
+//! - `state <name> [x y]`: drop everything for a state like `walking` or
+//!   `bird`, walking to `x y` if given and somewhere random if not. A state
+//!   that's turned off, or chasing when the cursor can't be found, is skipped.
+//! - `quit`: close Bonnie.
+//!
+//! `bath-hack-25 ctl <command>` sends one and prints her answer. There's no
+//! socket on Windows yet, so there she can't be scripted.
+
+use std::{
+    io,
+    path::PathBuf,
+    sync::{
+        Mutex,
+        mpsc::{self, Receiver, Sender},
+    },
+};
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use rand::Rng;
+
+use crate::bonnie::{Bonnie, BonnieWindow, StateMachine};
+
+use super::{
+    bonnie_state::{
+        BonnieState, BonnieStateDiscriminants, CalmRequest, GlobalRng, Interrupts, StateRules,
+        WanderRegion, random_walk_target,
+    },
+    control::summon_state,
+    global_cursor::GlobalCursorPosition,
+    monitor::MonitorInfo,
+};
+
+pub struct IpcPlugin;
+
+impl Plugin for IpcPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<IpcCommands>()
+            .add_systems(Startup, spawn_listener)
+            .add_systems(Update, handle_commands)
+            .add_systems(Last, remove_socket_on_exit);
+    }
+}
+
+/// Something asked of her over the socket.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IpcCommand {
+    Calm,
+    Quit,
+    /// Something for every Bonnie to drop everything for.
+    Bonnie(BonnieCommand),
+}
+
+/// What each Bonnie's told to do.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BonnieCommand {
+    Meow,
+    Summon,
+    State(BonnieStateDiscriminants, Option<IVec2>),
+}
+
+impl TryFrom<&str> for IpcCommand {
+    type Error = String;
+
+    fn try_from(line: &str) -> Result<Self, Self::Error> {
+        let mut words = line.split_whitespace();
+
+        let command = match words.next() {
+            Some("meow") => Self::Bonnie(BonnieCommand::Meow),
+            Some("calm") => Self::Calm,
+            Some("summon") => Self::Bonnie(BonnieCommand::Summon),
+            Some("quit") => Self::Quit,
+            Some("state") => {
+                let name = words.next().ok_or("state needs a name, like walking")?;
+                let state = name
+                    .to_lowercase()
+                    .parse::<BonnieStateDiscriminants>()
+                    .map_err(|_| format!("unknown state {:?}", name))?;
+
+                let target = match (words.next(), words.next()) {
+                    (None, _) => None,
+                    (Some(x), Some(y)) => {
+                        let parse = |n: &str| {
+                            n.parse::<i32>()
+                                .map_err(|err| format!("invalid coordinate {:?}: {}", n, err))
+                        };
+                        Some(IVec2::new(parse(x)?, parse(y)?))
+                    }
+                    (Some(_), None) => return Err("a target needs both x and y".into()),
+                };
+
+                if target.is_some() && state != BonnieStateDiscriminants::Walking {
+                    return Err(format!("only walking takes a target, not {}", name));
+                }
+
+                Self::Bonnie(BonnieCommand::State(state, target))
+            }
+            Some(other) => return Err(format!("unknown command {:?}", other)),
+            None => return Err("empty command".into()),
+        };
+
+        match words.next() {
+            Some(extra) => Err(format!("unexpected {:?}", extra)),
+            None => Ok(command),
+        }
+    }
+}
+
+/// Commands read off the socket, waiting to be carried out.
+#[derive(Resource)]
+struct IpcCommands {
+    sender: Sender<IpcCommand>,
+    receiver: Mutex<Receiver<IpcCommand>>,
+    /// Whether the socket's this Bonnie's own.
+    listening: bool,
+}
+
+impl Default for IpcCommands {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            sender,
+            receiver: Mutex::new(receiver),
+            listening: false,
+        }
+    }
+}
+
+/// Where she listens for commands.
+fn socket_path() -> PathBuf {
+    dirs::runtime_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("bonnie-buddy.sock")
+}
+
+/// Sends `command` to the running Bonnie, returning her answer.
+#[cfg(unix)]
+pub fn send(command: &str) -> io::Result<String> {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        os::unix::net::UnixStream,
+    };
+
+    let mut stream = UnixStream::connect(socket_path())?;
+    writeln!(stream, "{}", command.trim())?;
+
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    Ok(reply.trim_end().to_string())
+}
+
+#[cfg(not(unix))]
+pub fn send(_command: &str) -> io::Result<String> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "scripting Bonnie isn't supported on this platform",
+    ))
+}
+
+#[cfg(unix)]
+fn spawn_listener(mut commands: ResMut<IpcCommands>) {
+    use std::{
+        os::unix::net::{UnixListener, UnixStream},
+        thread,
+    };
+
+    let path = socket_path();
+
+    // a socket someone's answering is another Bonnie, anything else is stale
+    if UnixStream::connect(&path).is_ok() {
+        warn!(
+            "Another Bonnie's already listening on {}, this one can't be scripted.",
+            path.display()
+        );
+        return;
+    }
+    let _ = std::fs::remove_file(&path);
+
+    let listener = match UnixListener::bind(&path) {
+        Ok(listener) => listener,
+        Err(err) => {
+            warn!("Couldn't listen on {}: {}", path.display(), err);
+            return;
+        }
+    };
+    info!("Listening for commands on {}.", path.display());
+    commands.listening = true;
+
+    let sender = commands.sender.clone();
+    let spawned = thread::Builder::new()
+        .name("ipc-listener".into())
+        .spawn(move || {
+            for stream in listener.incoming().flatten() {
+                // each on its own, so one that never writes can't hold up the rest
+                let sender = sender.clone();
+                let served = thread::Builder::new()
+                    .name("ipc-connection".into())
+                    .spawn(move || serve(stream, sender));
+
+                if let Err(err) = served {
+                    warn!("Couldn't answer a command connection: {}", err);
+                }
+            }
+        });
+
+    if let Err(err) = spawned {
+        warn!("Couldn't start the command listener: {}", err);
+    }
+}
+
+/// Answers the commands on one connection until it's closed.
+#[cfg(unix)]
+fn serve(stream: std::os::unix::net::UnixStream, sender: Sender<IpcCommand>) {
+    use std::io::{BufRead, BufReader, Write};
+
+    let Ok(reader) = stream.try_clone() else {
+        return;
+    };
+    let mut writer = stream;
+
+    for line in BufReader::new(reader).lines().map_while(Result::ok) {
+        let reply = match IpcCommand::try_from(line.as_str()) {
+            Ok(command) => {
+                // the app's gone, nothing left to answer for
+                if sender.send(command).is_err() {
+                    return;
+                }
+                "ok".to_string()
+            }
+            Err(err) => format!("error: {}", err),
+        };
+
+        if writeln!(writer, "{}", reply).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn spawn_listener() {
+    info!("Scripting Bonnie isn't supported on this platform yet.");
+}
+
+/// Where a command sends her walking.
+#[derive(SystemParam)]
+pub struct Destinations<'w, 's> {
+    window_query: Query<'w, 's, &'static Window>,
+    cursor_pos: Res<'w, GlobalCursorPosition>,
+    monitor_info: Res<'w, MonitorInfo>,
+    wander_region: Res<'w, WanderRegion>,
+}
+
+impl Destinations<'_, '_> {
+    /// Over to the cursor, if it's anywhere.
+    fn summon(&self) -> Option<BonnieState> {
+        self.cursor_pos.0.map(summon_state)
+    }
+
+    /// Somewhere random for the Bonnie in `bonnie_window`.
+    pub fn random_walk(&self, rng: &mut impl Rng, bonnie_window: &BonnieWindow) -> BonnieState {
+        let window = self.window_query.get(bonnie_window.0).ok();
+        let window_size = window.map_or(IVec2::ZERO, |window| window.physical_size().as_ivec2());
+        let monitor = self
+            .monitor_info
+            .for_window(window)
+            .map_or(IRect::default(), |monitor| monitor.rect());
+
+        BonnieState::Walking(random_walk_target(
+            rng,
+            monitor,
+            &self.wander_region,
+            window_size,
+        ))
+    }
+}
+
+fn handle_commands(
+    ipc_commands: Res<IpcCommands>,
+    mut bonnie_query: Query<(Entity, &mut Bonnie, &BonnieWindow, &mut StateMachine)>,
+    mut interrupts: Interrupts,
+    mut calm_requests: EventWriter<CalmRequest>,
+    mut app_exit_events: EventWriter<AppExit>,
+    destinations: Destinations,
+    rules: StateRules,
+    mut rng: ResMut<GlobalRng>,
+) {
+    let Ok(receiver) = ipc_commands.receiver.lock() else {
+        return;
+    };
+
+    for command in receiver.try_iter() {
+        info!("Got {:?} over the socket.", command);
+
+        let order = match command {
+            IpcCommand::Calm => {
+                calm_requests.send(CalmRequest { poops: false });
+                continue;
+            }
+            IpcCommand::Quit => {
+                app_exit_events.send(AppExit::Success);
+                continue;
+            }
+            IpcCommand::Bonnie(order) => order,
+        };
+
+        // like chasing without a cursor, which she'd never finish
+        if let BonnieCommand::State(state, _) = &order
+            && !rules.allowed()(*state)
+        {
+            warn!("Bonnie can't go into {:?} at the moment.", state);
+            continue;
+        }
+
+        for (entity, mut bonnie, bonnie_window, mut machine) in &mut bonnie_query {
+            // a forced state is set up afresh, even the one she's already in
+            let (state, restart) = match &order {
+                BonnieCommand::Meow => (BonnieState::Meowing, false),
+                BonnieCommand::Summon => {
+                    let Some(state) = destinations.summon() else {
+                        continue;
+                    };
+                    (state, false)
+                }
+                BonnieCommand::State(BonnieStateDiscriminants::Walking, Some(target)) => {
+                    (BonnieState::Walking(*target), true)
+                }
+                BonnieCommand::State(BonnieStateDiscriminants::Walking, None) => {
+                    (destinations.random_walk(&mut rng.0, bonnie_window), true)
+                }
+                BonnieCommand::State(state, _) => (BonnieState::from(*state), true),
+            };
+
+            if restart {
+                interrupts.restart_to(entity, &mut bonnie, &mut machine, state);
+            } else {
+                interrupts.interrupt_to(entity, &mut bonnie, &mut machine, state);
+            }
+        }
+    }
+}
+
+fn remove_socket_on_exit(mut exit_events: EventReader<AppExit>, ipc_commands: Res<IpcCommands>) {
+    // another Bonnie's socket isn't hers to remove
+    if exit_events.read().next().is_some() && ipc_commands.listening {
+        let _ = std::fs::remove_file(socket_path());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_valid_forms() {
+        assert_eq!(
+            IpcCommand::try_from("meow"),
+            Ok(IpcCommand::Bonnie(BonnieCommand::Meow))
+        );
+        assert_eq!(IpcCommand::try_from("  calm "), Ok(IpcCommand::Calm));
+        assert_eq!(
+            IpcCommand::try_from("summon"),
+            Ok(IpcCommand::Bonnie(BonnieCommand::Summon))
+        );
+        assert_eq!(IpcCommand::try_from("quit"), Ok(IpcCommand::Quit));
+        assert_eq!(
+            IpcCommand::try_from("state Bird"),
+            Ok(IpcCommand::Bonnie(BonnieCommand::State(
+                BonnieStateDiscriminants::Bird,
+                None
+            )))
+        );
+        assert_eq!(
+            IpcCommand::try_from("state walking 100 -20"),
+            Ok(IpcCommand::Bonnie(BonnieCommand::State(
+                BonnieStateDiscriminants::Walking,
+                Some(IVec2::new(100, -20))
+            )))
+        );
+    }
+
+    #[test]
+    fn rejects_an_empty_or_unknown_command() {
+        assert!(IpcCommand::try_from("").is_err());
+        assert!(IpcCommand::try_from("purr").is_err());
+    }
+
+    #[test]
+    fn rejects_a_missing_or_unknown_state() {
+        assert!(IpcCommand::try_from("state").is_err());
+        assert!(IpcCommand::try_from("state napping").is_err());
+    }
+
+    #[test]
+    fn rejects_a_bad_target() {
+        assert!(IpcCommand::try_from("state walking 100").is_err());
+        assert!(IpcCommand::try_from("state walking 100 up").is_err());
+        assert!(IpcCommand::try_from("state bird 100 200").is_err());
+    }
+
+    #[test]
+    fn rejects_trailing_words() {
+        assert!(IpcCommand::try_from("meow loudly").is_err());
+        assert!(IpcCommand::try_from("state walking 1 2 3").is_err());
+    }
+}
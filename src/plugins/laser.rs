@@ -0,0 +1,139 @@
+//! A laser pointer for Bonnie to chase.
+//!
+//! Holding p (or whatever `laser` is bound to) shines a red dot at the cursor
+//! that every Bonnie chases, letting go puts it away and ends the chase.
+
+use bevy::{prelude::*, render::view::RenderLayers};
+
+use crate::{
+    bonnie::{Bonnie, StateMachine},
+    stacking::Stacking,
+    window::PetWindow,
+};
+
+use super::{
+    bonnie_state::{BonnieState, BonnieStateDiscriminants, Interrupts, StateRules},
+    control::{Action, KeyBindings},
+    global_cursor::GlobalCursorPosition,
+};
+
+/// Render layer the laser dot is drawn on.
+const LASER_LAYER: usize = 38;
+/// Size of the laser dot's window.
+const LASER_SIZE: f32 = 12.0;
+
+pub struct LaserPlugin;
+
+impl Plugin for LaserPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (toggle_laser, chase_laser, move_laser).chain());
+    }
+}
+
+/// Where the laser dot is, on the window showing it.
+#[derive(Component, Debug)]
+pub struct LaserDot(pub Vec2);
+
+/// A Bonnie the laser set chasing, who gives up once it's put away.
+#[derive(Component)]
+struct ChasingLaser;
+
+/// Everything making up the laser, so it can be put away in one go.
+#[derive(Component)]
+struct Laser;
+
+fn toggle_laser(
+    mut commands: Commands,
+    key_input: Res<ButtonInput<KeyCode>>,
+    keys: Res<KeyBindings>,
+    cursor_pos: Res<GlobalCursorPosition>,
+    laser_query: Query<Entity, With<Laser>>,
+    stacking: Res<Stacking>,
+) {
+    if keys.just_pressed(&key_input, Action::Laser) && laser_query.is_empty() {
+        let Some(cursor) = cursor_pos.0 else {
+            return;
+        };
+
+        let laser = PetWindow::new(
+            "Laser",
+            Vec2::splat(LASER_SIZE),
+            RenderLayers::layer(LASER_LAYER),
+        )
+        .at(WindowPosition::At(cursor.as_ivec2()))
+        .click_through()
+        .spawn(&mut commands, *stacking, (LaserDot(cursor), Laser));
+        commands.entity(laser.camera).insert(Laser);
+
+        commands.spawn((
+            Sprite::from_color(Color::srgb(1.0, 0.0, 0.0), Vec2::splat(LASER_SIZE)),
+            RenderLayers::layer(LASER_LAYER),
+            Laser,
+        ));
+
+        info!("Laser on.");
+    } else if keys.just_released(&key_input, Action::Laser) && !laser_query.is_empty() {
+        for entity in &laser_query {
+            commands.entity(entity).despawn_recursive();
+        }
+
+        info!("Laser off.");
+    }
+}
+
+/// Every Bonnie drops what she's doing to chase a laser that's just come on,
+/// and the ones it set chasing give up once it's put away.
+fn chase_laser(
+    mut commands: Commands,
+    new_lasers: Query<(), Added<LaserDot>>,
+    mut removed_lasers: RemovedComponents<LaserDot>,
+    mut bonnie_query: Query<(Entity, &mut Bonnie, &mut StateMachine, Has<ChasingLaser>)>,
+    mut interrupts: Interrupts,
+    rules: StateRules,
+) {
+    if !new_lasers.is_empty() && rules.allowed()(BonnieStateDiscriminants::Chasing) {
+        for (entity, mut bonnie, mut machine, _) in &mut bonnie_query {
+            // already after the cursor, and she'll carry on after the laser
+            if bonnie.state == BonnieState::Chasing {
+                continue;
+            }
+
+            interrupts.interrupt_to(entity, &mut bonnie, &mut machine, BonnieState::Chasing);
+            commands.entity(entity).insert(ChasingLaser);
+        }
+    }
+
+    if removed_lasers.read().next().is_some() {
+        for (entity, bonnie, mut machine, chasing_laser) in &mut bonnie_query {
+            if !chasing_laser {
+                continue;
+            }
+
+            if bonnie.state == BonnieState::Chasing {
+                machine.finish();
+            }
+            commands.entity(entity).remove::<ChasingLaser>();
+        }
+    }
+}
+
+fn move_laser(
+    cursor_pos: Res<GlobalCursorPosition>,
+    mut laser_query: Query<(&mut LaserDot, &mut Window)>,
+) {
+    let Some(cursor) = cursor_pos.0 else {
+        return;
+    };
+
+    for (mut dot, mut window) in &mut laser_query {
+        dot.0 = cursor;
+
+        // centre the dot on the cursor
+        let half_size = window.physical_size().as_ivec2() / 2;
+        let position = WindowPosition::At(cursor.as_ivec2() - half_size);
+
+        if window.position != position {
+            window.position = position;
+        }
+    }
+}
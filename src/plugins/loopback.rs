@@ -0,0 +1,177 @@
+//! Listening to whatever the computer's playing, so Bonnie can dance to it.
+//!
+//! When turned on in the config, what's going out to the speakers is captured
+//! on a background thread and boiled down to a loudness level. Only some
+//! platforms let that happen:
+//!
+//! - Windows: loopback capture of the default output device.
+//! - Linux and macOS: an input device that carries the output, like a
+//!   PulseAudio or PipeWire monitor source or BlackHole on macOS.
+//!
+//! Anywhere else, or if capturing fails, there's no level and she dances to a
+//! steady beat instead.
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU32, Ordering},
+    },
+    thread,
+};
+
+use bevy::prelude::*;
+use cpal::{
+    Device, SampleFormat, Stream, StreamConfig,
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+};
+
+/// Scales the captured loudness up so ordinary music reaches near 1.0.
+const LEVEL_GAIN: f32 = 4.0;
+/// How much of the last level carries over to each new buffer, so the level
+/// jumps up with a beat but falls away smoothly after it.
+const LEVEL_RELEASE: f32 = 0.85;
+
+pub struct LoopbackPlugin;
+
+impl Plugin for LoopbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LoopbackConfig>()
+            .init_resource::<AudioLevel>()
+            .add_systems(
+                Startup,
+                start_capture.run_if(|config: Res<LoopbackConfig>| config.enabled),
+            );
+    }
+}
+
+#[derive(Resource, Default, Debug, Clone)]
+pub struct LoopbackConfig {
+    /// Whether to capture the system's audio output at all.
+    pub enabled: bool,
+}
+
+/// How loud the system's audio output is, from the capture thread.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct AudioLevel {
+    level: Arc<AtomicU32>,
+    capturing: Arc<AtomicBool>,
+}
+
+impl AudioLevel {
+    /// Loudness from 0.0, silent, to 1.0, loud, or none if nothing's being
+    /// captured.
+    pub fn get(&self) -> Option<f32> {
+        self.capturing
+            .load(Ordering::Relaxed)
+            .then(|| f32::from_bits(self.level.load(Ordering::Relaxed)))
+    }
+
+    fn push(&self, rms: f32) {
+        let previous = f32::from_bits(self.level.load(Ordering::Relaxed));
+        let level = (rms * LEVEL_GAIN).min(1.0).max(previous * LEVEL_RELEASE);
+        self.level.store(level.to_bits(), Ordering::Relaxed);
+    }
+}
+
+fn start_capture(audio_level: Res<AudioLevel>) {
+    let audio_level = audio_level.clone();
+
+    // the stream stops when dropped, and can't always leave the thread it was
+    // made on, so it lives out the app parked on its own thread
+    let spawned = thread::Builder::new()
+        .name("loopback-capture".into())
+        .spawn(move || match capture(audio_level.clone()) {
+            Ok(_stream) => {
+                audio_level.capturing.store(true, Ordering::Relaxed);
+                info!("Capturing the system's audio for Bonnie to dance to.");
+                loop {
+                    thread::park();
+                }
+            }
+            Err(err) => info!(
+                "Can't capture the system's audio, Bonnie will dance to her own beat: {}",
+                err
+            ),
+        });
+
+    if let Err(err) = spawned {
+        warn!("Couldn't start loopback capture: {}", err);
+    }
+}
+
+/// The device carrying what's being played, and how to read from it.
+#[cfg(target_os = "windows")]
+fn loopback_device() -> Result<(Device, SampleFormat, StreamConfig), String> {
+    // WASAPI captures what an output device plays when asked for input from it
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or("no output device")?;
+    let config = device
+        .default_output_config()
+        .map_err(|err| err.to_string())?;
+
+    Ok((device, config.sample_format(), config.into()))
+}
+
+#[cfg(not(target_os = "windows"))]
+fn loopback_device() -> Result<(Device, SampleFormat, StreamConfig), String> {
+    let device = cpal::default_host()
+        .input_devices()
+        .map_err(|err| err.to_string())?
+        .find(|device| {
+            device.name().is_ok_and(|name| {
+                let name = name.to_lowercase();
+                ["monitor", "loopback", "blackhole"]
+                    .iter()
+                    .any(|hint| name.contains(hint))
+            })
+        })
+        .ok_or("no monitor or loopback input device")?;
+    let config = device
+        .default_input_config()
+        .map_err(|err| err.to_string())?;
+
+    Ok((device, config.sample_format(), config.into()))
+}
+
+fn capture(audio_level: AudioLevel) -> Result<Stream, String> {
+    let (device, format, config) = loopback_device()?;
+    let on_error = |err| warn!("Loopback capture failed: {}", err);
+
+    let stream = match format {
+        SampleFormat::F32 => device.build_input_stream(
+            &config,
+            move |data: &[f32], _| audio_level.push(rms(data.iter().copied())),
+            on_error,
+            None,
+        ),
+        SampleFormat::I16 => device.build_input_stream(
+            &config,
+            move |data: &[i16], _| {
+                audio_level.push(rms(data
+                    .iter()
+                    .map(|sample| *sample as f32 / i16::MAX as f32)))
+            },
+            on_error,
+            None,
+        ),
+        other => return Err(format!("unsupported sample format {}", other)),
+    }
+    .map_err(|err| err.to_string())?;
+
+    stream.play().map_err(|err| err.to_string())?;
+    Ok(stream)
+}
+
+/// Root mean square of `samples`, how loud they are overall.
+fn rms(samples: impl Iterator<Item = f32>) -> f32 {
+    let (sum, count) = samples.fold((0.0, 0), |(sum, count), sample| {
+        (sum + sample * sample, count + 1)
+    });
+
+    if count == 0 {
+        0.0
+    } else {
+        (sum / count as f32).sqrt()
+    }
+}
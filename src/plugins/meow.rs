@@ -0,0 +1,86 @@
+//! The Meowing state.
+//!
+//! She meows once, a random recording panned to wherever she is on screen,
+//! and moves straight on. Meows have a cooldown and stay quiet in quiet hours.
+
+use bevy::prelude::*;
+use rand::{Rng, prelude::IndexedRandom};
+
+use crate::bonnie::{BonnieWindow, StateMachine};
+
+use super::{
+    audio::{PlaySound, Sound, Sounds},
+    bonnie_state::{
+        BonnieStateDiscriminants, BonnieTransition, GlobalRng, StateSystems, entered, window_pan,
+    },
+    monitor::MonitorInfo,
+};
+
+pub struct MeowPlugin;
+
+impl Plugin for MeowPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PreUpdate, do_meow.in_set(StateSystems::Enter));
+    }
+}
+
+fn do_meow(
+    mut transitions: EventReader<BonnieTransition>,
+    mut sounds: Sounds,
+    mut rng: ResMut<GlobalRng>,
+    mut bonnie_query: Query<(&BonnieWindow, &mut StateMachine)>,
+    window_query: Query<&Window>,
+    monitor_info: Res<MonitorInfo>,
+) {
+    for bonnie in entered(&mut transitions, BonnieStateDiscriminants::Meowing) {
+        let Ok((bonnie_window, mut machine)) = bonnie_query.get_mut(bonnie) else {
+            continue;
+        };
+
+        // meowed too recently or it's quiet hours, move straight on
+        if !sounds.try_play(Sound::Meow) {
+            machine.finish();
+            continue;
+        }
+
+        // meow from wherever she is on screen
+        let pan = window_query
+            .get(bonnie_window.0)
+            .map_or(0.0, |window| window_pan(window, &monitor_info));
+
+        let meow = PlaySound::new(random_meow(&mut rng.0)).panned(pan);
+        let speed = sounds.config.meow_speed(&mut rng.0);
+        sounds.play(meow.speed(speed));
+
+        machine.finish();
+    }
+}
+
+fn random_meow(rng: &mut impl Rng) -> String {
+    const MEOWS: &[&str] = &[
+        "meows/anais.ogg",
+        "meows/bella.ogg",
+        "meows/ben.ogg",
+        "meows/caroline.ogg",
+        "meows/dimitra.ogg",
+        "meows/dom.ogg",
+        "meows/helen-long-quack.ogg",
+        "meows/helen.ogg",
+        "meows/helen-quack.ogg",
+        "meows/julian.ogg",
+        "meows/kenneth.ogg",
+        "meows/kian.ogg",
+        "meows/laura.ogg",
+        "meows/maddie.ogg",
+        "meows/manya.ogg",
+        "meows/nehal.ogg",
+        "meows/phoebe.ogg",
+        "meows/rose.ogg",
+        "meows/stemple.ogg",
+        "meows/tanmay.ogg",
+        "meows/tiff.ogg",
+        "meows/will-sasaki.ogg",
+        "meows/zoe.ogg",
+    ];
+    MEOWS.choose(rng).unwrap().to_string()
+}
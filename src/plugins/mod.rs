@@ -1,3 +1,36 @@
+pub mod audio;
+pub mod battery;
+pub mod birds;
 pub mod bonnie_state;
+pub mod boredom;
+pub mod chase;
+pub mod compositor;
 pub mod control;
+pub mod cpu_load;
+pub mod dance;
+pub mod drag;
+pub mod footsteps;
+pub mod fullscreen;
 pub mod global_cursor;
+pub mod hunger;
+pub mod idle;
+pub mod ipc;
+pub mod laser;
+pub mod loopback;
+pub mod meow;
+pub mod monitor;
+pub mod mood;
+pub mod particles;
+pub mod persistence;
+pub mod physics;
+pub mod pomodoro;
+pub mod poop;
+pub mod recording;
+pub mod scratch;
+pub mod screen_lock;
+pub mod snapshot;
+pub mod state_sounds;
+pub mod teaching;
+pub mod typing;
+pub mod waypoints;
+pub mod window_flags;
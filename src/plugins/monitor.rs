@@ -0,0 +1,300 @@
+//! Keeping the monitor information Bonnie relies on up to date.
+//!
+//! Bevy only reads a monitor's size when it's first connected, so changing
+//! resolution or DPI while running would otherwise leave it stale. The
+//! monitors are gathered into `MonitorInfo`, so everything else can read the
+//! one Bonnie's on without looking it up again. Unplugging the monitor she's
+//! on moves her over to one that's still there.
+
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    time::common_conditions::on_timer,
+    window::{Monitor, PrimaryMonitor},
+    winit::WinitWindows,
+};
+
+use crate::{
+    bonnie::{Bonnie, BonnieWindow},
+    plugins::bonnie_state::{BonnieState, window_centre},
+};
+
+pub struct MonitorPlugin;
+
+impl Plugin for MonitorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MonitorInfo>()
+            .add_systems(
+                PreUpdate,
+                (
+                    update_monitor_info,
+                    migrate_off_lost_monitors,
+                    reclamp_to_monitor,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                refresh_monitors.run_if(on_timer(Duration::from_secs(1))),
+            );
+    }
+}
+
+/// Where a monitor is and how big, in physical pixels.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonitorLayout {
+    pub name: Option<String>,
+    /// Top left corner on the desktop.
+    pub origin: IVec2,
+    pub size: UVec2,
+    pub scale_factor: f64,
+}
+
+impl MonitorLayout {
+    /// Where it covers on the desktop.
+    pub fn rect(&self) -> IRect {
+        IRect::from_corners(self.origin, self.origin + self.size.as_ivec2())
+    }
+}
+
+/// Every connected monitor, and which one Bonnie's on.
+#[derive(Resource, Default, Debug, Clone, PartialEq)]
+pub struct MonitorInfo {
+    pub monitors: Vec<MonitorLayout>,
+    /// Index into `monitors` of the one Bonnie's on.
+    active: usize,
+    /// Name of the monitor the first Bonnie was last seen on.
+    bonnie_on: Option<String>,
+}
+
+impl MonitorInfo {
+    /// The monitor Bonnie's on, or none before any monitors are known.
+    pub fn active(&self) -> Option<&MonitorLayout> {
+        self.monitors.get(self.active)
+    }
+
+    /// The monitor covering `point` on the desktop, if any.
+    pub fn monitor_at(&self, point: IVec2) -> Option<&MonitorLayout> {
+        self.monitors
+            .iter()
+            .find(|monitor| monitor.rect().contains(point))
+    }
+
+    /// The monitor covering `point` on the desktop, or failing that the one
+    /// closest to it.
+    pub fn nearest(&self, point: IVec2) -> Option<&MonitorLayout> {
+        self.monitor_at(point).or_else(|| {
+            self.monitors.iter().min_by(|a, b| {
+                let distance = |monitor: &MonitorLayout| {
+                    let rect = monitor.rect();
+                    point
+                        .clamp(rect.min, rect.max)
+                        .as_vec2()
+                        .distance_squared(point.as_vec2())
+                };
+                distance(a).total_cmp(&distance(b))
+            })
+        })
+    }
+
+    /// The monitor `window` is on, or the one Bonnie's on if it isn't on one
+    /// or hasn't been placed yet.
+    pub fn for_window(&self, window: Option<&Window>) -> Option<&MonitorLayout> {
+        window
+            .and_then(window_centre)
+            .and_then(|centre| self.nearest(centre))
+            .or_else(|| self.active())
+    }
+
+    /// Size of the monitor Bonnie's on, zero if there isn't one yet.
+    pub fn size(&self) -> UVec2 {
+        self.active().map_or(UVec2::ZERO, |monitor| monitor.size)
+    }
+}
+
+/// Gathers up the monitors, only touching `MonitorInfo` when something's
+/// actually changed so its change detection means something.
+fn update_monitor_info(
+    monitor_query: Query<(&Monitor, Has<PrimaryMonitor>)>,
+    mut monitor_info: ResMut<MonitorInfo>,
+) {
+    let monitors: Vec<_> = monitor_query
+        .iter()
+        .map(|(monitor, primary)| {
+            (
+                MonitorLayout {
+                    name: monitor.name.clone(),
+                    origin: monitor.physical_position,
+                    size: monitor.physical_size(),
+                    scale_factor: monitor.scale_factor,
+                },
+                primary,
+            )
+        })
+        .collect();
+
+    // the one she's on, otherwise the primary one, otherwise whichever's first
+    let active = monitors
+        .iter()
+        .position(|(layout, _)| {
+            monitor_info.bonnie_on.is_some() && layout.name == monitor_info.bonnie_on
+        })
+        .or_else(|| monitors.iter().position(|(_, primary)| *primary))
+        .unwrap_or(0);
+
+    let updated = MonitorInfo {
+        monitors: monitors.into_iter().map(|(layout, _)| layout).collect(),
+        active,
+        bonnie_on: monitor_info.bonnie_on.clone(),
+    };
+
+    if *monitor_info != updated {
+        if let Some(monitor) = updated.active() {
+            info!(
+                "Bonnie's on monitor {:?}, {}x{} at {}.",
+                monitor.name, monitor.size.x, monitor.size.y, monitor.origin
+            );
+        }
+        *monitor_info = updated;
+    }
+}
+
+/// Moves Bonnies left off every monitor after one's unplugged onto the one
+/// she's now counted as on, bringing any walk target along.
+fn migrate_off_lost_monitors(
+    mut monitor_info: ResMut<MonitorInfo>,
+    mut bonnie_query: Query<(&mut Bonnie, &BonnieWindow)>,
+    mut window_query: Query<&mut Window>,
+    mut known: Local<usize>,
+) {
+    if !monitor_info.is_changed() {
+        return;
+    }
+
+    let lost = monitor_info.monitors.len() < *known;
+    *known = monitor_info.monitors.len();
+    if !lost {
+        return;
+    }
+
+    let Some(monitor) = monitor_info.active().cloned() else {
+        return;
+    };
+    let bounds = monitor.rect();
+
+    for (mut bonnie, bonnie_window) in &mut bonnie_query {
+        let Ok(mut window) = window_query.get_mut(bonnie_window.0) else {
+            continue;
+        };
+        let WindowPosition::At(position) = window.position else {
+            continue;
+        };
+
+        let window_size = window.physical_size().as_ivec2();
+        if monitor_info
+            .monitor_at(position + window_size / 2)
+            .is_some()
+        {
+            continue;
+        }
+
+        let max = (bounds.max - window_size).max(bounds.min);
+        let migrated = position.clamp(bounds.min, max);
+        warn!(
+            "Bonnie's monitor was disconnected, moving her to {:?} at {}.",
+            monitor.name, migrated
+        );
+        window.position = WindowPosition::At(migrated);
+
+        if let BonnieState::Walking(target) = &mut bonnie.state {
+            *target = target.clamp(bounds.min, max);
+        }
+    }
+
+    // she's on it now, rather than waiting for the next refresh to notice
+    monitor_info.bonnie_on = monitor.name;
+}
+
+fn refresh_monitors(
+    winit_windows: NonSend<WinitWindows>,
+    bonnie_query: Query<&BonnieWindow>,
+    mut monitors: Query<&mut Monitor>,
+    mut monitor_info: ResMut<MonitorInfo>,
+) {
+    for (i, bonnie_window) in bonnie_query.iter().enumerate() {
+        let Some(current) = winit_windows
+            .get_window(bonnie_window.0)
+            .and_then(|window| window.current_monitor())
+        else {
+            continue;
+        };
+
+        let name = current.name();
+        if i == 0 && monitor_info.bonnie_on != name {
+            monitor_info.bonnie_on = name.clone();
+        }
+        let size = current.size();
+        let scale_factor = current.scale_factor();
+
+        // the monitor bonnie is on, or the only one if names aren't available
+        let single = monitors.iter().count() == 1;
+        for mut monitor in &mut monitors {
+            if !single && monitor.name != name {
+                continue;
+            }
+
+            let stale = monitor.physical_width != size.width
+                || monitor.physical_height != size.height
+                || monitor.scale_factor != scale_factor;
+
+            if stale {
+                info!(
+                    "Monitor {:?} changed to {}x{} at {}x scale.",
+                    name, size.width, size.height, scale_factor
+                );
+                monitor.physical_width = size.width;
+                monitor.physical_height = size.height;
+                monitor.scale_factor = scale_factor;
+            }
+        }
+    }
+}
+
+/// Pulls each Bonnie and her walk target back onto the monitor they're on
+/// when the monitors change, so shrinking one doesn't leave her off its edge.
+fn reclamp_to_monitor(
+    monitor_info: Res<MonitorInfo>,
+    mut bonnie_query: Query<(&mut Bonnie, &BonnieWindow)>,
+    mut window_query: Query<&mut Window>,
+) {
+    if !monitor_info.is_changed() {
+        return;
+    }
+
+    for (mut bonnie, bonnie_window) in &mut bonnie_query {
+        let Ok(mut window) = window_query.get_mut(bonnie_window.0) else {
+            continue;
+        };
+        let window_size = window.physical_size().as_ivec2();
+
+        // the furthest a window placed at `position` can go on its monitor
+        let clamp = |position: IVec2| {
+            let rect = monitor_info.nearest(position + window_size / 2)?.rect();
+            Some(position.clamp(rect.min, (rect.max - window_size).max(rect.min)))
+        };
+
+        if let BonnieState::Walking(target) = &mut bonnie.state
+            && let Some(clamped) = clamp(*target)
+        {
+            *target = clamped;
+        }
+
+        if let WindowPosition::At(position) = window.position
+            && let Some(clamped) = clamp(position)
+            && clamped != position
+        {
+            window.position = WindowPosition::At(clamped);
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! Bonnie's mood.
+//!
+//! Keeping the cursor on her pets her and cheers her up, with a burst of
+//! confetti once she's been petted for a moment, while ignoring her
+//! for a long while or closing the windows she makes, by clicking a lesson
+//! away or dismissing them all, gets her grumpy. Either
+//! way she drifts back to neutral over time. A happy Bonnie plays and meows
+//! more, a grumpy one poops, scratches and sulks more.
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::bonnie::{Bonnie, BonnieWindow, FEET_OFFSET};
+
+use super::{
+    bonnie_state::BonnieStateDiscriminants,
+    global_cursor::GlobalCursorPosition,
+    particles::{ParticleBurst, SpawnParticles},
+};
+
+/// How close the cursor has to be to her feet to pet her.
+const PET_DISTANCE: f32 = 100.0;
+/// How much happier each second of petting makes her.
+const PET_RATE: f32 = 0.1;
+/// Seconds of petting before it counts as a proper pet, worth celebrating.
+const GOOD_PET_SECS: f32 = 2.0;
+/// Seconds without being petted before she feels ignored.
+const IGNORED_AFTER_SECS: f32 = 10.0 * 60.0;
+/// How much grumpier each second of being ignored makes her, outpacing her
+/// drift back to neutral.
+const IGNORED_RATE: f32 = 0.01;
+/// How much she drifts back towards neutral each second.
+const DECAY_RATE: f32 = 0.005;
+/// How much having her windows closed on her annoys her.
+const CLOSED_WINDOW_MOOD: f32 = -0.15;
+/// How far from neutral she has to be to count as happy or grumpy.
+const MOOD_THRESHOLD: f32 = 0.33;
+
+pub struct MoodPlugin;
+
+impl Plugin for MoodPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Mood>()
+            .add_event::<MoodChange>()
+            .add_systems(Update, (pet_bonnie, apply_mood_changes, decay_mood).chain());
+    }
+}
+
+/// A nudge to her mood, positive to cheer her up and negative to annoy her.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct MoodChange(pub f32);
+
+/// Letting her know her windows were closed on her, which she doesn't like.
+#[derive(SystemParam)]
+pub struct WindowsClosed<'w> {
+    mood_changes: EventWriter<'w, MoodChange>,
+}
+
+impl WindowsClosed<'_> {
+    pub fn on_her(&mut self) {
+        self.mood_changes.send(MoodChange(CLOSED_WINDOW_MOOD));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoodKind {
+    Happy,
+    Neutral,
+    Grumpy,
+}
+
+/// How she's feeling from -1.0, grumpy, to 1.0, happy.
+#[derive(Resource, Default, Debug)]
+pub struct Mood {
+    pub level: f32,
+    /// Seconds since she was last petted.
+    ignored_for: f32,
+    /// Seconds she's been petted for without a break.
+    petted_for: f32,
+}
+
+impl Mood {
+    pub fn kind(&self) -> MoodKind {
+        if self.level > MOOD_THRESHOLD {
+            MoodKind::Happy
+        } else if self.level < -MOOD_THRESHOLD {
+            MoodKind::Grumpy
+        } else {
+            MoodKind::Neutral
+        }
+    }
+
+    /// Whether the cursor's resting on her right now.
+    pub fn being_petted(&self) -> bool {
+        self.petted_for > 0.0
+    }
+
+    /// How much more or less likely `state` gets in her current mood.
+    pub fn weight_scale(&self, state: BonnieStateDiscriminants) -> f32 {
+        match (self.kind(), state) {
+            (MoodKind::Happy, BonnieStateDiscriminants::Meowing) => 1.5,
+            (MoodKind::Happy, BonnieStateDiscriminants::Chasing) => 1.5,
+            (MoodKind::Grumpy, BonnieStateDiscriminants::Pooping) => 2.0,
+            (MoodKind::Grumpy, BonnieStateDiscriminants::Scratch) => 2.0,
+            (MoodKind::Grumpy, BonnieStateDiscriminants::Idle) => 1.5,
+            _ => 1.0,
+        }
+    }
+
+    fn nudge(&mut self, amount: f32) {
+        let before = self.kind();
+        self.level = (self.level + amount).clamp(-1.0, 1.0);
+
+        if self.kind() != before {
+            info!("Bonnie's now {:?}.", self.kind());
+        }
+    }
+}
+
+/// Cheers her up while the cursor's resting on her.
+fn pet_bonnie(
+    bonnie_query: Query<&BonnieWindow, With<Bonnie>>,
+    window_query: Query<&Window>,
+    cursor_pos: Res<GlobalCursorPosition>,
+    mut mood: ResMut<Mood>,
+    mut particles: EventWriter<SpawnParticles>,
+    time: Res<Time>,
+) {
+    // where the petted bonnie's feet are
+    let petted = cursor_pos.0.and_then(|cursor| {
+        bonnie_query.iter().find_map(|bonnie_window| {
+            let Ok(WindowPosition::At(position)) =
+                window_query.get(bonnie_window.0).map(|w| w.position)
+            else {
+                return None;
+            };
+
+            let feet = position + FEET_OFFSET;
+            ((feet.as_vec2() - cursor).length() < PET_DISTANCE).then_some(feet)
+        })
+    });
+
+    let dt = time.delta_secs();
+
+    if let Some(feet) = petted {
+        mood.ignored_for = 0.0;
+        mood.nudge(PET_RATE * dt);
+
+        let was_good = mood.petted_for >= GOOD_PET_SECS;
+        mood.petted_for += dt;
+        if !was_good && mood.petted_for >= GOOD_PET_SECS {
+            particles.send(SpawnParticles {
+                at: feet,
+                burst: ParticleBurst::confetti(),
+            });
+        }
+        return;
+    }
+
+    mood.petted_for = 0.0;
+    mood.ignored_for += dt;
+    if mood.ignored_for > IGNORED_AFTER_SECS {
+        mood.nudge(-IGNORED_RATE * dt);
+    }
+}
+
+fn apply_mood_changes(mut changes: EventReader<MoodChange>, mut mood: ResMut<Mood>) {
+    for change in changes.read() {
+        mood.nudge(change.0);
+    }
+}
+
+fn decay_mood(mut mood: ResMut<Mood>, time: Res<Time>) {
+    let decay = DECAY_RATE * time.delta_secs();
+    let level = mood.level;
+    mood.nudge(-level.signum() * decay.min(level.abs()));
+}
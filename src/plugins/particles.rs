@@ -0,0 +1,163 @@
+//! Short bursts of particles, like confetti when Bonnie's happy.
+//!
+//! Each burst gets its own click-through window centred where it goes off,
+//! drawing the shared particle layer from that spot on the desktop. Particles
+//! fall and fade, and the window goes once they're all gone.
+
+use bevy::{prelude::*, render::view::RenderLayers};
+use rand::{Rng, prelude::IndexedRandom};
+
+use crate::{stacking::Stacking, window::PetWindow};
+
+use super::bonnie_state::GlobalRng;
+
+/// Render layer particles are drawn on.
+const PARTICLE_LAYER: usize = 36;
+/// Size of each burst's window, big enough for particles to spread out in.
+const BURST_SIZE: f32 = 300.0;
+/// How fast particles fall in pixels/s².
+const PARTICLE_GRAVITY: f32 = 600.0;
+
+pub struct ParticlePlugin;
+
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SpawnParticles>().add_systems(
+            Update,
+            (spawn_bursts, update_particles, clean_up_bursts).chain(),
+        );
+    }
+}
+
+/// What a burst looks like.
+#[derive(Debug, Clone)]
+pub struct ParticleBurst {
+    pub count: usize,
+    /// Each particle picks one at random.
+    pub colors: Vec<Color>,
+    /// Seconds each particle lasts, fading out as it goes.
+    pub lifetime: f32,
+    /// Fastest a particle starts off in pixels/s.
+    pub speed: f32,
+    /// Width and height of each particle.
+    pub size: f32,
+}
+
+impl ParticleBurst {
+    pub fn confetti() -> Self {
+        Self {
+            count: 40,
+            colors: vec![
+                Color::srgb(1.0, 0.3, 0.4),
+                Color::srgb(1.0, 0.8, 0.2),
+                Color::srgb(0.3, 0.8, 0.4),
+                Color::srgb(0.3, 0.6, 1.0),
+                Color::srgb(0.8, 0.4, 1.0),
+            ],
+            lifetime: 1.5,
+            speed: 350.0,
+            size: 6.0,
+        }
+    }
+}
+
+/// Sets off `burst` centred on `at`, in pixels from the top left of the screen.
+#[derive(Event, Debug, Clone)]
+pub struct SpawnParticles {
+    pub at: IVec2,
+    pub burst: ParticleBurst,
+}
+
+#[derive(Component, Debug)]
+struct Particle {
+    velocity: Vec2,
+    lifetime: Timer,
+    color: Color,
+}
+
+/// A burst's window and camera, put away once its particles have faded.
+#[derive(Component, Debug)]
+struct Burst(Timer);
+
+fn spawn_bursts(
+    mut commands: Commands,
+    mut spawn_events: EventReader<SpawnParticles>,
+    stacking: Res<Stacking>,
+    mut rng: ResMut<GlobalRng>,
+) {
+    for SpawnParticles { at, burst } in spawn_events.read() {
+        let layer = RenderLayers::layer(PARTICLE_LAYER);
+        // the world's laid out like the screen, so bursts can share the layer
+        let origin = Vec2::new(at.x as f32, -at.y as f32);
+
+        let window = PetWindow::new("Confetti", Vec2::splat(BURST_SIZE), layer.clone())
+            .at(WindowPosition::At(
+                *at - IVec2::splat(BURST_SIZE as i32 / 2),
+            ))
+            .click_through()
+            .spawn(
+                &mut commands,
+                *stacking,
+                Burst(Timer::from_seconds(burst.lifetime, TimerMode::Once)),
+            );
+        commands.entity(window.camera).insert((
+            Transform::from_translation(origin.extend(0.0)),
+            Burst(Timer::from_seconds(burst.lifetime, TimerMode::Once)),
+        ));
+
+        for _ in 0..burst.count {
+            let Some(&color) = burst.colors.choose(&mut rng.0) else {
+                break;
+            };
+
+            // mostly upwards, so they arc over and fall
+            let angle = rng.0.random_range(0.2..std::f32::consts::PI - 0.2);
+            let speed = rng.0.random_range(0.3..=1.0) * burst.speed;
+
+            commands.spawn((
+                Sprite::from_color(color, Vec2::splat(burst.size)),
+                Transform::from_translation(origin.extend(1.0)),
+                Particle {
+                    velocity: Vec2::from_angle(angle) * speed,
+                    lifetime: Timer::from_seconds(burst.lifetime, TimerMode::Once),
+                    color,
+                },
+                layer.clone(),
+            ));
+        }
+    }
+}
+
+fn update_particles(
+    mut commands: Commands,
+    mut particle_query: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_secs();
+
+    for (entity, mut particle, mut transform, mut sprite) in &mut particle_query {
+        if particle.lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        particle.velocity.y -= PARTICLE_GRAVITY * dt;
+        transform.translation += (particle.velocity * dt).extend(0.0);
+        transform.rotate_z(particle.velocity.x.signum() * 8.0 * dt);
+
+        let alpha = particle.lifetime.fraction_remaining();
+        sprite.color = particle.color.with_alpha(alpha);
+    }
+}
+
+fn clean_up_bursts(
+    mut commands: Commands,
+    mut burst_query: Query<(Entity, &mut Burst)>,
+    time: Res<Time>,
+) {
+    for (entity, mut burst) in &mut burst_query {
+        if burst.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,271 @@
+//! Remembering Bonnie between runs.
+//!
+//! Her stats, mood, hunger and where her window was are saved to
+//! `bonnie-buddy/state.toml` in the OS's data directory every so often and on
+//! exit, then read back at startup. Saves are written to a temporary file and
+//! renamed over the old one, so a crash mid-save can't leave it half written.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::*,
+    window::{Monitor, PrimaryWindow},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bonnie::{Bonnie, BonnieWindow},
+    cli::Cli,
+};
+
+use super::{
+    bonnie_state::{BonnieStateDiscriminants, BonnieTransition},
+    hunger::{Hunger, HungerConfig},
+    mood::Mood,
+};
+
+pub struct PersistencePlugin;
+
+impl Plugin for PersistencePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PersistenceConfig>()
+            .init_resource::<Stats>()
+            .add_systems(Startup, load_state)
+            .add_systems(Update, (count_stats, autosave).chain())
+            .add_systems(Last, save_on_exit);
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct PersistenceConfig {
+    /// How often she's saved while running, on top of on exit. Zero only
+    /// saves on exit.
+    pub autosave: Duration,
+}
+
+impl Default for PersistenceConfig {
+    fn default() -> Self {
+        Self {
+            autosave: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Running totals of what she's been up to, kept across runs.
+#[derive(Resource, Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct Stats {
+    /// How many times she's been petted.
+    pub pets: u32,
+    /// How many times she's gone into each state.
+    pub states: BTreeMap<BonnieStateDiscriminants, u32>,
+}
+
+/// Everything saved between runs.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct PersistentState {
+    pub mood: f32,
+    pub hunger: f32,
+    /// Where the first Bonnie's window was, in pixels from the top left.
+    pub position: Option<[i32; 2]>,
+    pub stats: Stats,
+}
+
+impl PersistentState {
+    /// Where she's saved by default.
+    fn path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("bonnie-buddy/state.toml"))
+    }
+
+    /// Reads what was saved at `path`, falling back to the defaults if it's
+    /// missing or broken.
+    fn load(path: &Path) -> Self {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) => {
+                warn!(
+                    "Couldn't read Bonnie's saved state {}, starting afresh: {}",
+                    path.display(),
+                    err
+                );
+                return Self::default();
+            }
+        };
+
+        toml::from_str(&contents).unwrap_or_else(|err| {
+            warn!(
+                "Ignoring invalid saved state {}, starting afresh: {}",
+                path.display(),
+                err
+            );
+            Self::default()
+        })
+    }
+
+    /// Writes her to `path` in one go, via a temporary file next to it.
+    fn save(&self, path: &Path) -> io::Result<()> {
+        let contents = toml::to_string(self).map_err(io::Error::other)?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // the rename swaps it in whole, so there's never half a file
+        let temp = path.with_extension("toml.tmp");
+        fs::write(&temp, contents)?;
+        fs::rename(&temp, path)
+    }
+}
+
+fn load_state(
+    mut stats: ResMut<Stats>,
+    mut mood: ResMut<Mood>,
+    mut hunger: ResMut<Hunger>,
+    hunger_config: Res<HungerConfig>,
+    cli: Res<Cli>,
+    mut primary_window: Query<&mut Window, With<PrimaryWindow>>,
+    monitor_query: Query<&Monitor>,
+) {
+    let Some(path) = PersistentState::path() else {
+        return;
+    };
+
+    let state = PersistentState::load(&path);
+
+    *stats = state.stats;
+    mood.level = state.mood.clamp(-1.0, 1.0);
+    // a saved hunger would still sway her while she can't be fed
+    if hunger_config.enabled {
+        hunger.0 = state.hunger.clamp(0.0, 1.0);
+    }
+
+    // being told where to start beats where she was left
+    if let Some([x, y]) = state.position
+        && cli.start_at.is_none()
+        && let Ok(mut window) = primary_window.get_single_mut()
+    {
+        let position = IVec2::new(x, y);
+        let centre = position + window.physical_size().as_ivec2() / 2;
+
+        // a monitor that's since been unplugged would leave her off screen
+        let on_a_monitor = monitor_query.iter().any(|monitor| {
+            let origin = monitor.physical_position;
+            IRect::from_corners(origin, origin + monitor.physical_size().as_ivec2())
+                .contains(centre)
+        });
+
+        if on_a_monitor {
+            window.position = WindowPosition::At(position);
+        } else {
+            info!(
+                "Bonnie was left at {} which isn't on a connected monitor, starting her afresh.",
+                position
+            );
+        }
+    }
+
+    info!(
+        "Loaded Bonnie's saved state, petted {} times so far.",
+        stats.pets
+    );
+}
+
+fn count_stats(
+    mut stats: ResMut<Stats>,
+    mut transitions: EventReader<BonnieTransition>,
+    mood: Res<Mood>,
+    mut was_petted: Local<bool>,
+) {
+    for transition in transitions.read() {
+        *stats
+            .states
+            .entry(BonnieStateDiscriminants::from(&transition.to))
+            .or_default() += 1;
+    }
+
+    if mood.being_petted() && !*was_petted {
+        stats.pets += 1;
+    }
+    *was_petted = mood.being_petted();
+}
+
+/// Everything worth keeping between runs.
+#[derive(SystemParam)]
+struct Keepsakes<'w, 's> {
+    stats: Res<'w, Stats>,
+    mood: Res<'w, Mood>,
+    hunger: Res<'w, Hunger>,
+    bonnie_query: Query<'w, 's, (&'static Bonnie, &'static BonnieWindow)>,
+    window_query: Query<'w, 's, &'static Window>,
+}
+
+impl Keepsakes<'_, '_> {
+    /// Gathers it all into one place for saving.
+    fn snapshot(&self) -> PersistentState {
+        let position = self
+            .bonnie_query
+            .iter()
+            .find(|(bonnie, _)| bonnie.index == 0)
+            .and_then(|(_, bonnie_window)| self.window_query.get(bonnie_window.0).ok())
+            .and_then(|window| match window.position {
+                WindowPosition::At(position) => Some(position.to_array()),
+                _ => None,
+            });
+
+        PersistentState {
+            mood: self.mood.level,
+            hunger: self.hunger.0,
+            position,
+            stats: self.stats.clone(),
+        }
+    }
+}
+
+fn save(state: &PersistentState) {
+    let Some(path) = PersistentState::path() else {
+        return;
+    };
+
+    if let Err(err) = state.save(&path) {
+        warn!(
+            "Couldn't save Bonnie's state to {}: {}",
+            path.display(),
+            err
+        );
+    }
+}
+
+fn autosave(
+    config: Res<PersistenceConfig>,
+    keepsakes: Keepsakes,
+    mut since_save: Local<Duration>,
+    time: Res<Time>,
+) {
+    if config.autosave.is_zero() {
+        return;
+    }
+
+    *since_save += time.delta();
+    if *since_save < config.autosave {
+        return;
+    }
+    *since_save = Duration::ZERO;
+
+    debug!("Autosaving Bonnie's state.");
+    save(&keepsakes.snapshot());
+}
+
+fn save_on_exit(mut exit_events: EventReader<AppExit>, keepsakes: Keepsakes) {
+    if exit_events.read().next().is_none() {
+        return;
+    }
+
+    save(&keepsakes.snapshot());
+}
@@ -0,0 +1,166 @@
+//! Optional gravity and throwing for Bonnie.
+//!
+//! While airborne she flies with her velocity, stopping at or bouncing off the
+//! edges of the monitor. With gravity on she falls towards the bottom of the
+//! monitor and walking and chasing keep her on the ground, otherwise she slows
+//! to a stop.
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::{
+    bonnie::BonnieWindow,
+    edges::{EdgeBehaviour, push_inward},
+};
+
+use super::{
+    audio::{PlaySound, Sounds},
+    bonnie_state::window_centre,
+    drag::NotHeld,
+    monitor::MonitorInfo,
+};
+
+/// Downwards acceleration in pixels/s².
+const GRAVITY: f32 = 2400.0;
+/// Fraction of her speed kept when bouncing off an edge.
+const RESTITUTION: f32 = 0.35;
+/// Slowest landing that still bounces, anything slower just stops.
+const MIN_BOUNCE_SPEED: f32 = 150.0;
+/// How quickly she slows down in the air (without gravity) or sliding along the ground.
+const FRICTION: f32 = 3.0;
+/// Below this speed she stops flying.
+const SETTLE_SPEED: f32 = 30.0;
+
+pub struct PhysicsPlugin;
+
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PhysicsConfig>()
+            .add_systems(Update, integrate_velocity);
+    }
+}
+
+#[derive(Resource, Default, Debug, Clone)]
+pub struct PhysicsConfig {
+    pub gravity: bool,
+}
+
+/// How fast a Bonnie is flying through the air, in pixels/s.
+#[derive(Component, Default, Debug, Clone, Copy)]
+pub struct Velocity(pub Vec2);
+
+/// Marks a Bonnie that's flying, so nothing else moves her.
+#[derive(Component, Debug)]
+pub struct Airborne;
+
+/// Whether she falls, and what she does on hitting an edge.
+#[derive(SystemParam)]
+struct Rules<'w> {
+    physics_config: Res<'w, PhysicsConfig>,
+    edge_behaviour: Res<'w, EdgeBehaviour>,
+}
+
+fn integrate_velocity(
+    mut commands: Commands,
+    mut bonnie_query: Query<(Entity, &BonnieWindow, Option<&Velocity>), NotHeld>,
+    mut window_query: Query<&mut Window>,
+    monitor_info: Res<MonitorInfo>,
+    rules: Rules,
+    mut sounds: Sounds,
+    time: Res<Time>,
+) {
+    let Some(active) = monitor_info.active() else {
+        return;
+    };
+
+    let dt = time.delta_secs();
+
+    for (entity, bonnie_window, velocity) in &mut bonnie_query {
+        // without gravity only thrown bonnies move
+        if !rules.physics_config.gravity && velocity.is_none() {
+            continue;
+        }
+
+        let Ok(mut window) = window_query.get_mut(bonnie_window.0) else {
+            continue;
+        };
+
+        let WindowPosition::At(position) = window.position else {
+            continue;
+        };
+
+        // the monitor she's over, and the furthest her window can go while
+        // staying on it
+        let rect = window_centre(&window)
+            .and_then(|centre| monitor_info.monitor_at(centre))
+            .unwrap_or(active)
+            .rect();
+        let min = rect.min;
+        let max = rect.max - window.physical_size().as_ivec2();
+
+        let flying = velocity.is_some();
+        let mut velocity = velocity.map_or(Vec2::ZERO, |v| v.0);
+        let grounded = position.y >= max.y && velocity.y >= 0.0;
+
+        if rules.physics_config.gravity && !grounded {
+            velocity.y += GRAVITY * dt;
+        }
+
+        if !rules.physics_config.gravity || grounded {
+            velocity *= (1.0 - FRICTION * dt).max(0.0);
+        }
+
+        let mut new_position = position + (velocity * dt).round().as_ivec2();
+
+        // the sides and top, the bottom is the ground
+        let inward = push_inward(new_position, min, IVec2::new(max.x, i32::MAX));
+        if inward.x != 0 {
+            new_position.x = new_position.x.clamp(min.x, max.x.max(min.x));
+            velocity.x = rebound(velocity.x, inward.x, *rules.edge_behaviour);
+        }
+        if inward.y != 0 {
+            new_position.y = min.y;
+            velocity.y = rebound(velocity.y, inward.y, *rules.edge_behaviour);
+        }
+
+        // hit the bottom, she always bounces when landing under gravity
+        if new_position.y > max.y || (rules.physics_config.gravity && new_position.y == max.y) {
+            new_position.y = max.y;
+
+            let bounces =
+                rules.physics_config.gravity || *rules.edge_behaviour == EdgeBehaviour::Bounce;
+            if bounces && velocity.y > MIN_BOUNCE_SPEED {
+                if rules.physics_config.gravity {
+                    sounds.play(PlaySound::new("bonk.wav").volume(0.6));
+                }
+                velocity.y = -velocity.y * RESTITUTION;
+            } else if velocity.y > 0.0 {
+                velocity.y = 0.0;
+            }
+        }
+
+        if new_position != position {
+            window.position = WindowPosition::At(new_position);
+        }
+
+        let on_ground = new_position.y >= max.y && velocity.y >= 0.0;
+        let settled = velocity.length() < SETTLE_SPEED;
+
+        if settled && (!rules.physics_config.gravity || on_ground) {
+            if flying {
+                commands.entity(entity).remove::<(Airborne, Velocity)>();
+            }
+        } else {
+            commands
+                .entity(entity)
+                .insert((Airborne, Velocity(velocity)));
+        }
+    }
+}
+
+/// Velocity along an axis after hitting an edge, `inward` pointing back onto the monitor.
+fn rebound(velocity: f32, inward: i32, edge_behaviour: EdgeBehaviour) -> f32 {
+    match edge_behaviour {
+        EdgeBehaviour::Bounce => velocity.abs() * inward as f32 * RESTITUTION,
+        EdgeBehaviour::Clamp => 0.0,
+    }
+}
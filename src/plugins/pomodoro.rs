@@ -0,0 +1,105 @@
+//! Pomodoro timer mode.
+//!
+//! After each work interval Bonnie comes to teach you to take a break, and
+//! after the break she comes back to tell you to get back to it. Off unless a
+//! work interval is set.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    bonnie::{Bonnie, StateMachine},
+    notify::Notifications,
+};
+
+use super::teaching::Messengers;
+
+pub struct PomodoroPlugin;
+
+impl Plugin for PomodoroPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PomodoroConfig>()
+            .init_resource::<Notifications>()
+            .add_systems(Startup, start_pomodoro)
+            .add_systems(Update, handle_pomodoro);
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct PomodoroConfig {
+    /// How long to work between breaks, no breaks if unset.
+    pub work: Option<Duration>,
+    /// How long each break lasts.
+    pub rest: Duration,
+}
+
+impl Default for PomodoroConfig {
+    fn default() -> Self {
+        Self {
+            work: None,
+            rest: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+/// Where the pomodoro cycle is up to.
+#[derive(Resource, Debug)]
+struct Pomodoro {
+    timer: Timer,
+    on_break: bool,
+}
+
+fn start_pomodoro(mut commands: Commands, config: Res<PomodoroConfig>) {
+    let Some(work) = config.work else {
+        return;
+    };
+
+    info!("Pomodoro on, breaks every {:?}.", work);
+    commands.insert_resource(Pomodoro {
+        timer: Timer::new(work, TimerMode::Once),
+        on_break: false,
+    });
+}
+
+fn handle_pomodoro(
+    pomodoro: Option<ResMut<Pomodoro>>,
+    config: Res<PomodoroConfig>,
+    notifications: Res<Notifications>,
+    mut bonnie_query: Query<(Entity, &mut Bonnie, &mut StateMachine)>,
+    mut messengers: Messengers,
+    time: Res<Time>,
+) {
+    let (Some(mut pomodoro), Some(work)) = (pomodoro, config.work) else {
+        return;
+    };
+
+    if !pomodoro.timer.tick(time.delta()).finished() {
+        return;
+    }
+
+    let message = if pomodoro.on_break {
+        pomodoro.timer = Timer::new(work, TimerMode::Once);
+        "Break's over, back to work!".to_string()
+    } else {
+        pomodoro.timer = Timer::new(config.rest, TimerMode::Once);
+        format!(
+            "Time for a break! I'll come and get you in {} minutes.",
+            (config.rest.as_secs_f32() / 60.0).round()
+        )
+    };
+    pomodoro.on_break = !pomodoro.on_break;
+
+    // in case she's hidden behind something
+    notifications.notify("Bonnie Buddy", &message);
+
+    // only the first bonnie brings the message
+    let Some((entity, mut bonnie, mut machine)) = bonnie_query
+        .iter_mut()
+        .find(|(_, bonnie, _)| bonnie.index == 0)
+    else {
+        return;
+    };
+
+    messengers.bring(entity, &mut bonnie, &mut machine, message);
+}
@@ -0,0 +1,418 @@
+//! The Pooping state, and what happens to her poops after.
+//!
+//! Each poop gets a little window behind her feet that stays until it's dealt
+//! with. Clicking one eats it, and dragging one puts a trash can out in the
+//! corner to drop it in. Either way it fades out before it goes.
+
+use bevy::{
+    ecs::system::SystemParam,
+    input::{ButtonState, mouse::MouseButtonInput},
+    prelude::*,
+    render::{camera::RenderTarget, view::RenderLayers},
+    window::{PresentMode, WindowRef},
+};
+
+use crate::{
+    bonnie::{BonnieWindow, StateMachine},
+    stacking::Stacking,
+    window::PetWindow,
+};
+
+use super::{
+    audio::{MunchSounds, PlaySound, Sound, Sounds},
+    bonnie_state::{
+        BonnieStateDiscriminants, BonnieTransition, GlobalRng, OwnedBy, Sprites, StateSystems,
+        entered, window_pan,
+    },
+    global_cursor::GlobalCursorPosition,
+    monitor::MonitorInfo,
+};
+
+/// Shared by every poop window.
+const POOP_LAYER: usize = 42;
+const TRASH_LAYER: usize = 47;
+/// Layers for fading poops start here, well clear of every Bonnie's layers.
+pub const FADE_LAYER_BASE: usize = 4096;
+/// How many poops can fade at once before layers get reused.
+pub const FADE_LAYERS: usize = 32;
+/// Width and height of a poop's window.
+const POOP_SIZE: f32 = 40.0;
+const POOP_FADE_SECS: f32 = 0.3;
+/// How far a poop has to be dragged before it isn't a click to eat it.
+const POOP_DRAG_THRESHOLD: f32 = 8.0;
+const TRASH_SIZE: f32 = 60.0;
+/// Gap between the trash can and the corner of the monitor.
+const TRASH_MARGIN: i32 = 40;
+
+pub struct PoopPlugin;
+
+impl Plugin for PoopPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PoopConfig>()
+            .add_systems(Startup, setup_poop_sprite)
+            .add_systems(PreUpdate, setup_pooping.in_set(StateSystems::Enter))
+            .add_systems(
+                Update,
+                (start_poop_drag, update_poop_drag, end_poop_drag, fade_poops)
+                    .chain()
+                    .in_set(StateSystems::Input),
+            );
+    }
+}
+
+/// Where her poops land.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct PoopConfig {
+    /// From her window's top left to the poop's, in pixels. Unset puts it
+    /// just behind her feet, whatever size her window is.
+    pub offset: Option<IVec2>,
+}
+
+#[derive(Component)]
+pub struct PoopWindow;
+
+/// The one poop sprite, drawn by the camera of every poop window so
+/// they don't need their own. It lives for the whole app.
+fn setup_poop_sprite(mut commands: Commands, sprites: Sprites) {
+    // get the sprite
+    let mut poop_sprite = Sprite::from_image(sprites.load("BonPoop.png"));
+    poop_sprite.custom_size = Some(Vec2::splat(POOP_SIZE));
+
+    // add to poop render layer
+    commands.spawn((poop_sprite, RenderLayers::layer(POOP_LAYER), PoopSprite));
+}
+
+fn setup_pooping(
+    mut commands: Commands,
+    mut transitions: EventReader<BonnieTransition>,
+    mut bonnie_query: Query<(&BonnieWindow, &mut StateMachine)>,
+    window_query: Query<&Window>,
+    monitor_info: Res<MonitorInfo>,
+    poop_config: Res<PoopConfig>,
+    stacking: Res<Stacking>,
+) {
+    for bonnie in entered(&mut transitions, BonnieStateDiscriminants::Pooping) {
+        let Ok((bonnie_window, mut machine)) = bonnie_query.get_mut(bonnie) else {
+            continue;
+        };
+
+        let Ok(window) = window_query.get(bonnie_window.0) else {
+            continue;
+        };
+        let WindowPosition::At(bonnie_position) = window.position else {
+            continue;
+        };
+
+        let bonnie_size = window.physical_size().as_ivec2();
+        let poop_size = (Vec2::splat(POOP_SIZE) * window.scale_factor()).as_ivec2();
+        let offset = poop_config
+            .offset
+            .unwrap_or_else(|| poop_offset(bonnie_size, poop_size));
+        let mut position = bonnie_position + offset;
+
+        // kept whole on the monitor she's on, even with her up against an edge
+        if let Some(monitor) = monitor_info
+            .monitor_at(bonnie_position + bonnie_size / 2)
+            .or(monitor_info.active())
+        {
+            let bounds = monitor.rect();
+            position = position.clamp(bounds.min, (bounds.max - poop_size).max(bounds.min));
+        }
+
+        PetWindow::new(
+            "Poop!",
+            Vec2::splat(POOP_SIZE),
+            RenderLayers::layer(POOP_LAYER),
+        )
+        .at(WindowPosition::At(position))
+        .present_mode(PresentMode::AutoNoVsync)
+        .spawn(&mut commands, *stacking, (PoopWindow, OwnedBy(bonnie)));
+
+        machine.finish();
+    }
+}
+
+/// Where a poop window of `poop_size` goes relative to a Bonnie window of
+/// `bonnie_size`, centred a little below her middle so it sits behind her feet.
+fn poop_offset(bonnie_size: IVec2, poop_size: IVec2) -> IVec2 {
+    IVec2::new(
+        (bonnie_size.x - poop_size.x) / 2,
+        bonnie_size.y * 3 / 4 - poop_size.y / 2,
+    )
+}
+
+/// The sprite every poop window shows.
+#[derive(Component)]
+struct PoopSprite;
+
+/// A poop window fading out before it's despawned, with the camera and sprite
+/// it was given to fade on its own.
+#[derive(Component, Debug)]
+pub struct Fading {
+    timer: Timer,
+    camera: Entity,
+    sprite: Entity,
+}
+
+/// A poop being dragged, maybe towards the trash can.
+#[derive(Component, Debug)]
+struct DraggedPoop {
+    /// Cursor position relative to the window's top left.
+    offset: Vec2,
+    /// Where the cursor was when the drag started.
+    start: Vec2,
+    /// Whether it's gone far enough to not count as a click.
+    moved: bool,
+}
+
+#[derive(Component)]
+struct TrashWindow;
+
+/// Everything making up the trash can, so it can be put away in one go.
+#[derive(Component)]
+struct Trash;
+
+/// The trash can put out while a poop's being dragged.
+#[derive(SystemParam)]
+struct TrashCan<'w, 's> {
+    windows: Query<'w, 's, &'static Window, With<TrashWindow>>,
+    parts: Query<'w, 's, Entity, With<Trash>>,
+}
+
+impl TrashCan<'_, '_> {
+    fn is_out(&self) -> bool {
+        !self.windows.is_empty()
+    }
+
+    /// Whether `cursor` is over it.
+    fn contains(&self, cursor: Vec2) -> bool {
+        self.windows.iter().any(|trash| {
+            let WindowPosition::At(position) = trash.position else {
+                return false;
+            };
+            let min = position.as_vec2();
+            let max = min + trash.physical_size().as_vec2();
+            cursor.cmpge(min).all() && cursor.cmple(max).all()
+        })
+    }
+
+    fn put_away(&self, commands: &mut Commands) {
+        for entity in &self.parts {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Poops that can be picked up, not already in hand or on their way out.
+type LoosePoop = (With<PoopWindow>, Without<DraggedPoop>, Without<Fading>);
+
+fn start_poop_drag(
+    mut commands: Commands,
+    mut mouse_events: EventReader<MouseButtonInput>,
+    poop_query: Query<&Window, LoosePoop>,
+    trash: TrashCan,
+    monitor_info: Res<MonitorInfo>,
+    cursor_pos: Res<GlobalCursorPosition>,
+    stacking: Res<Stacking>,
+) {
+    for event in mouse_events.read() {
+        if event.button != MouseButton::Left || event.state != ButtonState::Pressed {
+            continue;
+        }
+
+        let Ok(window) = poop_query.get(event.window) else {
+            continue;
+        };
+
+        let (Some(cursor), WindowPosition::At(position)) = (cursor_pos.0, window.position) else {
+            continue;
+        };
+
+        commands.entity(event.window).insert(DraggedPoop {
+            offset: cursor - position.as_vec2(),
+            start: cursor,
+            moved: false,
+        });
+
+        if trash.is_out() {
+            continue;
+        }
+
+        // put the trash can out in the bottom right corner of the poop's monitor
+        let Some(monitor) = monitor_info.for_window(Some(window)) else {
+            continue;
+        };
+        let position = monitor.rect().max - IVec2::splat(TRASH_SIZE as i32 + TRASH_MARGIN);
+
+        let trash = PetWindow::new(
+            "Trash",
+            Vec2::splat(TRASH_SIZE),
+            RenderLayers::layer(TRASH_LAYER),
+        )
+        .at(WindowPosition::At(position))
+        .click_through()
+        .spawn(&mut commands, *stacking, (TrashWindow, Trash));
+        commands.entity(trash.camera).insert(Trash);
+
+        // a can and its lid
+        let grey = Color::srgb(0.55, 0.57, 0.6);
+        commands.spawn((
+            Sprite::from_color(grey, Vec2::new(36.0, 40.0)),
+            Transform::from_xyz(0.0, -8.0, 0.0),
+            RenderLayers::layer(TRASH_LAYER),
+            Trash,
+        ));
+        commands.spawn((
+            Sprite::from_color(grey, Vec2::new(46.0, 6.0)),
+            Transform::from_xyz(0.0, 17.0, 0.0),
+            RenderLayers::layer(TRASH_LAYER),
+            Trash,
+        ));
+    }
+}
+
+fn update_poop_drag(
+    mut poop_query: Query<(&mut Window, &mut DraggedPoop)>,
+    cursor_pos: Res<GlobalCursorPosition>,
+) {
+    let Some(cursor) = cursor_pos.0 else {
+        return;
+    };
+
+    for (mut window, mut drag) in &mut poop_query {
+        if (cursor - drag.start).length() > POOP_DRAG_THRESHOLD {
+            drag.moved = true;
+        }
+
+        if drag.moved {
+            let position = WindowPosition::At((cursor - drag.offset).round().as_ivec2());
+            if window.position != position {
+                window.position = position;
+            }
+        }
+    }
+}
+
+/// Gives a poop window its own copy of the sprite to fade out on, so the
+/// others aren't faded with it.
+#[derive(SystemParam)]
+struct PoopFades<'w, 's> {
+    poop_sprite: Query<'w, 's, &'static Sprite, With<PoopSprite>>,
+    cameras: Query<'w, 's, (Entity, &'static Camera)>,
+    next_layer: Local<'s, usize>,
+}
+
+impl PoopFades<'_, '_> {
+    fn start(&mut self, commands: &mut Commands, window: Entity) {
+        let layer = RenderLayers::layer(FADE_LAYER_BASE + *self.next_layer);
+        *self.next_layer = (*self.next_layer + 1) % FADE_LAYERS;
+
+        let sprite = commands
+            .spawn((self.poop_sprite.single().clone(), layer.clone()))
+            .id();
+
+        for (camera_entity, camera) in &self.cameras {
+            let RenderTarget::Window(WindowRef::Entity(target)) = camera.target else {
+                continue;
+            };
+
+            if target == window {
+                commands.entity(camera_entity).insert(layer.clone());
+                commands.entity(window).insert(Fading {
+                    timer: Timer::from_seconds(POOP_FADE_SECS, TimerMode::Once),
+                    camera: camera_entity,
+                    sprite,
+                });
+            }
+        }
+    }
+}
+
+/// What she sounds like eating a poop, or binning one.
+#[derive(SystemParam)]
+struct PoopSounds<'w, 's> {
+    sounds: Sounds<'w, 's>,
+    munches: Res<'w, MunchSounds>,
+    monitor_info: Res<'w, MonitorInfo>,
+    rng: ResMut<'w, GlobalRng>,
+}
+
+impl PoopSounds<'_, '_> {
+    /// A random munch from where `window` is, unless she's munched too recently.
+    fn munch(&mut self, window: &Window) {
+        if !self.sounds.try_play(Sound::Munch) {
+            return;
+        }
+
+        self.sounds.play(
+            PlaySound::new(self.munches.random(&mut self.rng.0))
+                .panned(window_pan(window, &self.monitor_info)),
+        );
+    }
+
+    fn trash(&mut self) {
+        self.sounds.play(PlaySound::new("trash.wav").volume(0.8));
+    }
+}
+
+/// Bins a poop dropped on the trash can or eats one that was only clicked.
+fn end_poop_drag(
+    mut commands: Commands,
+    mut mouse_events: EventReader<MouseButtonInput>,
+    poop_query: Query<(Entity, &Window, &DraggedPoop)>,
+    trash: TrashCan,
+    mut fades: PoopFades,
+    cursor_pos: Res<GlobalCursorPosition>,
+    mut poop_sounds: PoopSounds,
+) {
+    let released = mouse_events
+        .read()
+        .any(|event| event.button == MouseButton::Left && event.state == ButtonState::Released);
+
+    if !released || poop_query.is_empty() {
+        return;
+    }
+
+    // whether the cursor let go over the trash can
+    let over_trash = cursor_pos.0.is_some_and(|cursor| trash.contains(cursor));
+
+    for (entity, window, drag) in &poop_query {
+        if !drag.moved || over_trash {
+            commands.entity(entity).remove::<DraggedPoop>();
+            fades.start(&mut commands, entity);
+        }
+
+        if !drag.moved {
+            // just a click, eat it
+            poop_sounds.munch(window);
+        } else if over_trash {
+            poop_sounds.trash();
+        } else {
+            // dropped somewhere else, leave it there
+            commands.entity(entity).remove::<DraggedPoop>();
+        }
+    }
+
+    trash.put_away(&mut commands);
+}
+
+fn fade_poops(
+    mut commands: Commands,
+    mut fading_query: Query<(Entity, &mut Fading)>,
+    mut sprite_query: Query<&mut Sprite>,
+    time: Res<Time>,
+) {
+    for (entity, mut fading) in &mut fading_query {
+        fading.timer.tick(time.delta());
+
+        if let Ok(mut sprite) = sprite_query.get_mut(fading.sprite) {
+            sprite.color.set_alpha(fading.timer.fraction_remaining());
+        }
+
+        if fading.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+            commands.entity(fading.camera).despawn_recursive();
+            commands.entity(fading.sprite).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,260 @@
+//! Recording Bonnie's states and positions to a file and playing them back.
+//!
+//! `--record` writes a line whenever a Bonnie moves or changes state, and
+//! `--replay` drives them from such a file instead of picking states at
+//! random, carrying on as normal once it runs out.
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use bevy::{prelude::*, utils::HashMap};
+use strum::IntoEnumIterator;
+
+use crate::bonnie::{Bonnie, BonnieWindow};
+
+use super::bonnie_state::{BonnieState, BonnieStateDiscriminants, BonnieTransition};
+
+pub struct RecordingPlugin;
+
+impl Plugin for RecordingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RecordingConfig>()
+            .add_systems(Startup, (start_recording, load_replay))
+            .add_systems(
+                PostUpdate,
+                (
+                    record_frame.run_if(resource_exists::<Recorder>),
+                    play_replay.run_if(resource_exists::<Replay>),
+                ),
+            );
+    }
+}
+
+#[derive(Resource, Default, Debug, Clone)]
+pub struct RecordingConfig {
+    /// File to record to.
+    pub record: Option<PathBuf>,
+    /// File to play back.
+    pub replay: Option<PathBuf>,
+}
+
+/// Where a Bonnie was and what she was doing at some point.
+#[derive(Debug, Clone)]
+struct Sample {
+    secs: f32,
+    index: usize,
+    position: IVec2,
+    state: BonnieState,
+}
+
+#[derive(Resource)]
+struct Recorder {
+    file: BufWriter<File>,
+    /// What was last written for each Bonnie, so only changes get written.
+    last: HashMap<usize, (IVec2, BonnieState)>,
+}
+
+/// A recording being played back, random state changes are off while it exists.
+#[derive(Resource, Debug)]
+pub struct Replay {
+    samples: Vec<Sample>,
+    next: usize,
+}
+
+fn start_recording(mut commands: Commands, config: Res<RecordingConfig>) {
+    let Some(path) = &config.record else {
+        return;
+    };
+
+    let file = File::create(path).and_then(|file| {
+        let mut file = BufWriter::new(file);
+        writeln!(file, "# seconds index x y state")?;
+        Ok(file)
+    });
+
+    match file {
+        Ok(file) => {
+            info!("Recording to {}.", path.display());
+            commands.insert_resource(Recorder {
+                file,
+                last: HashMap::default(),
+            });
+        }
+        Err(err) => warn!("Couldn't record to {}: {}", path.display(), err),
+    }
+}
+
+fn load_replay(mut commands: Commands, config: Res<RecordingConfig>) {
+    let Some(path) = &config.replay else {
+        return;
+    };
+
+    match read_samples(path) {
+        Ok(samples) => {
+            info!(
+                "Replaying {} samples from {}.",
+                samples.len(),
+                path.display()
+            );
+            commands.insert_resource(Replay { samples, next: 0 });
+        }
+        Err(err) => warn!("Couldn't replay {}: {}", path.display(), err),
+    }
+}
+
+fn read_samples(path: &Path) -> io::Result<Vec<Sample>> {
+    let mut samples = Vec::new();
+
+    for (number, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let sample = parse_sample(line).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {} isn't a sample: {:?}", number + 1, line),
+            )
+        })?;
+        samples.push(sample);
+    }
+
+    // played back in order of time
+    samples.sort_by(|a, b| a.secs.total_cmp(&b.secs));
+    Ok(samples)
+}
+
+fn parse_sample(line: &str) -> Option<Sample> {
+    let mut fields = line.split_whitespace();
+    let mut next = || fields.next();
+
+    let secs = next()?.parse().ok()?;
+    let index = next()?.parse().ok()?;
+    let position = IVec2::new(next()?.parse().ok()?, next()?.parse().ok()?);
+
+    let name = next()?;
+    let discriminant = BonnieStateDiscriminants::iter().find(|d| state_name(*d) == name)?;
+    let state = match BonnieState::from(discriminant) {
+        BonnieState::Walking(_) => {
+            BonnieState::Walking(IVec2::new(next()?.parse().ok()?, next()?.parse().ok()?))
+        }
+        BonnieState::Patrolling(_) => BonnieState::Patrolling(next()?.parse().ok()?),
+        state => state,
+    };
+
+    Some(Sample {
+        secs,
+        index,
+        position,
+        state,
+    })
+}
+
+/// How a state's written in a recording.
+fn state_name(state: BonnieStateDiscriminants) -> String {
+    format!("{:?}", state).to_lowercase()
+}
+
+fn write_sample(file: &mut impl Write, sample: &Sample) -> io::Result<()> {
+    write!(
+        file,
+        "{:.3} {} {} {} {}",
+        sample.secs,
+        sample.index,
+        sample.position.x,
+        sample.position.y,
+        state_name(BonnieStateDiscriminants::from(&sample.state))
+    )?;
+
+    match sample.state {
+        BonnieState::Walking(target) => writeln!(file, " {} {}", target.x, target.y),
+        BonnieState::Patrolling(leg) => writeln!(file, " {}", leg),
+        _ => writeln!(file),
+    }
+}
+
+fn record_frame(
+    mut commands: Commands,
+    mut recorder: ResMut<Recorder>,
+    bonnie_query: Query<(&Bonnie, &BonnieWindow)>,
+    window_query: Query<&Window>,
+    time: Res<Time>,
+) {
+    let recorder = &mut *recorder;
+    let mut result = Ok(());
+
+    for (bonnie, bonnie_window) in &bonnie_query {
+        let Ok(WindowPosition::At(position)) =
+            window_query.get(bonnie_window.0).map(|w| w.position)
+        else {
+            continue;
+        };
+
+        let current = (position, bonnie.state.clone());
+        if recorder.last.get(&bonnie.index) == Some(&current) {
+            continue;
+        }
+
+        let sample = Sample {
+            secs: time.elapsed_secs(),
+            index: bonnie.index,
+            position,
+            state: bonnie.state.clone(),
+        };
+        result = result.and_then(|_| write_sample(&mut recorder.file, &sample));
+        recorder.last.insert(bonnie.index, current);
+    }
+
+    if let Err(err) = result.and_then(|_| recorder.file.flush()) {
+        warn!("Stopped recording: {}", err);
+        commands.remove_resource::<Recorder>();
+    }
+}
+
+fn play_replay(
+    mut commands: Commands,
+    mut replay: ResMut<Replay>,
+    mut bonnie_query: Query<(Entity, &mut Bonnie, &BonnieWindow)>,
+    mut window_query: Query<&mut Window>,
+    mut transitions: EventWriter<BonnieTransition>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_secs();
+
+    while let Some(sample) = replay.samples.get(replay.next) {
+        if sample.secs > now {
+            break;
+        }
+
+        let found = bonnie_query
+            .iter_mut()
+            .find(|(_, bonnie, _)| bonnie.index == sample.index);
+
+        if let Some((entity, mut bonnie, bonnie_window)) = found {
+            if bonnie.state != sample.state {
+                transitions.send(BonnieTransition {
+                    bonnie: entity,
+                    from: bonnie.state.clone(),
+                    to: sample.state.clone(),
+                });
+                bonnie.state = sample.state.clone();
+            }
+
+            if let Ok(mut window) = window_query.get_mut(bonnie_window.0) {
+                window.position = WindowPosition::At(sample.position);
+            }
+        }
+
+        replay.next += 1;
+    }
+
+    if replay.next >= replay.samples.len() {
+        info!("Replay finished, back to normal.");
+        commands.remove_resource::<Replay>();
+    }
+}
@@ -0,0 +1,70 @@
+//! The Scratch state.
+//!
+//! She leaves a scratch mark where she's sitting, in a window that clicks go
+//! straight through.
+
+use bevy::{prelude::*, render::view::RenderLayers};
+
+use crate::{
+    bonnie::{BonnieWindow, StateMachine},
+    stacking::Stacking,
+    window::PetWindow,
+};
+
+use super::bonnie_state::{
+    BonnieStateDiscriminants, BonnieTransition, OwnedBy, Sprites, StateSystems, entered,
+};
+
+const SCRATCH_LAYER: usize = 45;
+
+pub struct ScratchPlugin;
+
+impl Plugin for ScratchPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_scratch_sprite)
+            .add_systems(PreUpdate, create_scratch.in_set(StateSystems::Enter));
+    }
+}
+
+#[derive(Component)]
+pub struct ScratchWindow;
+
+/// Shared by every scratch window, like the poop sprite.
+fn setup_scratch_sprite(mut commands: Commands, sprites: Sprites) {
+    // get the sprite
+    let mut scratch_sprite = Sprite::from_image(sprites.load("BonScratch.png"));
+    scratch_sprite.custom_size = Some(Vec2::new(60.0, 60.0));
+
+    // add to scratch render layer
+    commands.spawn((scratch_sprite, RenderLayers::layer(SCRATCH_LAYER)));
+}
+
+fn create_scratch(
+    mut commands: Commands,
+    mut transitions: EventReader<BonnieTransition>,
+    mut bonnie_query: Query<(&BonnieWindow, &mut StateMachine)>,
+    window_query: Query<&Window>,
+    stacking: Res<Stacking>,
+) {
+    for bonnie in entered(&mut transitions, BonnieStateDiscriminants::Scratch) {
+        let Ok((bonnie_window, mut machine)) = bonnie_query.get_mut(bonnie) else {
+            continue;
+        };
+
+        let Ok(pos) = window_query.get(bonnie_window.0).map(|w| w.position) else {
+            continue;
+        };
+
+        PetWindow::new(
+            "Scratch!",
+            Vec2::splat(60.0),
+            RenderLayers::layer(SCRATCH_LAYER),
+        )
+        .at(pos)
+        .click_through()
+        .spawn(&mut commands, *stacking, (ScratchWindow, OwnedBy(bonnie)));
+
+        // finish state
+        machine.finish();
+    }
+}
@@ -0,0 +1,205 @@
+//! Pausing Bonnie while the screen is locked.
+//!
+//! The lock state is polled from the OS on a background thread. Where that
+//! isn't possible, the cursor not moving for a long time counts as locked.
+
+use std::{
+    process::Command,
+    sync::{
+        Arc,
+        atomic::{AtomicU8, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use bevy::prelude::*;
+
+use crate::bonnie::{BlockReason, StateMachine};
+
+use super::global_cursor::{CursorTracking, GlobalCursorPosition};
+
+/// How often the OS is asked whether the screen is locked.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// How long without the cursor moving before assuming the screen is locked.
+const INACTIVITY_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+/// How long without the cursor moving before the user counts as away.
+const AWAY_TIMEOUT: Duration = Duration::from_secs(60);
+
+const UNKNOWN: u8 = 0;
+const UNLOCKED: u8 = 1;
+const LOCKED: u8 = 2;
+
+pub struct ScreenLockPlugin;
+
+impl Plugin for ScreenLockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScreenLock>()
+            .add_systems(Startup, spawn_lock_poller)
+            .add_systems(Update, (track_activity, handle_screen_lock).chain());
+    }
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct ScreenLock {
+    /// Whether Bonnie is currently paused for the lock screen.
+    pub locked: bool,
+    /// Latest answer from the OS poller.
+    native: Arc<AtomicU8>,
+    /// When the cursor last moved.
+    last_activity: Duration,
+    last_cursor: Option<Vec2>,
+}
+
+impl ScreenLock {
+    /// Whether the cursor's been left alone long enough at `now` that nobody
+    /// seems to be using the computer, well short of it counting as locked.
+    pub fn user_away(&self, now: Duration) -> bool {
+        now.saturating_sub(self.last_activity) > AWAY_TIMEOUT
+    }
+}
+
+/// Marks sounds paused because of the lock screen, so only they get resumed.
+#[derive(Component)]
+struct PausedForLock;
+
+fn spawn_lock_poller(screen_lock: Res<ScreenLock>) {
+    let native = screen_lock.native.clone();
+
+    let spawned = thread::Builder::new()
+        .name("screen-lock-poller".into())
+        .spawn(move || {
+            loop {
+                let state = match is_screen_locked() {
+                    Some(true) => LOCKED,
+                    Some(false) => UNLOCKED,
+                    None => UNKNOWN,
+                };
+                native.store(state, Ordering::Relaxed);
+
+                // no point polling a platform that can't tell us
+                if state == UNKNOWN {
+                    info!("Screen lock detection unavailable, using inactivity instead.");
+                    return;
+                }
+
+                thread::sleep(POLL_INTERVAL);
+            }
+        });
+
+    if let Err(err) = spawned {
+        warn!("Couldn't start screen lock poller: {}", err);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn is_screen_locked() -> Option<bool> {
+    let session = std::env::var("XDG_SESSION_ID").ok()?;
+    let output = Command::new("loginctl")
+        .args(["show-session", &session, "-p", "LockedHint", "--value"])
+        .output()
+        .ok()?;
+
+    match String::from_utf8_lossy(&output.stdout).trim() {
+        "yes" => Some(true),
+        "no" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn is_screen_locked() -> Option<bool> {
+    let output = Command::new("ioreg")
+        .args(["-n", "Root", "-d1"])
+        .output()
+        .ok()?;
+
+    let locked = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .any(|line| line.contains("\"IOConsoleLocked\" = Yes"));
+    Some(locked)
+}
+
+/// Keeps a console window from flashing up each time a command's run.
+#[cfg(target_os = "windows")]
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+#[cfg(target_os = "windows")]
+fn is_screen_locked() -> Option<bool> {
+    use std::os::windows::process::CommandExt;
+
+    // the lock screen is drawn by LogonUI
+    let output = Command::new("tasklist")
+        .args(["/FI", "IMAGENAME eq LogonUI.exe", "/NH"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok()?;
+
+    Some(String::from_utf8_lossy(&output.stdout).contains("LogonUI.exe"))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn is_screen_locked() -> Option<bool> {
+    None
+}
+
+fn track_activity(
+    mut screen_lock: ResMut<ScreenLock>,
+    cursor_pos: Res<GlobalCursorPosition>,
+    time: Res<Time>,
+) {
+    if cursor_pos.0 != screen_lock.last_cursor {
+        screen_lock.last_cursor = cursor_pos.0;
+        screen_lock.last_activity = time.elapsed();
+    }
+}
+
+fn handle_screen_lock(
+    mut commands: Commands,
+    mut screen_lock: ResMut<ScreenLock>,
+    mut machines: Query<&mut StateMachine>,
+    sinks: Query<(Entity, &AudioSink, Has<PausedForLock>)>,
+    cursor_tracking: Res<CursorTracking>,
+    time: Res<Time>,
+) {
+    let locked = match screen_lock.native.load(Ordering::Relaxed) {
+        LOCKED => true,
+        UNLOCKED => false,
+        // a cursor that can't be found never moves
+        _ if !cursor_tracking.available => false,
+        _ => time.elapsed().saturating_sub(screen_lock.last_activity) > INACTIVITY_TIMEOUT,
+    };
+
+    if locked == screen_lock.locked {
+        return;
+    }
+    screen_lock.locked = locked;
+
+    if locked {
+        info!("Screen locked, pausing Bonnie.");
+
+        for mut machine in &mut machines {
+            machine.block_for(BlockReason::ScreenLock);
+        }
+
+        for (entity, sink, _) in &sinks {
+            if !sink.is_paused() {
+                sink.pause();
+                commands.entity(entity).insert(PausedForLock);
+            }
+        }
+    } else {
+        info!("Screen unlocked, resuming Bonnie.");
+
+        for mut machine in &mut machines {
+            machine.unblock_for(BlockReason::ScreenLock);
+        }
+
+        for (entity, sink, paused_for_lock) in &sinks {
+            if paused_for_lock {
+                sink.play();
+                commands.entity(entity).remove::<PausedForLock>();
+            }
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! Saving a picture of Bonnie for sharing.
+//!
+//! Her windows are transparent and separate, so rather than grabbing the whole
+//! desktop, each window's camera is drawn again into an offscreen image and
+//! saved as a PNG with the transparency kept. That's whatever frame she's on at
+//! the time, mid-animation or not.
+//!
+//! Snapshots go in the OS's pictures directory, named after when they were
+//! taken, with one file per window.
+
+use std::fs;
+
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+        view::{
+            RenderLayers,
+            screenshot::{Screenshot, ScreenshotCaptured},
+        },
+    },
+    window::WindowRef,
+};
+use chrono::Local;
+
+use crate::{
+    bonnie::{Bonnie, BonnieWindow},
+    window::Transient,
+};
+
+use super::control::{Action, KeyBindings};
+
+pub struct SnapshotPlugin;
+
+impl Plugin for SnapshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SnapshotConfig>()
+            .add_systems(Update, take_snapshot);
+    }
+}
+
+#[derive(Resource, Default, Debug, Clone)]
+pub struct SnapshotConfig {
+    /// Whether the other windows she has open, like poops or speech bubbles,
+    /// are saved alongside her.
+    pub windows: bool,
+}
+
+/// The windows that go in a snapshot.
+#[derive(SystemParam)]
+struct SnapshotWindows<'w, 's> {
+    config: Res<'w, SnapshotConfig>,
+    bonnie_query: Query<'w, 's, (&'static Bonnie, &'static BonnieWindow)>,
+    transient_query: Query<'w, 's, Entity, (With<Window>, With<Transient>)>,
+}
+
+impl SnapshotWindows<'_, '_> {
+    /// Each window, named for its file.
+    fn named(&self) -> Vec<(String, Entity)> {
+        let mut windows: Vec<(String, Entity)> = self
+            .bonnie_query
+            .iter()
+            .map(|(bonnie, bonnie_window)| (format!("bonnie-{}", bonnie.index), bonnie_window.0))
+            .collect();
+        if self.config.windows {
+            windows.extend(
+                self.transient_query
+                    .iter()
+                    .enumerate()
+                    .map(|(i, window)| (format!("window-{}", i), window)),
+            );
+        }
+
+        windows
+    }
+}
+
+fn take_snapshot(
+    mut commands: Commands,
+    key_input: Res<ButtonInput<KeyCode>>,
+    keys: Res<KeyBindings>,
+    windows: SnapshotWindows,
+    window_query: Query<&Window>,
+    camera_query: Query<(&Camera, &Transform, Option<&RenderLayers>)>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    if !keys.just_pressed(&key_input, Action::Snapshot) {
+        return;
+    }
+
+    let Some(dir) = dirs::picture_dir().or_else(dirs::home_dir) else {
+        warn!("Nowhere to save a snapshot of Bonnie to.");
+        return;
+    };
+    if let Err(err) = fs::create_dir_all(&dir) {
+        warn!("Couldn't create {} for snapshots: {}", dir.display(), err);
+        return;
+    }
+
+    let taken = Local::now().format("%Y-%m-%d_%H-%M-%S");
+    for (name, window_entity) in windows.named() {
+        let Ok(window) = window_query.get(window_entity) else {
+            continue;
+        };
+
+        // the camera already drawing into the window, to draw the same again
+        let Some((_, transform, layers)) = camera_query.iter().find(|(camera, ..)| {
+            matches!(
+                camera.target,
+                RenderTarget::Window(WindowRef::Entity(target)) if target == window_entity
+            )
+        }) else {
+            continue;
+        };
+
+        let size = window.physical_size();
+        let mut image = Image::new_fill(
+            Extent3d {
+                width: size.x.max(1),
+                height: size.y.max(1),
+                ..default()
+            },
+            TextureDimension::D2,
+            &[0; 4],
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::default(),
+        );
+        image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING
+            | TextureUsages::COPY_SRC
+            | TextureUsages::COPY_DST
+            | TextureUsages::RENDER_ATTACHMENT;
+        let image = images.add(image);
+
+        let camera = commands
+            .spawn((
+                Camera2d,
+                Camera {
+                    target: RenderTarget::Image(image.clone()),
+                    clear_color: ClearColorConfig::Custom(Color::NONE),
+                    ..default()
+                },
+                // full resolution, as an image has no scale factor of its own
+                OrthographicProjection {
+                    scale: 1.0 / window.scale_factor(),
+                    ..OrthographicProjection::default_2d()
+                },
+                *transform,
+                layers.cloned().unwrap_or_default(),
+            ))
+            .id();
+
+        let path = dir.join(format!("bonnie-buddy_{}_{}.png", taken, name));
+        commands.spawn(Screenshot::image(image.clone())).observe(
+            move |trigger: Trigger<ScreenshotCaptured>,
+                  mut commands: Commands,
+                  mut images: ResMut<Assets<Image>>| {
+                // done with the image it was drawn into
+                commands.entity(camera).despawn();
+                images.remove(&image);
+
+                let saved = match trigger.event().0.clone().try_into_dynamic() {
+                    Ok(image) => image.to_rgba8().save(&path).map_err(|err| err.to_string()),
+                    Err(err) => Err(err.to_string()),
+                };
+
+                match saved {
+                    Ok(()) => info!("Saved a snapshot of Bonnie to {}.", path.display()),
+                    Err(err) => warn!("Couldn't save a snapshot to {}: {}", path.display(), err),
+                }
+            },
+        );
+    }
+}
@@ -0,0 +1,60 @@
+//! Sounds for going into a state.
+//!
+//! Any state can be given a sound in the config, played as she goes into it,
+//! panned to where she is and with a cooldown so quick changes don't pile up.
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::bonnie::BonnieWindow;
+
+use super::{
+    audio::{PlaySound, Sound, Sounds, audio_enabled},
+    bonnie_state::{BonnieStateDiscriminants, BonnieTransition, StateSystems, window_pan},
+    monitor::MonitorInfo,
+};
+
+pub struct StateSoundsPlugin;
+
+impl Plugin for StateSoundsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StateSounds>().add_systems(
+            PreUpdate,
+            play_state_sounds
+                .run_if(audio_enabled)
+                .in_set(StateSystems::Enter),
+        );
+    }
+}
+
+/// Sounds played as she goes into a state, by asset path. States without
+/// one stay silent.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct StateSounds(pub HashMap<BonnieStateDiscriminants, String>);
+
+/// Plays whatever sound's set for each state she goes into.
+fn play_state_sounds(
+    mut transitions: EventReader<BonnieTransition>,
+    state_sounds: Res<StateSounds>,
+    bonnie_query: Query<&BonnieWindow>,
+    window_query: Query<&Window>,
+    monitor_info: Res<MonitorInfo>,
+    mut sounds: Sounds,
+) {
+    for transition in transitions.read() {
+        let state = BonnieStateDiscriminants::from(&transition.to);
+        let Some(path) = state_sounds.0.get(&state) else {
+            continue;
+        };
+
+        if !sounds.try_play(Sound::State(state)) {
+            continue;
+        }
+
+        let pan = bonnie_query
+            .get(transition.bonnie)
+            .and_then(|bonnie_window| window_query.get(bonnie_window.0))
+            .map_or(0.0, |window| window_pan(window, &monitor_info));
+
+        sounds.play(PlaySound::new(path.clone()).panned(pan));
+    }
+}
@@ -0,0 +1,533 @@
+//! The Teaching state.
+//!
+//! She puts on her glasses and brings out a tip, either a picture or some text
+//! in a speech bubble in the user's language, in a window that follows along
+//! below her. She keeps teaching until the tip's clicked away, which she
+//! doesn't much like.
+
+use std::path::Path;
+
+use bevy::{
+    asset::io::file::FileAssetReader,
+    ecs::system::SystemParam,
+    input::mouse::MouseButtonInput,
+    prelude::*,
+    render::view::RenderLayers,
+    text::{LineBreak, TextBounds, TextLayoutInfo},
+};
+use rand::{Rng, prelude::IndexedRandom};
+
+use crate::{
+    bonnie::{Bonnie, BonnieWindow, StateMachine},
+    stacking::Stacking,
+    window::PetWindow,
+};
+
+use super::{
+    bonnie_state::{
+        BonnieState, BonnieStateDiscriminants, BonnieTransition, GlobalRng, Interrupts,
+        MovementConfig, OwnedBy, SpeedConfig, Sprites, StateSystems, calculate_movement_speed,
+        despawn_window, entered, layer_for, left_clicked,
+    },
+    monitor::MonitorInfo,
+    mood::WindowsClosed,
+};
+
+/// Each Bonnie's tip goes on her own layer from here.
+const TEACH_LAYER: usize = 43;
+const NERD_LAYER: usize = 46;
+const TIP_BUBBLE_WIDTH: f32 = 260.0;
+const TIP_BUBBLE_PADDING: f32 = 12.0;
+/// Longest side of a tip image's window, the other side following its aspect ratio.
+const TIP_IMAGE_MAX: f32 = 300.0;
+
+pub struct TeachingPlugin;
+
+impl Plugin for TeachingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TeachingConfig>()
+            .init_resource::<Tips>()
+            .add_systems(Startup, (setup_nerd_sprite, load_tips))
+            .add_systems(PreUpdate, setup_teaching.in_set(StateSystems::Enter))
+            .add_systems(Update, close_teach_windows.in_set(StateSystems::Input))
+            .add_systems(
+                Update,
+                (handle_teaching, fit_tip_bubbles, fit_tip_images)
+                    .chain()
+                    .in_set(StateSystems::Behave),
+            );
+    }
+}
+
+/// How the Teaching state shows its tips.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct TeachingConfig {
+    /// Render tips from text in a speech bubble instead of using the images.
+    pub text_tips: bool,
+    /// Locale to pick tips for instead of the system's, e.g. `fr` or `en-GB`.
+    pub locale: Option<String>,
+}
+
+/// Something a Bonnie has to say next time she teaches, instead of a tip.
+#[derive(Component, Debug, Clone)]
+pub struct TeachingMessage(pub String);
+
+/// Has Bonnies drop what they're doing to teach a message, for anything with
+/// news for the user.
+#[derive(SystemParam)]
+pub struct Messengers<'w, 's> {
+    commands: Commands<'w, 's>,
+    interrupts: Interrupts<'w, 's>,
+}
+
+impl Messengers<'_, '_> {
+    /// Sends `entity` to teach `message`, starting her lesson over with it if
+    /// she's already teaching so the old tip's closed first.
+    pub fn bring(
+        &mut self,
+        entity: Entity,
+        bonnie: &mut Bonnie,
+        machine: &mut StateMachine,
+        message: String,
+    ) {
+        self.commands
+            .entity(entity)
+            .insert(TeachingMessage(message));
+        self.interrupts
+            .restart_to(entity, bonnie, machine, BonnieState::Teaching);
+    }
+}
+
+/// Tip text for the speech bubble.
+#[derive(Resource, Default, Debug)]
+struct Tips(Vec<String>);
+
+#[derive(Component)]
+pub struct TeachWindow;
+
+#[derive(Component)]
+pub struct NerdWindow;
+
+fn handle_teaching(
+    mut teach_window: Query<(&mut Window, &OwnedBy), With<TeachWindow>>,
+    bonnie_windows: Query<&Window, Without<TeachWindow>>,
+    bonnie_query: Query<&BonnieWindow>,
+    time: Res<Time>,
+    monitor_info: Res<MonitorInfo>,
+    movement_config: Res<MovementConfig>,
+    speed_config: Res<SpeedConfig>,
+) {
+    for (mut window, owner) in &mut teach_window {
+        // get bonnies position
+        let Ok(bonnie_window) = bonnie_query.get(owner.0) else {
+            continue;
+        };
+
+        let Ok(bonnie_window) = bonnie_windows.get(bonnie_window.0) else {
+            continue;
+        };
+        let bonnie_pos = match bonnie_window.position {
+            WindowPosition::At(pos) => pos,
+            _ => IVec2::ZERO,
+        };
+
+        let target = bonnie_pos
+            + teach_offset(
+                bonnie_window.physical_size().as_ivec2(),
+                window.physical_size().as_ivec2(),
+            );
+
+        // get the current teach position
+        let current_pos = match window.position {
+            WindowPosition::At(pos) => pos,
+            _ => IVec2::ZERO,
+        };
+
+        let Some(monitor) = monitor_info.active() else {
+            continue;
+        };
+
+        // get direction and delta
+        let direction = (target - current_pos).as_vec2().normalize();
+        let speed = calculate_movement_speed(
+            monitor.size,
+            &BonnieState::Teaching,
+            movement_config.speed_multiplier,
+            &speed_config,
+        );
+        let delta = direction * speed * (time.delta_secs_f64() as f32);
+
+        // calculate remaining
+        let remaining_vector = target - current_pos;
+        let remaining_length = remaining_vector.as_vec2().length();
+        let step_length = delta.length();
+
+        // only step if needed
+        if remaining_length <= step_length {
+            window.position = WindowPosition::At(target);
+        } else {
+            window.position = WindowPosition::At(current_pos + delta.round().as_ivec2());
+        }
+    }
+}
+
+/// Her tip and glasses windows, and whatever's drawn into them.
+#[derive(SystemParam)]
+struct TeachWindows<'w, 's> {
+    teach_query: Query<'w, 's, &'static OwnedBy, With<TeachWindow>>,
+    nerd_query: Query<'w, 's, (Entity, &'static OwnedBy), With<NerdWindow>>,
+    render_layer_query: Query<'w, 's, (Entity, &'static RenderLayers)>,
+    camera_query: Query<'w, 's, (Entity, &'static Camera)>,
+}
+
+impl TeachWindows<'_, '_> {
+    /// The Bonnie teaching with `window`, if it's one of her tips.
+    fn owner(&self, window: Entity) -> Option<Entity> {
+        self.teach_query.get(window).ok().map(|owned_by| owned_by.0)
+    }
+
+    /// Closes the tip `window` and `owner`'s glasses.
+    fn close(&self, commands: &mut Commands, window: Entity, owner: Entity) {
+        despawn_window(commands, window, &self.camera_query);
+
+        for (nerd_window, nerd_owner) in &self.nerd_query {
+            if nerd_owner.0 == owner {
+                despawn_window(commands, nerd_window, &self.camera_query);
+            }
+        }
+    }
+
+    /// Clears everything drawn on `layer`, ready for her next tip.
+    fn clear_layer(&self, commands: &mut Commands, layer: &RenderLayers) {
+        for (entity, render_layers) in &self.render_layer_query {
+            if render_layers == layer {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
+
+/// Closes a clicked tip along with her glasses, ending her lesson.
+fn close_teach_windows(
+    mut commands: Commands,
+    mut mouse_events: EventReader<MouseButtonInput>,
+    teach_windows: TeachWindows,
+    mut bonnie_query: Query<(&Bonnie, &mut StateMachine)>,
+    mut windows_closed: WindowsClosed,
+) {
+    for window in left_clicked(&mut mouse_events) {
+        let Some(owner) = teach_windows.owner(window) else {
+            continue;
+        };
+
+        teach_windows.close(&mut commands, window, owner);
+
+        // she doesn't like being cut off
+        windows_closed.on_her();
+
+        if let Ok((bonnie, mut machine)) = bonnie_query.get_mut(owner) {
+            // finish state machine
+            machine.finish();
+
+            teach_windows.clear_layer(&mut commands, &layer_for(TEACH_LAYER, bonnie.index));
+        }
+    }
+}
+
+/// Shared by every nerd window, like the poop sprite.
+fn setup_nerd_sprite(mut commands: Commands, sprites: Sprites) {
+    // get the sprite
+    let mut nerd_sprite = Sprite::from_image(sprites.load("BonNerd.png"));
+    nerd_sprite.custom_size = Some(Vec2::new(35.0, 35.0));
+
+    // add to nerd render layer
+    commands.spawn((nerd_sprite, RenderLayers::layer(NERD_LAYER)));
+}
+
+/// Picks what she teaches when she hasn't been given something to say.
+#[derive(SystemParam)]
+struct Lessons<'w> {
+    teaching_config: Res<'w, TeachingConfig>,
+    tips: Res<'w, Tips>,
+    asset_server: Res<'w, AssetServer>,
+    rng: ResMut<'w, GlobalRng>,
+}
+
+impl Lessons<'_> {
+    /// A random text tip, if they're on.
+    fn tip(&mut self) -> Option<&String> {
+        if self.teaching_config.text_tips {
+            self.tips.0.choose(&mut self.rng.0)
+        } else {
+            None
+        }
+    }
+
+    /// A random tip image.
+    fn image(&mut self) -> Handle<Image> {
+        self.asset_server
+            .load(random_education_image(&mut self.rng.0))
+    }
+}
+
+fn setup_teaching(
+    mut commands: Commands,
+    mut transitions: EventReader<BonnieTransition>,
+    mut lessons: Lessons,
+    mut bonnie_query: Query<(
+        &Bonnie,
+        &BonnieWindow,
+        &mut StateMachine,
+        Option<&TeachingMessage>,
+    )>,
+    window_query: Query<&Window>,
+    stacking: Res<Stacking>,
+) {
+    for bonnie_entity in entered(&mut transitions, BonnieStateDiscriminants::Teaching) {
+        let Ok((bonnie, bonnie_window, mut machine, message)) = bonnie_query.get_mut(bonnie_entity)
+        else {
+            continue;
+        };
+
+        info!("Blocking state machine...");
+        machine.block();
+
+        let pos = WindowPosition::At(IVec2::new(-1000, 300));
+        let teach_layer = layer_for(TEACH_LAYER, bonnie.index);
+
+        // pick the tip, the bubble gets resized once its text is laid out
+        let tip = if let Some(message) = message {
+            commands.entity(bonnie_entity).remove::<TeachingMessage>();
+            Some(message.0.clone())
+        } else {
+            lessons.tip().cloned()
+        };
+        let size = match tip {
+            Some(_) => Vec2::new(TIP_BUBBLE_WIDTH, TIP_BUBBLE_WIDTH / 2.0),
+            None => Vec2::splat(TIP_IMAGE_MAX),
+        };
+
+        let teach_window = PetWindow::new("Education!", size, teach_layer.clone())
+            .at(pos)
+            .spawn(
+                &mut commands,
+                *stacking,
+                (TeachWindow, OwnedBy(bonnie_entity)),
+            )
+            .window;
+
+        if let Some(tip) = tip {
+            spawn_tip_bubble(&mut commands, teach_window, &tip, teach_layer);
+        } else {
+            // get the sprite
+            let mut teach_sprite = Sprite::from_image(lessons.image());
+            teach_sprite.custom_size = Some(size);
+
+            // spawn the sprite on the teach layer, hidden until it's loaded
+            // and the window's been sized to it
+            commands.spawn((
+                teach_sprite,
+                Visibility::Hidden,
+                TipImage {
+                    window: teach_window,
+                },
+                teach_layer,
+            ));
+        }
+
+        // get bonnies position
+        let (bonnie_pos, bonnie_size) = match window_query.get(bonnie_window.0) {
+            Ok(window) => (
+                match window.position {
+                    WindowPosition::At(pos) => pos,
+                    _ => IVec2::ZERO,
+                },
+                window.physical_size().as_ivec2(),
+            ),
+            Err(_) => (IVec2::ZERO, IVec2::ZERO),
+        };
+
+        let nerd_pos = WindowPosition::At(bonnie_pos + nerd_offset(bonnie_size));
+
+        PetWindow::new(
+            "Education!",
+            Vec2::splat(35.0),
+            RenderLayers::layer(NERD_LAYER),
+        )
+        .at(nerd_pos)
+        .spawn(
+            &mut commands,
+            *stacking,
+            (NerdWindow, OwnedBy(bonnie_entity)),
+        );
+    }
+}
+
+/// Where a teach window of `teach_size` goes relative to a Bonnie window of
+/// `bonnie_size`, just below her and mostly off to her left.
+fn teach_offset(bonnie_size: IVec2, teach_size: IVec2) -> IVec2 {
+    IVec2::new(bonnie_size.x / 2 - teach_size.x * 9 / 10, bonnie_size.y)
+}
+
+/// Where the nerd glasses window goes relative to a Bonnie window of
+/// `bonnie_size`, towards her bottom right.
+fn nerd_offset(bonnie_size: IVec2) -> IVec2 {
+    bonnie_size * 7 / 10
+}
+
+/// Tip text that sizes its speech bubble and window to fit.
+#[derive(Component)]
+struct TipBubble {
+    window: Entity,
+    background: Entity,
+}
+
+fn spawn_tip_bubble(commands: &mut Commands, window: Entity, tip: &str, layer: RenderLayers) {
+    let text_width = TIP_BUBBLE_WIDTH - TIP_BUBBLE_PADDING * 2.0;
+
+    let background = commands
+        .spawn((
+            Sprite::from_color(Color::WHITE, Vec2::splat(TIP_BUBBLE_WIDTH)),
+            layer.clone(),
+        ))
+        .id();
+
+    commands.spawn((
+        Text2d::new(tip),
+        TextFont::from_font_size(16.0),
+        TextColor(Color::BLACK),
+        TextLayout::new(JustifyText::Center, LineBreak::WordBoundary),
+        TextBounds::new_horizontal(text_width),
+        Transform::from_xyz(0.0, 0.0, 1.0),
+        TipBubble { window, background },
+        layer,
+    ));
+}
+
+fn fit_tip_bubbles(
+    bubbles: Query<(&TipBubble, &TextLayoutInfo), Changed<TextLayoutInfo>>,
+    mut windows: Query<&mut Window>,
+    mut sprites: Query<&mut Sprite>,
+) {
+    for (bubble, layout) in &bubbles {
+        let size = Vec2::new(TIP_BUBBLE_WIDTH, layout.size.y + TIP_BUBBLE_PADDING * 2.0).ceil();
+
+        if let Ok(mut window) = windows.get_mut(bubble.window) {
+            resize_fixed(&mut window, size);
+        }
+
+        if let Ok(mut sprite) = sprites.get_mut(bubble.background) {
+            sprite.custom_size = Some(size);
+        }
+    }
+}
+
+/// A tip image still waiting to load, so its window can take its shape.
+#[derive(Component)]
+struct TipImage {
+    window: Entity,
+}
+
+/// Sizes each tip image's window to the image's aspect ratio once it's loaded,
+/// rather than stretching it into a square.
+fn fit_tip_images(
+    mut commands: Commands,
+    mut tip_images: Query<(Entity, &TipImage, &mut Sprite, &mut Visibility)>,
+    mut windows: Query<&mut Window>,
+    images: Res<Assets<Image>>,
+) {
+    for (entity, tip_image, mut sprite, mut visibility) in &mut tip_images {
+        let Some(image) = images.get(&sprite.image) else {
+            continue;
+        };
+
+        let image_size = image.size_f32();
+        let size = (image_size * TIP_IMAGE_MAX / image_size.max_element().max(1.0)).ceil();
+
+        if let Ok(mut window) = windows.get_mut(tip_image.window) {
+            resize_fixed(&mut window, size);
+        }
+
+        sprite.custom_size = Some(size);
+        *visibility = Visibility::Inherited;
+        commands.entity(entity).remove::<TipImage>();
+    }
+}
+
+/// Resizes a window that can't be resized by hand.
+fn resize_fixed(window: &mut Window, size: Vec2) {
+    window.resolution.set(size.x, size.y);
+    window.resize_constraints = WindowResizeConstraints {
+        min_width: size.x,
+        min_height: size.y,
+        max_width: size.x,
+        max_height: size.y,
+    };
+}
+
+/// Reads the tips for the active locale, falling back to the English ones in
+/// `educational/text` when there's no `educational/text/<language>` for it.
+fn load_tips(mut tips: ResMut<Tips>, teaching_config: Res<TeachingConfig>) {
+    let dir = FileAssetReader::get_base_path().join("assets/educational/text");
+
+    let locale = teaching_config
+        .locale
+        .clone()
+        .or_else(sys_locale::get_locale)
+        .unwrap_or_else(|| "en".to_string());
+
+    // only the language matters, "en-GB" and "en_US" are both "en"
+    let language = locale
+        .split(['-', '_', '.'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+
+    tips.0 = read_tips(&dir.join(&language));
+    if tips.0.is_empty() {
+        debug!("No tips for {}, using English.", language);
+        tips.0 = read_tips(&dir);
+    }
+
+    info!("Loaded {} tips for locale {}.", tips.0.len(), locale);
+}
+
+/// Every non-empty `.txt` tip in `dir`, in file name order.
+fn read_tips(dir: &Path) -> Vec<String> {
+    let mut paths = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+            .collect::<Vec<_>>(),
+        Err(err) => {
+            debug!("Couldn't read tips from {}: {}", dir.display(), err);
+            return Vec::new();
+        }
+    };
+    paths.sort();
+
+    paths
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .map(|tip| tip.trim().to_string())
+        .filter(|tip| !tip.is_empty())
+        .collect()
+}
+
+fn random_education_image(rng: &mut impl Rng) -> String {
+    const IMAGES: &[&str] = &[
+        "educational/meme1.png",
+        "educational/meme2.png",
+        "educational/meme3.png",
+        "educational/meme4.png",
+        "educational/meme5.png",
+        "educational/text/tip1.png",
+        "educational/text/tip2.png",
+        "educational/text/tip3.png",
+        "educational/text/tip4.png",
+        "educational/text/tip5.png",
+        "educational/text/tip6.png",
+        "educational/text/tip7.png",
+    ];
+    IMAGES.choose(rng).unwrap().to_string()
+}
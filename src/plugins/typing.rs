@@ -0,0 +1,169 @@
+//! Bonnie noticing you typing.
+//!
+//! Key presses over the last few seconds give a typing rate. While you're
+//! typing away she perks up and wanders more, and every so often comes over
+//! to the cursor to see what you're up to. Only keys pressed while one of her
+//! windows has focus count, since there's no global keyboard hook.
+
+use std::{collections::VecDeque, time::Duration};
+
+use bevy::{
+    input::{ButtonState, keyboard::KeyboardInput},
+    prelude::*,
+};
+use rand::Rng;
+
+use crate::bonnie::{Bonnie, FEET_OFFSET, StateMachine};
+
+use super::{
+    bonnie_state::{BonnieState, BonnieStateDiscriminants, GlobalRng, Interrupts, StateRules},
+    global_cursor::GlobalCursorPosition,
+};
+
+/// How far back key presses count towards the typing rate.
+const TYPING_WINDOW_SECS: f32 = 3.0;
+/// Key presses per second that count as typing away.
+const ACTIVE_RATE: f32 = 3.0;
+/// Chance each second, once the cooldown's up, that she comes over to look.
+const REACTION_CHANCE: f64 = 0.05;
+
+pub struct TypingPlugin;
+
+impl Plugin for TypingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TypingConfig>()
+            .init_resource::<TypingActivity>()
+            .add_systems(
+                Update,
+                (track_typing, react_to_typing)
+                    .chain()
+                    .run_if(|config: Res<TypingConfig>| config.enabled),
+            );
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct TypingConfig {
+    /// Whether she notices typing at all.
+    pub enabled: bool,
+    /// Shortest time between her coming over to look.
+    pub cooldown: Duration,
+}
+
+impl Default for TypingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            cooldown: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+/// Recent key presses, for how fast you're typing.
+#[derive(Resource, Default, Debug)]
+pub struct TypingActivity {
+    /// When each recent key was pressed, in seconds since startup.
+    presses: VecDeque<f32>,
+    /// When she last came over to look.
+    last_reaction: Option<Duration>,
+}
+
+impl TypingActivity {
+    /// Key presses per second lately.
+    pub fn rate(&self) -> f32 {
+        self.presses.len() as f32 / TYPING_WINDOW_SECS
+    }
+
+    pub fn active(&self) -> bool {
+        self.rate() >= ACTIVE_RATE
+    }
+
+    /// How much more or less likely `state` gets, livelier while you're typing.
+    pub fn weight_scale(&self, state: BonnieStateDiscriminants) -> f32 {
+        if !self.active() {
+            return 1.0;
+        }
+
+        match state {
+            BonnieStateDiscriminants::Walking => 1.5,
+            BonnieStateDiscriminants::Idle => 0.7,
+            _ => 1.0,
+        }
+    }
+}
+
+fn track_typing(
+    mut key_events: EventReader<KeyboardInput>,
+    mut activity: ResMut<TypingActivity>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_secs();
+
+    for event in key_events.read() {
+        // held keys repeat, which isn't typing
+        if event.state == ButtonState::Pressed && !event.repeat {
+            activity.presses.push_back(now);
+        }
+    }
+
+    while activity
+        .presses
+        .front()
+        .is_some_and(|t| now - t > TYPING_WINDOW_SECS)
+    {
+        activity.presses.pop_front();
+    }
+}
+
+/// Now and then walks an idle Bonnie over to the cursor while you're typing.
+fn react_to_typing(
+    mut activity: ResMut<TypingActivity>,
+    config: Res<TypingConfig>,
+    mut bonnie_query: Query<(Entity, &mut Bonnie, &mut StateMachine)>,
+    mut interrupts: Interrupts,
+    cursor_pos: Res<GlobalCursorPosition>,
+    mut rng: ResMut<GlobalRng>,
+    rules: StateRules,
+    time: Res<Time>,
+) {
+    // she only comes over if she'd walk anywhere at the moment
+    if !activity.active() || !rules.allowed()(BonnieStateDiscriminants::Walking) {
+        return;
+    }
+
+    let now = time.elapsed();
+    let cooling_down = activity
+        .last_reaction
+        .is_some_and(|last| now.saturating_sub(last) < config.cooldown);
+    if cooling_down {
+        return;
+    }
+
+    let chance = (REACTION_CHANCE * time.delta_secs_f64()).clamp(0.0, 1.0);
+    if !rng.0.random_bool(chance) {
+        return;
+    }
+
+    let Some(cursor) = cursor_pos.0 else {
+        return;
+    };
+
+    let Some((entity, mut bonnie, mut machine)) = bonnie_query
+        .iter_mut()
+        .find(|(_, bonnie, _)| bonnie.state == BonnieState::Idle)
+    else {
+        return;
+    };
+
+    activity.last_reaction = Some(now);
+    info!("Bonnie's coming to see what you're typing.");
+
+    // stand just short of the cursor rather than on it
+    let target = cursor.as_ivec2() - FEET_OFFSET - IVec2::new(60, 0);
+    interrupts.interrupt_to(
+        entity,
+        &mut bonnie,
+        &mut machine,
+        BonnieState::Walking(target),
+    );
+}
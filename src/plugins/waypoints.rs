@@ -0,0 +1,188 @@
+//! Placing Bonnie's patrol route by clicking on the desktop.
+//!
+//! Pressing w (or whatever `waypoints` is bound to) dims the screen and each
+//! click adds a waypoint where she'll stand. Enter (or whatever
+//! `waypoints_save` is bound to) saves the route to the config file and Escape
+//! (or whatever `waypoints_cancel` is bound to) throws it away.
+
+use bevy::{
+    input::{ButtonState, mouse::MouseButtonInput},
+    prelude::*,
+    render::view::RenderLayers,
+};
+
+use crate::{bonnie::FEET_OFFSET, cli::Cli, config::Config, stacking::Stacking, window::PetWindow};
+
+use super::{
+    bonnie_state::PatrolRoute,
+    control::{Action, KeyBindings},
+    global_cursor::GlobalCursorPosition,
+    monitor::MonitorInfo,
+};
+
+/// Render layer the click catcher and markers are drawn on.
+const WAYPOINT_LAYER: usize = 37;
+/// Size of the marker shown for each waypoint.
+const MARKER_SIZE: f32 = 10.0;
+
+pub struct WaypointPlugin;
+
+impl Plugin for WaypointPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WaypointPlacement>().add_systems(
+            Update,
+            (start_placement, record_waypoints, finish_placement)
+                .chain()
+                .in_set(WaypointSystems),
+        );
+    }
+}
+
+/// Everything to do with placing waypoints, for ordering against.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WaypointSystems;
+
+/// The waypoints clicked so far, while placing a route.
+#[derive(Resource, Default, Debug)]
+pub struct WaypointPlacement {
+    pub active: bool,
+    points: Vec<Vec2>,
+}
+
+/// Everything shown while placing, so it can be put away in one go.
+#[derive(Component)]
+struct Placement;
+
+/// The fullscreen window clicks are caught by.
+#[derive(Component)]
+struct ClickCatcher;
+
+fn start_placement(
+    mut commands: Commands,
+    key_input: Res<ButtonInput<KeyCode>>,
+    keys: Res<KeyBindings>,
+    monitor_info: Res<MonitorInfo>,
+    mut placement: ResMut<WaypointPlacement>,
+) {
+    if placement.active || !keys.just_pressed(&key_input, Action::Waypoints) {
+        return;
+    }
+
+    let Some(monitor) = monitor_info.active() else {
+        return;
+    };
+
+    // over everything, dimming the screen a little, which also stops clicks
+    // going straight through
+    let catcher = PetWindow::new(
+        "Patrol route",
+        monitor.size.as_vec2(),
+        RenderLayers::layer(WAYPOINT_LAYER),
+    )
+    .at(WindowPosition::At(monitor.origin))
+    // a pixel per pixel, so clicks and markers line up with the cursor
+    .scale_factor_override(1.0)
+    .clear_color(Color::srgba(0.0, 0.0, 0.0, 0.25))
+    .spawn(&mut commands, Stacking::Top, (ClickCatcher, Placement));
+    commands.entity(catcher.camera).insert(Placement);
+
+    placement.active = true;
+    placement.points.clear();
+    info!(
+        "Placing patrol waypoints, {:?} to save or {:?} to cancel.",
+        keys.key(Action::WaypointsSave),
+        keys.key(Action::WaypointsCancel)
+    );
+}
+
+fn record_waypoints(
+    mut commands: Commands,
+    mut mouse_events: EventReader<MouseButtonInput>,
+    catcher_query: Query<&Window, With<ClickCatcher>>,
+    cursor_pos: Res<GlobalCursorPosition>,
+    mut placement: ResMut<WaypointPlacement>,
+) {
+    for event in mouse_events.read() {
+        if event.button != MouseButton::Left || event.state != ButtonState::Pressed {
+            continue;
+        }
+
+        let Ok(catcher) = catcher_query.get(event.window) else {
+            continue;
+        };
+
+        let Some(cursor) = cursor_pos.0 else {
+            continue;
+        };
+
+        // the camera's in the middle of the catcher with y going up, and the
+        // catcher covers whichever monitor she's on
+        let origin = match catcher.position {
+            WindowPosition::At(origin) => origin.as_vec2(),
+            _ => Vec2::ZERO,
+        };
+        let size = catcher.physical_size().as_vec2();
+        let on_catcher = cursor - origin;
+        let world = Vec2::new(on_catcher.x - size.x / 2.0, size.y / 2.0 - on_catcher.y);
+
+        commands.spawn((
+            Sprite::from_color(Color::srgb(1.0, 0.8, 0.0), Vec2::splat(MARKER_SIZE)),
+            Transform::from_translation(world.extend(0.0)),
+            RenderLayers::layer(WAYPOINT_LAYER),
+            Placement,
+        ));
+
+        placement.points.push(cursor);
+        info!("Added waypoint {} at {}.", placement.points.len(), cursor);
+    }
+}
+
+fn finish_placement(
+    mut commands: Commands,
+    key_input: Res<ButtonInput<KeyCode>>,
+    keys: Res<KeyBindings>,
+    placement_query: Query<Entity, With<Placement>>,
+    mut placement: ResMut<WaypointPlacement>,
+    mut patrol_route: ResMut<PatrolRoute>,
+    cli: Res<Cli>,
+) {
+    if !placement.active {
+        return;
+    }
+
+    let save = keys.just_pressed(&key_input, Action::WaypointsSave);
+    if !save && !keys.just_pressed(&key_input, Action::WaypointsCancel) {
+        return;
+    }
+
+    for entity in &placement_query {
+        commands.entity(entity).despawn_recursive();
+    }
+    placement.active = false;
+
+    if !save {
+        info!("Cancelled placing patrol waypoints.");
+        return;
+    }
+
+    let waypoints: Vec<IVec2> = placement
+        .points
+        .iter()
+        .map(|point| (*point - FEET_OFFSET.as_vec2()).round().as_ivec2())
+        .collect();
+
+    let route: Vec<[i32; 2]> = waypoints
+        .iter()
+        .map(|waypoint| waypoint.to_array())
+        .collect();
+    match Config::save_patrol_route(cli.config.as_deref(), &route) {
+        Ok(path) => info!(
+            "Saved {} patrol waypoints to {}.",
+            route.len(),
+            path.display()
+        ),
+        Err(err) => warn!("Couldn't save patrol route: {}", err),
+    }
+
+    patrol_route.waypoints = waypoints;
+}
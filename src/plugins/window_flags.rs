@@ -0,0 +1,245 @@
+//! Keeping Bonnie's windows borderless and stacked where they should be.
+//!
+//! Some Linux window managers forget a window's always-on-top or undecorated
+//! state after a workspace switch or restart, leaving her with a title bar or
+//! behind everything else. Every so often her windows are checked against
+//! what the window system says they actually look like, and set again only if
+//! they've drifted.
+//!
+//! - Linux: the X11 properties the window manager keeps, so not on native
+//!   Wayland.
+//! - macOS and Windows: only decorations are checked, as the level can't be
+//!   read back.
+//!
+//! Below everything she's pinned to the desktop as well, on every workspace
+//! and out of the taskbar, where the platform allows it:
+//!
+//! - Linux: the sticky and skip taskbar and pager states, so again only X11.
+//! - Windows: kept out of the taskbar.
+
+use std::time::Duration;
+
+use bevy::{
+    ecs::entity::EntityHashSet, prelude::*, time::common_conditions::on_timer, window::WindowLevel,
+    winit::WinitWindows,
+};
+
+use crate::{bonnie::BonnieWindow, stacking::Stacking};
+
+/// How often her windows are checked for drift.
+const CHECK_INTERVAL: Duration = Duration::from_secs(3);
+
+pub struct WindowFlagsPlugin;
+
+impl Plugin for WindowFlagsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            reassert_window_flags.run_if(on_timer(CHECK_INTERVAL)),
+        );
+    }
+}
+
+/// What the window system says a window looks like, where it can tell.
+#[derive(Debug, Default)]
+struct ActualFlags {
+    decorated: Option<bool>,
+    level: Option<WindowLevel>,
+}
+
+fn reassert_window_flags(
+    winit_windows: NonSend<WinitWindows>,
+    bonnie_query: Query<&BonnieWindow>,
+    window_query: Query<&Window>,
+    stacking: Res<Stacking>,
+    mut pinned: Local<EntityHashSet>,
+) {
+    let pin = *stacking == Stacking::Bottom;
+
+    for bonnie_window in &bonnie_query {
+        let (Ok(window), Some(winit_window)) = (
+            window_query.get(bonnie_window.0),
+            winit_windows.get_window(bonnie_window.0),
+        ) else {
+            continue;
+        };
+
+        let actual = actual_flags(winit_window);
+
+        if actual
+            .decorated
+            .is_some_and(|decorated| decorated != window.decorations)
+        {
+            warn!("Bonnie's window got a title bar back, taking it off again.");
+            winit_window.set_decorations(window.decorations);
+        }
+
+        if let Some(level) = actual.level
+            && level != window.window_level
+        {
+            warn!(
+                "Bonnie's window dropped to {:?}, putting it back to {:?}.",
+                level, window.window_level
+            );
+            winit_window.set_window_level(winit_level(window.window_level));
+        }
+
+        if pin != pinned.contains(&bonnie_window.0) {
+            pin_to_desktop(winit_window, pin);
+            if pin {
+                pinned.insert(bonnie_window.0);
+            } else {
+                pinned.remove(&bonnie_window.0);
+            }
+        }
+    }
+}
+
+fn winit_level(level: WindowLevel) -> winit::window::WindowLevel {
+    match level {
+        WindowLevel::AlwaysOnBottom => winit::window::WindowLevel::AlwaysOnBottom,
+        WindowLevel::Normal => winit::window::WindowLevel::Normal,
+        WindowLevel::AlwaysOnTop => winit::window::WindowLevel::AlwaysOnTop,
+    }
+}
+
+/// The window's X11 id, or none on native Wayland.
+#[cfg(target_os = "linux")]
+fn x11_id(window: &winit::window::Window) -> Option<u32> {
+    use winit::raw_window_handle::{HasWindowHandle, RawWindowHandle};
+
+    match window.window_handle().ok()?.as_raw() {
+        RawWindowHandle::Xlib(handle) => Some(handle.window as u32),
+        RawWindowHandle::Xcb(handle) => Some(handle.window.get()),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn actual_flags(window: &winit::window::Window) -> ActualFlags {
+    // wayland doesn't let anyone read these back
+    x11_id(window).and_then(x11_flags).unwrap_or_default()
+}
+
+#[cfg(target_os = "linux")]
+fn x11_flags(window: u32) -> Option<ActualFlags> {
+    use x11rb::protocol::xproto::{AtomEnum, ConnectionExt};
+
+    let (conn, _) = x11rb::connect(None).ok()?;
+    let atom = |name: &str| -> Option<u32> {
+        Some(
+            conn.intern_atom(false, name.as_bytes())
+                .ok()?
+                .reply()
+                .ok()?
+                .atom,
+        )
+    };
+
+    // flags, functions, decorations, input mode and status, where the 2 flag
+    // says whether decorations are given at all
+    let motif = atom("_MOTIF_WM_HINTS")?;
+    let hints: Vec<u32> = conn
+        .get_property(false, window, motif, motif, 0, 5)
+        .ok()?
+        .reply()
+        .ok()?
+        .value32()
+        .map(Iterator::collect)
+        .unwrap_or_default();
+    let decorated = match hints.as_slice() {
+        [flags, _, decorations, ..] => flags & 2 == 0 || *decorations != 0,
+        // without hints the window manager decorates it
+        _ => true,
+    };
+
+    let above = atom("_NET_WM_STATE_ABOVE")?;
+    let below = atom("_NET_WM_STATE_BELOW")?;
+    let state: Vec<u32> = conn
+        .get_property(false, window, atom("_NET_WM_STATE")?, AtomEnum::ATOM, 0, 64)
+        .ok()?
+        .reply()
+        .ok()?
+        .value32()
+        .map(Iterator::collect)
+        .unwrap_or_default();
+    let level = if state.contains(&above) {
+        WindowLevel::AlwaysOnTop
+    } else if state.contains(&below) {
+        WindowLevel::AlwaysOnBottom
+    } else {
+        WindowLevel::Normal
+    };
+
+    Some(ActualFlags {
+        decorated: Some(decorated),
+        level: Some(level),
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn actual_flags(window: &winit::window::Window) -> ActualFlags {
+    ActualFlags {
+        decorated: Some(window.is_decorated()),
+        level: None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn pin_to_desktop(window: &winit::window::Window, pin: bool) {
+    if x11_id(window).and_then(|id| x11_pin(id, pin)).is_none() {
+        debug!("Couldn't pin Bonnie's window to the desktop.");
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn x11_pin(window: u32, pin: bool) -> Option<()> {
+    use x11rb::{
+        connection::Connection,
+        protocol::xproto::{ClientMessageEvent, ConnectionExt, EventMask},
+    };
+
+    let (conn, screen) = x11rb::connect(None).ok()?;
+    let root = conn.setup().roots.get(screen)?.root;
+    let atom = |name: &str| -> Option<u32> {
+        Some(
+            conn.intern_atom(false, name.as_bytes())
+                .ok()?
+                .reply()
+                .ok()?
+                .atom,
+        )
+    };
+
+    // a mapped window's state is changed by asking the window manager, which
+    // takes two states at a time, with 1 to add them and 0 to remove them
+    let wm_state = atom("_NET_WM_STATE")?;
+    let action = u32::from(pin);
+    for [first, second] in [
+        ["_NET_WM_STATE_STICKY", "_NET_WM_STATE_SKIP_TASKBAR"],
+        ["_NET_WM_STATE_SKIP_PAGER", ""],
+    ] {
+        let second = if second.is_empty() { 0 } else { atom(second)? };
+        let event =
+            ClientMessageEvent::new(32, window, wm_state, [action, atom(first)?, second, 1, 0]);
+        conn.send_event(
+            false,
+            root,
+            EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+            event,
+        )
+        .ok()?;
+    }
+
+    conn.flush().ok()
+}
+
+#[cfg(target_os = "windows")]
+fn pin_to_desktop(window: &winit::window::Window, pin: bool) {
+    use winit::platform::windows::WindowExtWindows;
+
+    window.set_skip_taskbar(pin);
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn pin_to_desktop(_window: &winit::window::Window, _pin: bool) {}
@@ -0,0 +1,67 @@
+//! Behaviour presets that can be switched between while she's running.
+
+use bevy::{prelude::*, utils::HashMap};
+use clap::ValueEnum;
+
+use crate::plugins::bonnie_state::{BehaviourConfig, BonnieStateDiscriminants};
+
+/// A bundle of behaviour settings and volume.
+#[derive(Resource, ValueEnum, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    #[default]
+    Normal,
+    /// Quieter and calmer, no chasing, teaching, birds or dancing.
+    Work,
+    /// Everything, often.
+    Play,
+}
+
+impl Profile {
+    pub fn behaviour(self) -> BehaviourConfig {
+        use BonnieStateDiscriminants::*;
+
+        match self {
+            Self::Normal => BehaviourConfig::default(),
+            Self::Work => BehaviourConfig {
+                weights: HashMap::from_iter([
+                    (Idle, 3.0),
+                    (Pooping, 0.5),
+                    (Chasing, 0.0),
+                    (Teaching, 0.0),
+                    (Meowing, 0.5),
+                    (Bird, 0.0),
+                    (Scratch, 0.5),
+                    (Dancing, 0.0),
+                ]),
+                timer_range: (5.0, 15.0),
+            },
+            Self::Play => BehaviourConfig {
+                weights: HashMap::from_iter([
+                    (Idle, 0.3),
+                    (Walking, 2.0),
+                    (Chasing, 2.0),
+                    (Meowing, 1.5),
+                    (Bird, 2.0),
+                    (Dancing, 2.0),
+                ]),
+                timer_range: (0.5, 2.5),
+            },
+        }
+    }
+
+    pub fn volume(self) -> f32 {
+        match self {
+            Self::Work => 0.5,
+            Self::Normal | Self::Play => 1.0,
+        }
+    }
+
+    /// The next profile when cycling through them.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Normal => Self::Work,
+            Self::Work => Self::Play,
+            Self::Play => Self::Normal,
+        }
+    }
+}
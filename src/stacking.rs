@@ -0,0 +1,39 @@
+//! Where Bonnie's windows sit relative to other windows.
+//!
+//! Below everything she's a wallpaper companion, living on the desktop behind
+//! whatever's open. She can still be dragged and petted wherever the desktop
+//! shows through, and on platforms that allow it her window's also pinned to
+//! every workspace and kept out of the taskbar, like part of the desktop.
+
+use bevy::{prelude::*, window::WindowLevel};
+use clap::ValueEnum;
+use serde::Deserialize;
+
+/// Whether Bonnie's windows stay above, among or below other windows.
+#[derive(Resource, ValueEnum, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Stacking {
+    #[default]
+    Top,
+    Normal,
+    Bottom,
+}
+
+impl Stacking {
+    pub fn level(self) -> WindowLevel {
+        match self {
+            Self::Top => WindowLevel::AlwaysOnTop,
+            Self::Normal => WindowLevel::Normal,
+            Self::Bottom => WindowLevel::AlwaysOnBottom,
+        }
+    }
+
+    /// The next stacking when cycling through them.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Top => Self::Normal,
+            Self::Normal => Self::Bottom,
+            Self::Bottom => Self::Top,
+        }
+    }
+}
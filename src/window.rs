@@ -0,0 +1,146 @@
+//! Spawning windows that match Bonnie's own.
+//!
+//! They're transparent and undecorated, stacked like the rest of her windows,
+//! and come with a camera drawing one render layer, so anything put on that
+//! layer shows up in the window.
+
+use bevy::{
+    prelude::*,
+    render::{camera::RenderTarget, view::RenderLayers},
+    window::{CursorOptions, PresentMode, WindowRef, WindowResolution},
+};
+
+use crate::{plugins::compositor::get_composite_mode, stacking::Stacking};
+
+/// A fixed size window and the camera drawing into it.
+#[derive(Debug, Clone)]
+pub struct PetWindow {
+    title: String,
+    size: Vec2,
+    layer: RenderLayers,
+    position: WindowPosition,
+    click_through: bool,
+    present_mode: PresentMode,
+    clear_color: ClearColorConfig,
+    scale_factor_override: Option<f32>,
+}
+
+/// A window or camera that only lives for a while, as opposed to Bonnie's
+/// own, so everything like it can be closed in one go on exit.
+#[derive(Component, Debug, Default)]
+pub struct Transient;
+
+/// The entities a [`PetWindow`] spawned.
+#[derive(Debug, Clone, Copy)]
+pub struct SpawnedPetWindow {
+    pub window: Entity,
+    pub camera: Entity,
+}
+
+impl PetWindow {
+    /// A window of `size` showing whatever's on `layer`.
+    pub fn new(title: impl Into<String>, size: Vec2, layer: RenderLayers) -> Self {
+        Self {
+            title: title.into(),
+            size,
+            layer,
+            position: WindowPosition::Automatic,
+            click_through: false,
+            present_mode: PresentMode::default(),
+            clear_color: ClearColorConfig::Default,
+            scale_factor_override: None,
+        }
+    }
+
+    pub fn at(mut self, position: WindowPosition) -> Self {
+        self.position = position;
+        self
+    }
+
+    /// Lets clicks go through to whatever's underneath.
+    pub fn click_through(mut self) -> Self {
+        self.click_through = true;
+        self
+    }
+
+    pub fn present_mode(mut self, present_mode: PresentMode) -> Self {
+        self.present_mode = present_mode;
+        self
+    }
+
+    /// What's shown behind the layer, instead of the usual see-through.
+    pub fn clear_color(mut self, color: Color) -> Self {
+        self.clear_color = ClearColorConfig::Custom(color);
+        self
+    }
+
+    /// Pins the scale factor, like a pixel per pixel for lining things up
+    /// with the cursor.
+    pub fn scale_factor_override(mut self, scale_factor: f32) -> Self {
+        self.scale_factor_override = Some(scale_factor);
+        self
+    }
+
+    /// Spawns the window with `bundle` on it, and its camera.
+    pub fn spawn(
+        self,
+        commands: &mut Commands,
+        stacking: Stacking,
+        bundle: impl Bundle,
+    ) -> SpawnedPetWindow {
+        let mut resolution = WindowResolution::from(self.size);
+        if let Some(scale_factor) = self.scale_factor_override {
+            resolution.set_scale_factor_override(Some(scale_factor));
+        }
+
+        let window = commands
+            .spawn((
+                Window {
+                    transparent: true,
+                    composite_alpha_mode: get_composite_mode(),
+                    decorations: false,
+                    resizable: false,
+                    has_shadow: false,
+                    titlebar_shown: false,
+                    titlebar_transparent: false,
+                    titlebar_show_buttons: false,
+                    titlebar_show_title: false,
+                    title: self.title,
+                    name: Some("bonnie.buddy".into()),
+                    resolution,
+                    resize_constraints: WindowResizeConstraints {
+                        min_width: self.size.x,
+                        min_height: self.size.y,
+                        max_width: self.size.x,
+                        max_height: self.size.y,
+                    },
+                    present_mode: self.present_mode,
+                    window_level: stacking.level(),
+                    position: self.position,
+                    cursor_options: CursorOptions {
+                        hit_test: !self.click_through,
+                        ..default()
+                    },
+                    ..default()
+                },
+                bundle,
+                Transient,
+            ))
+            .id();
+
+        let camera = commands
+            .spawn((
+                Camera2d,
+                Camera {
+                    target: RenderTarget::Window(WindowRef::Entity(window)),
+                    clear_color: self.clear_color,
+                    ..default()
+                },
+                self.layer,
+                Transient,
+            ))
+            .id();
+
+        SpawnedPetWindow { window, camera }
+    }
+}